@@ -1,15 +1,15 @@
-use crate::ParseCsvField;
+use std::borrow::Cow;
+
+use crate::{unescape_field_str, CsvDialect, CsvEncoding, Error, ErrorKind, ParseCsvField, Result};
 
 macro_rules! parse_primitive_type {
     ($ty:ty) => {
         impl<'buf> ParseCsvField<'buf> for $ty {
-            fn parse_csv_field(buffer: &'buf [u8]) -> std::result::Result<Self, ()>
+            fn parse_csv_field(buffer: &'buf [u8]) -> Result<Self>
             where
                 Self: 'buf,
             {
-                std::str::from_utf8(buffer.trim_ascii())
-                    .map_err(|_| ())
-                    .and_then(|s| s.parse().map_err(|_| ()))
+                Ok(std::str::from_utf8(buffer.trim_ascii())?.parse()?)
             }
         }
     };
@@ -25,17 +25,34 @@ parse_primitive_type!(u8);
 parse_primitive_type!(u16);
 parse_primitive_type!(u32);
 
+/// Returns the field as is. Fields with escaped quotes are rejected, because the result has
+/// to reference the buffer and can't be unescaped. Use `Cow<str>` or `String` for fields that
+/// may contain quotes. For the same reason, fields in other encodings than UTF-8 have to be
+/// ASCII.
 impl<'buf> ParseCsvField<'buf> for &'buf str {
-    fn parse_csv_field<'b>(buffer: &'buf [u8]) -> std::result::Result<Self, ()>
+    fn parse_csv_field<'b>(buffer: &'buf [u8]) -> Result<Self>
     where
         Self: 'buf,
     {
-        std::str::from_utf8(buffer).map_err(|_| ())
+        Self::parse_csv_field_with_dialect(buffer, &CsvDialect::default())
+    }
+
+    fn parse_csv_field_with_dialect(buffer: &'buf [u8], dialect: &CsvDialect) -> Result<Self>
+    where
+        Self: 'buf,
+    {
+        if dialect.encoding != CsvEncoding::Utf8 && !buffer.is_ascii() {
+            return Err(Error::new(ErrorKind::InvalidUtf8));
+        }
+        if buffer.windows(2).any(|pair| pair == [dialect.quote; 2]) {
+            return Err(Error::new(ErrorKind::EscapedQuote));
+        }
+        Ok(std::str::from_utf8(buffer)?)
     }
 }
 
 impl<'buf> ParseCsvField<'buf> for &'buf [u8] {
-    fn parse_csv_field(buffer: &'buf [u8]) -> std::result::Result<Self, ()>
+    fn parse_csv_field(buffer: &'buf [u8]) -> Result<Self>
     where
         Self: 'buf,
     {
@@ -43,13 +60,40 @@ impl<'buf> ParseCsvField<'buf> for &'buf [u8] {
     }
 }
 
+/// Only copies the field if it contains escaped quotes or has to be transcoded to UTF-8.
+impl<'buf> ParseCsvField<'buf> for Cow<'buf, str> {
+    fn parse_csv_field(buffer: &'buf [u8]) -> Result<Self>
+    where
+        Self: 'buf,
+    {
+        Self::parse_csv_field_with_dialect(buffer, &CsvDialect::default())
+    }
+
+    fn parse_csv_field_with_dialect(buffer: &'buf [u8], dialect: &CsvDialect) -> Result<Self>
+    where
+        Self: 'buf,
+    {
+        match dialect.transcode_to_utf8(buffer) {
+            Cow::Borrowed(buffer) => unescape_field_str(buffer, dialect.quote),
+            Cow::Owned(transcoded) => Ok(Cow::Owned(
+                unescape_field_str(&transcoded, dialect.quote)?.into_owned(),
+            )),
+        }
+    }
+}
+
 impl<'buf> ParseCsvField<'buf> for String {
-    fn parse_csv_field(buffer: &'buf [u8]) -> std::result::Result<Self, ()>
+    fn parse_csv_field(buffer: &'buf [u8]) -> Result<Self>
+    where
+        Self: 'buf,
+    {
+        Self::parse_csv_field_with_dialect(buffer, &CsvDialect::default())
+    }
+
+    fn parse_csv_field_with_dialect(buffer: &'buf [u8], dialect: &CsvDialect) -> Result<Self>
     where
         Self: 'buf,
     {
-        std::str::from_utf8(buffer)
-            .map_err(|_| ())
-            .and_then(|s| s.parse().map_err(|_| ()))
+        Ok(unescape_field_str(&dialect.transcode_to_utf8(buffer), dialect.quote)?.into_owned())
     }
 }
@@ -0,0 +1,66 @@
+use std::borrow::Cow;
+use std::io::Write;
+
+use crate::WriteCsvField;
+
+macro_rules! write_primitive_type {
+    ($ty:ty) => {
+        impl WriteCsvField for $ty {
+            fn write_csv_field(&self, buffer: &mut Vec<u8>) {
+                // Writing into a vector does not fail.
+                write!(buffer, "{}", self).unwrap();
+            }
+        }
+    };
+}
+
+write_primitive_type!(f32);
+write_primitive_type!(f64);
+write_primitive_type!(i8);
+write_primitive_type!(i16);
+write_primitive_type!(i32);
+write_primitive_type!(i64);
+write_primitive_type!(u8);
+write_primitive_type!(u16);
+write_primitive_type!(u32);
+write_primitive_type!(u64);
+write_primitive_type!(usize);
+
+impl WriteCsvField for str {
+    fn write_csv_field(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(self.as_bytes());
+    }
+}
+
+impl WriteCsvField for [u8] {
+    fn write_csv_field(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(self);
+    }
+}
+
+impl WriteCsvField for String {
+    fn write_csv_field(&self, buffer: &mut Vec<u8>) {
+        self.as_str().write_csv_field(buffer);
+    }
+}
+
+impl WriteCsvField for Cow<'_, str> {
+    fn write_csv_field(&self, buffer: &mut Vec<u8>) {
+        self.as_ref().write_csv_field(buffer);
+    }
+}
+
+impl<T: WriteCsvField + ?Sized> WriteCsvField for &T {
+    fn write_csv_field(&self, buffer: &mut Vec<u8>) {
+        (**self).write_csv_field(buffer);
+    }
+}
+
+/// `None` is written as empty field.
+impl<T: WriteCsvField> WriteCsvField for Option<T> {
+    fn write_csv_field(&self, buffer: &mut Vec<u8>) {
+        if let Some(value) = self {
+            value.write_csv_field(buffer);
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use std::borrow::Cow;
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CsvEncoding {
+    #[default]
+    Utf8,
+    /// ISO 8859-1. Text fields are transcoded to UTF-8 while parsing. Fields that reference the
+    /// buffer directly, i.e. `&str`, have to be ASCII.
+    Latin1,
+}
+
+/// Describes the variant of CSV that a buffer uses. The default is the RFC 4180 format
+/// with an optional UTF-8 byte order mark.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CsvDialect {
+    pub delimiter: u8,
+    pub quote: u8,
+    /// Ignore a UTF-8 byte order mark at the start of the buffer. Otherwise it becomes part
+    /// of the first column title.
+    pub strip_bom: bool,
+    pub encoding: CsvEncoding,
+}
+
+impl Default for CsvDialect {
+    fn default() -> Self {
+        Self {
+            delimiter: b',',
+            quote: b'"',
+            strip_bom: true,
+            encoding: CsvEncoding::Utf8,
+        }
+    }
+}
+
+impl CsvDialect {
+    /// Get the buffer without the byte order mark if it should be stripped.
+    pub fn strip_bom<'buf>(&self, buffer: &'buf [u8]) -> &'buf [u8] {
+        if self.strip_bom {
+            buffer.strip_prefix(UTF8_BOM).unwrap_or(buffer)
+        } else {
+            buffer
+        }
+    }
+
+    /// Converts the buffer to UTF-8 if it uses a different encoding. This does not copy
+    /// the buffer if it is UTF-8 already.
+    pub fn transcode_to_utf8<'buf>(&self, buffer: &'buf [u8]) -> Cow<'buf, [u8]> {
+        match self.encoding {
+            CsvEncoding::Utf8 => Cow::Borrowed(buffer),
+            CsvEncoding::Latin1 => {
+                if buffer.is_ascii() {
+                    return Cow::Borrowed(buffer);
+                }
+                // Every Latin-1 byte has the same value as the corresponding unicode code point.
+                let text: String = buffer.iter().map(|&c| c as char).collect();
+                Cow::Owned(text.into_bytes())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_bom() {
+        let dialect = CsvDialect::default();
+        assert_eq!(dialect.strip_bom(b"\xEF\xBB\xBFa,b"), b"a,b");
+        assert_eq!(dialect.strip_bom(b"a,b"), b"a,b");
+        let dialect = CsvDialect {
+            strip_bom: false,
+            ..CsvDialect::default()
+        };
+        assert_eq!(dialect.strip_bom(b"\xEF\xBB\xBFa,b"), b"\xEF\xBB\xBFa,b");
+    }
+
+    #[test]
+    fn test_transcode_latin1() {
+        let dialect = CsvDialect {
+            encoding: CsvEncoding::Latin1,
+            ..CsvDialect::default()
+        };
+        assert_eq!(dialect.transcode_to_utf8(b"abc"), Cow::Borrowed(b"abc"));
+        assert_eq!(
+            dialect.transcode_to_utf8(b"M\xFCnchen").as_ref(),
+            "München".as_bytes()
+        );
+    }
+}
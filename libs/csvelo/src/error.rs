@@ -0,0 +1,114 @@
+use std::fmt::Display;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    /// The field is not valid UTF-8 but is parsed as text.
+    InvalidUtf8,
+    /// The field is not a valid number.
+    InvalidNumber,
+    /// The field is a number but does not fit into the target type.
+    NumberOverflow,
+    /// The field can't be parsed into the target type for another reason.
+    InvalidValue,
+    /// The field contains an escaped quote but is parsed as borrowed text, which can't be
+    /// unescaped.
+    EscapedQuote,
+    /// The buffer does not start with a header record.
+    MissingHeader,
+}
+
+/// Describes why and where parsing a CSV buffer failed.
+///
+/// Field parsers only know the [`ErrorKind`]. The location is filled in by the caller that
+/// knows which record and column the field belongs to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    pub kind: ErrorKind,
+    /// Offset of the field from the start of the parsed buffer.
+    pub byte_offset: Option<usize>,
+    /// Index of the record, not counting the header.
+    pub record_index: Option<usize>,
+    pub column_name: Option<&'static str>,
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    pub fn new(kind: ErrorKind) -> Self {
+        Self {
+            kind,
+            byte_offset: None,
+            record_index: None,
+            column_name: None,
+        }
+    }
+
+    pub fn with_location(
+        self,
+        byte_offset: Option<usize>,
+        record_index: usize,
+        column_name: &'static str,
+    ) -> Self {
+        Self {
+            byte_offset,
+            record_index: Some(record_index),
+            column_name: Some(column_name),
+            ..self
+        }
+    }
+}
+
+impl From<std::str::Utf8Error> for Error {
+    fn from(_: std::str::Utf8Error) -> Self {
+        Self::new(ErrorKind::InvalidUtf8)
+    }
+}
+
+impl From<std::num::ParseIntError> for Error {
+    fn from(err: std::num::ParseIntError) -> Self {
+        match err.kind() {
+            std::num::IntErrorKind::PosOverflow | std::num::IntErrorKind::NegOverflow => {
+                Self::new(ErrorKind::NumberOverflow)
+            }
+            _ => Self::new(ErrorKind::InvalidNumber),
+        }
+    }
+}
+
+impl From<std::num::ParseFloatError> for Error {
+    fn from(_: std::num::ParseFloatError) -> Self {
+        Self::new(ErrorKind::InvalidNumber)
+    }
+}
+
+impl Display for ErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            ErrorKind::InvalidUtf8 => "invalid UTF-8",
+            ErrorKind::InvalidNumber => "invalid number",
+            ErrorKind::NumberOverflow => "number out of range",
+            ErrorKind::InvalidValue => "invalid value",
+            ErrorKind::EscapedQuote => "escaped quote in borrowed text",
+            ErrorKind::MissingHeader => "missing header",
+        };
+        f.write_str(text)
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.kind)?;
+        if let Some(column_name) = self.column_name {
+            write!(f, " in column {}", column_name)?;
+        }
+        if let Some(record_index) = self.record_index {
+            write!(f, " in record {}", record_index)?;
+        }
+        if let Some(byte_offset) = self.byte_offset {
+            write!(f, " at byte {}", byte_offset)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Error {}
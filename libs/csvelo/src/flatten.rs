@@ -13,11 +13,9 @@ pub fn flatten_slices<T: Clone + Send + Sync>(slices: &[&[T]]) -> Vec<T> {
     }
     let total_len = start;
 
-    // Allocate the output vector.
+    // Allocate the output vector. Its elements are initialized below before the length is set.
     let mut flattened: Vec<T> = Vec::with_capacity(total_len);
-    // This is safe as long because all the elements are initialized below.
-    unsafe { flattened.set_len(total_len) };
-    let mut flattened_slice = flattened.as_mut_slice();
+    let mut flattened_slice = &mut flattened.spare_capacity_mut()[..total_len];
 
     // Split the output vector into slices for each chunk.
     let mut dst_slices = vec![];
@@ -28,14 +26,13 @@ pub fn flatten_slices<T: Clone + Send + Sync>(slices: &[&[T]]) -> Vec<T> {
     }
 
     // Actually copy over the data.
-    slices
-        .par_iter()
-        .zip(dst_slices)
-        .for_each(|(src, dst)| unsafe {
-            for i in 0..src.len() {
-                std::ptr::write(dst.get_unchecked_mut(i), src.get_unchecked(i).clone());
-            }
-        });
+    slices.par_iter().zip(dst_slices).for_each(|(src, dst)| {
+        for (dst, src) in dst.iter_mut().zip(src.iter()) {
+            dst.write(src.clone());
+        }
+    });
 
+    // This is safe because all the elements have been initialized above.
+    unsafe { flattened.set_len(total_len) };
     flattened
 }
@@ -1,13 +1,25 @@
-pub use csvelo_derive::CSVParser;
+pub use csvelo_derive::{CSVColumns, CSVParser, CSVWriter};
+pub use dialect::{CsvDialect, CsvEncoding};
+pub use error::{Error, ErrorKind, Result};
 pub use flatten::flatten_slices;
+pub use parse_errors::*;
 pub use records::CsvRecords;
+pub use unescape::{unescape_field, unescape_field_str};
+pub use writer::CsvWriter;
 
 mod builtin_field_parsers;
+mod builtin_field_writers;
+mod dialect;
+mod error;
 mod flatten;
+mod parse_errors;
 mod parse_record;
 mod records;
+mod unescape;
+mod writer;
 
 use parse_record::*;
+use rayon::prelude::*;
 
 pub struct CsvBufferSections<'buf> {
     pub header: &'buf [u8],
@@ -19,9 +31,40 @@ pub struct CsvHeader<'buf> {
 }
 
 pub trait ParseCsvField<'buf>: Sized {
-    fn parse_csv_field(buffer: &'buf [u8]) -> std::result::Result<Self, ()>
+    /// Parses a single field. The returned error does not have to contain the location yet.
+    fn parse_csv_field(buffer: &'buf [u8]) -> Result<Self>
     where
         Self: 'buf;
+
+    /// Same as [`ParseCsvField::parse_csv_field`] but for fields from a buffer in the given
+    /// dialect. Types that contain text should unescape quotes with the dialect's quote character.
+    fn parse_csv_field_with_dialect(buffer: &'buf [u8], dialect: &CsvDialect) -> Result<Self>
+    where
+        Self: 'buf,
+    {
+        let _ = dialect;
+        Self::parse_csv_field(buffer)
+    }
+}
+
+/// The counterpart of [`ParseCsvField`] used by [`CsvWriter`].
+pub trait WriteCsvField {
+    /// Appends the value to the buffer. The value must not be quoted or escaped, this is done by
+    /// the writer if necessary.
+    fn write_csv_field(&self, buffer: &mut Vec<u8>);
+}
+
+/// Record-wise operations on structs with `Option<Vec<T>>` columns. Use `#[derive(CSVColumns)]`
+/// to implement it.
+pub trait CsvColumns {
+    /// Keeps only the records for which `keep` is true. Panics if `keep` does not have one entry
+    /// per record.
+    fn retain_records(&mut self, keep: &[bool]);
+
+    /// Appends the records of `other`. The lengths are passed in because they can't be derived
+    /// when there are no columns. Columns that exist in only one of both are filled up with
+    /// default values.
+    fn append_records(&mut self, len: usize, other: Self, other_len: usize);
 }
 
 impl CsvHeader<'_> {
@@ -32,9 +75,16 @@ impl CsvHeader<'_> {
     }
 }
 
-pub fn parse_header(header: &[u8]) -> CsvHeader {
+pub fn parse_header(header: &[u8]) -> CsvHeader<'_> {
+    parse_header_with_dialect(header, &CsvDialect::default())
+}
+
+pub fn parse_header_with_dialect<'buf>(
+    header: &'buf [u8],
+    dialect: &CsvDialect,
+) -> CsvHeader<'buf> {
     let mut fields = vec![];
-    parse_record_fields(header, 0, &mut fields);
+    parse_record_fields(header, 0, dialect, &mut fields);
     CsvHeader {
         column_titles: fields,
     }
@@ -44,12 +94,22 @@ pub fn parse_header_record_str(
     header: &[u8],
 ) -> std::result::Result<Vec<&str>, std::str::Utf8Error> {
     let mut fields = vec![];
-    parse_record_fields(header, 0, &mut fields);
+    parse_record_fields(header, 0, &CsvDialect::default(), &mut fields);
     fields.iter().map(|f| std::str::from_utf8(f)).collect()
 }
 
-pub fn split_header_and_data(buffer: &[u8]) -> CsvBufferSections {
-    let data_start_i = find_start_of_next_record(buffer, 0);
+pub fn split_header_and_data(buffer: &[u8]) -> CsvBufferSections<'_> {
+    split_header_and_data_with_dialect(buffer, &CsvDialect::default())
+}
+
+/// Same as [`split_header_and_data`] but also removes the byte order mark if the dialect
+/// asks for it.
+pub fn split_header_and_data_with_dialect<'buf>(
+    buffer: &'buf [u8],
+    dialect: &CsvDialect,
+) -> CsvBufferSections<'buf> {
+    let buffer = dialect.strip_bom(buffer);
+    let data_start_i = find_start_of_next_record(buffer, 0, dialect);
     CsvBufferSections {
         header: &buffer[..data_start_i],
         data: &buffer[data_start_i..],
@@ -60,29 +120,126 @@ pub fn split_csv_buffer_into_record_aligned_chunks(
     buffer: &[u8],
     approximate_chunk_size: usize,
 ) -> Vec<&[u8]> {
+    split_csv_buffer_into_record_aligned_chunks_with_dialect(
+        buffer,
+        approximate_chunk_size,
+        &CsvDialect::default(),
+    )
+}
+
+/// Splits the buffer into chunks that can be parsed independently. The buffer has to start at
+/// a record.
+///
+/// Since quoted fields may contain newlines, whether a newline ends a record depends on all the
+/// data before it. To still find the chunk boundaries in parallel, every chunk is scanned twice:
+/// once assuming that it starts outside of quotes and once assuming that it starts inside. Then
+/// the correct scan of each chunk is picked based on the state at the end of the previous chunk.
+pub fn split_csv_buffer_into_record_aligned_chunks_with_dialect<'buf>(
+    buffer: &'buf [u8],
+    approximate_chunk_size: usize,
+    dialect: &CsvDialect,
+) -> Vec<&'buf [u8]> {
+    let approximate_chunk_size = approximate_chunk_size.max(1);
+    let mut segment_starts = vec![0];
+    let mut next_start = approximate_chunk_size;
+    while next_start < buffer.len() {
+        // Make sure that escaped quotes are not split.
+        while next_start < buffer.len() && buffer[next_start - 1] == dialect.quote {
+            next_start += 1;
+        }
+        if next_start < buffer.len() {
+            segment_starts.push(next_start);
+        }
+        next_start += approximate_chunk_size;
+    }
+    let segment_ends: Vec<usize> = segment_starts
+        .iter()
+        .skip(1)
+        .copied()
+        .chain([buffer.len()])
+        .collect();
+
+    let scans: Vec<[RecordStartScan; 2]> = segment_starts
+        .par_iter()
+        .zip(segment_ends.par_iter())
+        .map(|(&start, &end)| {
+            [false, true].map(|in_quotes| {
+                scan_for_record_start(buffer, start, end, in_quotes, true, dialect)
+            })
+        })
+        .collect();
+
     let mut chunks = vec![];
-    let mut next_chunk_start = 0;
-    while next_chunk_start < buffer.len() {
-        let approximate_chunk_end = (next_chunk_start + approximate_chunk_size).min(buffer.len());
-        let chunk_end = find_start_of_next_record(buffer, approximate_chunk_end);
-        chunks.push(&buffer[next_chunk_start..chunk_end]);
-        next_chunk_start = chunk_end;
+    let mut chunk_start = 0;
+    let mut in_quotes = false;
+    for (segment_i, scan) in scans.iter().enumerate() {
+        let scan = &scan[in_quotes as usize];
+        if segment_i > 0 {
+            if let Some(record_start) = scan.record_start {
+                chunks.push(&buffer[chunk_start..record_start]);
+                chunk_start = record_start;
+            }
+        }
+        in_quotes = scan.in_quotes_at_end;
+    }
+    if chunk_start < buffer.len() {
+        chunks.push(&buffer[chunk_start..]);
     }
     chunks
 }
 
-pub fn parse_column_value<'buf, T>(
+/// Get the line number of every record after the header, starting at 1 for the first line of
+/// the buffer. A record may span several lines if a quoted field contains a newline, so the
+/// record index alone does not identify the line.
+pub fn record_line_numbers(buffer: &[u8], dialect: &CsvDialect) -> Vec<usize> {
+    let buffer = dialect.strip_bom(buffer);
+    let count_newlines = |range: &[u8]| range.iter().filter(|c| **c == b'\n').count();
+    let mut start = find_start_of_next_record(buffer, 0, dialect);
+    let mut line = 1 + count_newlines(&buffer[..start]);
+    let mut line_numbers = vec![];
+    let mut fields = vec![];
+    while start < buffer.len() {
+        line_numbers.push(line);
+        let end = parse_record_fields(buffer, start, dialect, &mut fields);
+        fields.clear();
+        line += count_newlines(&buffer[start..end]);
+        start = end;
+    }
+    line_numbers
+}
+
+/// Parses all fields of a column. Fields that can't be parsed are replaced by the default value
+/// and are recorded in the returned errors. `buffer` is the buffer that the records reference and
+/// is used to compute the byte offsets of the errors.
+pub fn parse_column_value<'buf, T: Default>(
+    buffer: &'buf [u8],
     records: &CsvRecords<'buf>,
     column_i: usize,
-    parse_field: impl Fn(&'buf [u8]) -> std::result::Result<T, ()>,
-) -> std::result::Result<Vec<T>, ()> {
-    let mut data = vec![];
-    data.reserve(records.len());
-    for record in records.iter() {
-        let column_buffer = record.column(column_i).unwrap_or(b"");
-        data.push(parse_field(column_buffer)?);
+    column_name: &'static str,
+    dialect: &CsvDialect,
+    parse_field: impl Fn(&'buf [u8], &CsvDialect) -> Result<T>,
+) -> (Vec<T>, CsvColumnErrors) {
+    let mut data = Vec::with_capacity(records.len());
+    let mut errors = CsvColumnErrors::default();
+    for (record_i, record) in records.iter().enumerate() {
+        let column_buffer = record.column(column_i);
+        let column_buffer_or_empty = column_buffer.unwrap_or(b"");
+        match parse_field(column_buffer_or_empty, dialect) {
+            Ok(value) => data.push(value),
+            Err(err) => {
+                // Empty fields don't necessarily point into the buffer.
+                let byte_offset = column_buffer
+                    .and_then(|b| (b.as_ptr() as usize).checked_sub(buffer.as_ptr() as usize))
+                    .filter(|offset| *offset <= buffer.len());
+                errors.add(
+                    err.with_location(byte_offset, record_i, column_name),
+                    column_buffer_or_empty,
+                );
+                data.push(T::default());
+            }
+        }
     }
-    Ok(data)
+    (data, errors)
 }
 
 #[cfg(test)]
@@ -117,6 +274,58 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_split_chunks_with_quoted_newlines() {
+        let buffer = indoc! {r#"
+            1,"a
+            b",2
+            3,"
+            ""c""
+            ",4
+            5,6,7
+        "#};
+        let expected_records = ["1,\"a\nb\",2\n", "3,\"\n\"\"c\"\"\n\",4\n", "5,6,7\n"];
+        for chunk_size in 0..buffer.len() + 2 {
+            let chunks = split_csv_buffer_into_record_aligned_chunks(buffer.as_bytes(), chunk_size);
+            assert_eq!(chunks.concat(), buffer.as_bytes());
+            for chunk in &chunks {
+                let chunk = std::str::from_utf8(chunk).unwrap();
+                assert!(
+                    expected_records.iter().any(|r| chunk.starts_with(r)),
+                    "chunk size {}: {:?}",
+                    chunk_size,
+                    chunk
+                );
+            }
+            let records_num: usize = chunks
+                .iter()
+                .map(|chunk| CsvRecords::from_buffer(chunk).len())
+                .sum();
+            assert_eq!(records_num, 3);
+        }
+        let chunks = split_csv_buffer_into_record_aligned_chunks(buffer.as_bytes(), 0);
+        assert_eq!(chunks.len(), 3);
+        let records = CsvRecords::from_buffer(chunks[1]);
+        assert_eq!(records.record(0).column(1).unwrap(), b"\n\"\"c\"\"\n");
+    }
+
+    #[test]
+    fn test_record_line_numbers() {
+        let buffer = indoc! {r#"
+            a,b
+            1,"x
+            y"
+            2,z
+            3,"
+            ""q""
+            "
+        "#};
+        assert_eq!(
+            record_line_numbers(buffer.as_bytes(), &CsvDialect::default()),
+            vec![2, 4, 5]
+        );
+    }
+
     #[test]
     fn test_split_header_and_data() {
         let buffer = indoc! {r#"
@@ -131,4 +340,32 @@ mod tests {
         let headers = parse_header_record_str(sections.header).unwrap();
         assert_eq!(headers, &["Title", "Author", "Year"]);
     }
+
+    #[test]
+    fn test_split_header_and_data_with_dialect() {
+        let buffer = b"\xEF\xBB\xBFstop_id;stop_name\n1;'A;B'\n";
+        let dialect = CsvDialect {
+            delimiter: b';',
+            quote: b'\'',
+            ..CsvDialect::default()
+        };
+        let sections = split_header_and_data_with_dialect(buffer, &dialect);
+        assert_eq!(sections.header, b"stop_id;stop_name\n");
+        let header = parse_header_with_dialect(sections.header, &dialect);
+        assert_eq!(header.get_column_index("stop_name"), Some(1));
+
+        let records = CsvRecords::from_buffer_with_dialect(sections.data, &dialect);
+        assert_eq!(records.record(0).column(1).unwrap(), b"A;B");
+
+        let sections = split_header_and_data(buffer);
+        assert_eq!(sections.header, b"stop_id;stop_name\n");
+        let sections = split_header_and_data_with_dialect(
+            buffer,
+            &CsvDialect {
+                strip_bom: false,
+                ..dialect
+            },
+        );
+        assert_eq!(sections.header, b"\xEF\xBB\xBFstop_id;stop_name\n");
+    }
 }
@@ -0,0 +1,84 @@
+use crate::Error;
+
+/// Maximum number of failed fields per column that are kept with their raw value.
+pub const MAX_ERROR_SAMPLES_PER_COLUMN: usize = 10;
+
+/// A field that could not be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsvFieldErrorSample {
+    /// Always contains the record index and column name.
+    pub error: Error,
+    /// The unparsed field. This is copied so that the errors can outlive the buffer.
+    pub raw: Vec<u8>,
+}
+
+/// Errors that occurred while parsing the fields of a single column.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CsvColumnErrors {
+    /// Total number of fields in the column that could not be parsed.
+    pub errors_num: usize,
+    /// The first [`MAX_ERROR_SAMPLES_PER_COLUMN`] fields that could not be parsed.
+    pub samples: Vec<CsvFieldErrorSample>,
+}
+
+impl CsvColumnErrors {
+    pub fn is_empty(&self) -> bool {
+        self.errors_num == 0
+    }
+
+    pub fn add(&mut self, error: Error, raw: &[u8]) {
+        self.errors_num += 1;
+        if self.samples.len() < MAX_ERROR_SAMPLES_PER_COLUMN {
+            self.samples.push(CsvFieldErrorSample {
+                error,
+                raw: raw.to_vec(),
+            });
+        }
+    }
+
+    /// Appends the errors of a later chunk whose rows start at `row_offset`.
+    pub fn extend_from_chunk(&mut self, other: CsvColumnErrors, row_offset: usize) {
+        self.errors_num += other.errors_num;
+        let free_samples_num = MAX_ERROR_SAMPLES_PER_COLUMN.saturating_sub(self.samples.len());
+        self.samples.extend(
+            other
+                .samples
+                .into_iter()
+                .take(free_samples_num)
+                .map(|mut sample| {
+                    sample.error.record_index = sample.error.record_index.map(|i| i + row_offset);
+                    sample
+                }),
+        );
+    }
+}
+
+/// Errors that occurred while parsing a CSV buffer. Fields that could not be parsed are
+/// replaced by a default value, so that the rest of the column is still available.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CsvParseErrors {
+    /// Only contains the columns that had at least one error.
+    pub columns: Vec<(&'static str, CsvColumnErrors)>,
+}
+
+impl CsvParseErrors {
+    pub fn is_empty(&self) -> bool {
+        self.columns.is_empty()
+    }
+
+    /// Get the total number of fields that could not be parsed.
+    pub fn errors_num(&self) -> usize {
+        self.columns
+            .iter()
+            .map(|(_, errors)| errors.errors_num)
+            .sum()
+    }
+
+    /// Get the errors of a column or None if all its fields were parsed successfully.
+    pub fn column(&self, column_name: &str) -> Option<&CsvColumnErrors> {
+        self.columns
+            .iter()
+            .find(|(name, _)| *name == column_name)
+            .map(|(_, errors)| errors)
+    }
+}
@@ -1,11 +1,16 @@
+use crate::CsvDialect;
+
 /// Adds all the fields of the current record and returns the first index after the record.
 /// I.e. the index after the newline character or the end of the buffer.
 /// The start index has to be the index of the first character in the record.
 pub fn parse_record_fields<'a>(
     buffer: &'a [u8],
     start: usize,
+    dialect: &CsvDialect,
     fields: &mut Vec<&'a [u8]>,
 ) -> usize {
+    let delimiter = dialect.delimiter;
+    let quote = dialect.quote;
     let mut i = start;
     while i < buffer.len() {
         match buffer[i] {
@@ -15,22 +20,22 @@ pub fn parse_record_fields<'a>(
             b'\r' => {
                 i += 1;
             }
-            b',' => {
+            c if c == delimiter => {
                 fields.push(b"");
                 i += 1;
                 handle_potentially_trailing_comma(buffer, i, fields);
             }
-            b'"' => {
+            c if c == quote => {
                 i += 1;
-                let end_of_field = find_end_of_quoted_field(buffer, i);
+                let end_of_field = find_end_of_quoted_field(buffer, i, quote);
                 fields.push(&buffer[i..end_of_field]);
                 i = end_of_field;
                 while i < buffer.len() {
                     match buffer[i] {
-                        b'"' => {
+                        c if c == quote => {
                             i += 1;
                         }
-                        b',' => {
+                        c if c == delimiter => {
                             i += 1;
                             handle_potentially_trailing_comma(buffer, i, fields);
                             break;
@@ -45,23 +50,12 @@ pub fn parse_record_fields<'a>(
                 }
             }
             _ => {
-                let end_of_field = find_end_of_simple_field(buffer, i);
+                let end_of_field = find_end_of_simple_field(buffer, i, delimiter);
                 fields.push(&buffer[i..end_of_field]);
                 i = end_of_field;
-                while i < buffer.len() {
-                    match buffer[i] {
-                        b',' => {
-                            i += 1;
-                            handle_potentially_trailing_comma(buffer, i, fields);
-                            break;
-                        }
-                        b'\r' | b'\n' => {
-                            break;
-                        }
-                        _ => {
-                            unreachable!();
-                        }
-                    }
+                if i < buffer.len() && buffer[i] == delimiter {
+                    i += 1;
+                    handle_potentially_trailing_comma(buffer, i, fields);
                 }
             }
         }
@@ -85,11 +79,14 @@ fn handle_potentially_trailing_comma<'a>(buffer: &'a [u8], i: usize, fields: &mu
 /// Find the index that ends the current field (e.g. the index of the next comma or newline).
 /// The start index has to be the index of the first character in the field.
 /// It may also be the end of the field already if the field is empty.
-fn find_end_of_simple_field(buffer: &[u8], start: usize) -> usize {
+fn find_end_of_simple_field(buffer: &[u8], start: usize, delimiter: u8) -> usize {
     let mut i = start;
     while i < buffer.len() {
         match buffer[i] {
-            b',' | b'\n' | b'\r' => {
+            b'\n' | b'\r' => {
+                return i;
+            }
+            c if c == delimiter => {
                 return i;
             }
             _ => {
@@ -102,23 +99,20 @@ fn find_end_of_simple_field(buffer: &[u8], start: usize) -> usize {
 
 /// Find the index of the quote that ends the current field.
 /// The start index has to be the index after the opening quote.
-fn find_end_of_quoted_field(buffer: &[u8], start: usize) -> usize {
+fn find_end_of_quoted_field(buffer: &[u8], start: usize, quote: u8) -> usize {
     let mut i = start;
     while i < buffer.len() {
         match buffer[i] {
-            b'"' => {
-                if i + 1 < buffer.len() && buffer[i + 1] == b'"' {
+            c if c == quote => {
+                if i + 1 < buffer.len() && buffer[i + 1] == quote {
                     // Two consecutive quotes with in a quoted field are the escape sequence for a single quote.
                     i += 2;
                     continue;
                 }
                 return i;
             }
-            b'\n' | b'\r' => {
-                // Assume there is an end-quote missing.
-                return i;
-            }
             _ => {
+                // Newlines are part of the field too.
                 i += 1;
             }
         }
@@ -127,12 +121,91 @@ fn find_end_of_quoted_field(buffer: &[u8], start: usize) -> usize {
 }
 
 /// Finds the index of the first character in the next record, or the end of the buffer
-/// if there is no next record.
-pub fn find_start_of_next_record(buffer: &[u8], start: usize) -> usize {
-    if let Some(newline_offset) = buffer[start..].iter().position(|&c| c == b'\n') {
-        start + newline_offset + 1
-    } else {
-        buffer.len()
+/// if there is no next record. The start index has to be the start of a record.
+pub fn find_start_of_next_record(buffer: &[u8], start: usize, dialect: &CsvDialect) -> usize {
+    let scan = scan_for_record_start(buffer, start, buffer.len(), false, false, dialect);
+    scan.record_start.unwrap_or(buffer.len())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ScanState {
+    /// At the start of a field where a quote opens a quoted field.
+    FieldStart,
+    /// In an unquoted field or after the closing quote of a quoted field.
+    Unquoted,
+    Quoted,
+}
+
+/// Result of scanning a part of a buffer for a record boundary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecordStartScan {
+    /// Index of the first record that starts after the scan start within the scanned range.
+    pub record_start: Option<usize>,
+    /// Whether the end of the scanned range is within a quoted field. Only set when the end state
+    /// is tracked.
+    pub in_quotes_at_end: bool,
+}
+
+/// Scans the given range for the start of a record while tracking whether the current position
+/// is inside of a quoted field. Since the state at the start of the range may not be known, the
+/// caller can speculatively scan with both possible states and pick the right result later.
+///
+/// The byte before `start` and before `end` must not be a quote, because otherwise an escaped
+/// quote could be split. If `track_end_state` is false, the scan stops at the first record start
+/// and `in_quotes_at_end` is meaningless.
+pub fn scan_for_record_start(
+    buffer: &[u8],
+    start: usize,
+    end: usize,
+    in_quotes: bool,
+    track_end_state: bool,
+    dialect: &CsvDialect,
+) -> RecordStartScan {
+    let is_field_start = |i: usize| {
+        i == 0 || matches!(buffer[i - 1], b'\n' | b'\r') || buffer[i - 1] == dialect.delimiter
+    };
+    let mut state = match in_quotes {
+        true => ScanState::Quoted,
+        false if is_field_start(start) => ScanState::FieldStart,
+        false => ScanState::Unquoted,
+    };
+    let mut record_start = None;
+    let mut i = start;
+    while i < end {
+        let c = buffer[i];
+        match state {
+            ScanState::FieldStart | ScanState::Unquoted => {
+                if c == b'\n' {
+                    if record_start.is_none() {
+                        record_start = Some(i + 1);
+                        if !track_end_state {
+                            break;
+                        }
+                    }
+                    state = ScanState::FieldStart;
+                } else if c == b'\r' || c == dialect.delimiter {
+                    state = ScanState::FieldStart;
+                } else if c == dialect.quote && state == ScanState::FieldStart {
+                    state = ScanState::Quoted;
+                } else {
+                    state = ScanState::Unquoted;
+                }
+            }
+            ScanState::Quoted => {
+                if c == dialect.quote {
+                    if i + 1 < buffer.len() && buffer[i + 1] == dialect.quote {
+                        i += 2;
+                        continue;
+                    }
+                    state = ScanState::Unquoted;
+                }
+            }
+        }
+        i += 1;
+    }
+    RecordStartScan {
+        record_start,
+        in_quotes_at_end: state == ScanState::Quoted,
     }
 }
 
@@ -142,46 +215,78 @@ mod tests {
 
     #[test]
     fn test_find_end_of_simple_field() {
-        assert_eq!(find_end_of_simple_field(b"123", 0), 3);
-        assert_eq!(find_end_of_simple_field(b"123", 1), 3);
-        assert_eq!(find_end_of_simple_field(b"123", 2), 3);
-        assert_eq!(find_end_of_simple_field(b"123", 3), 3);
-        assert_eq!(find_end_of_simple_field(b"1'3", 3), 3);
-        assert_eq!(find_end_of_simple_field(b"123,", 0), 3);
-        assert_eq!(find_end_of_simple_field(b"123,456", 0), 3);
-        assert_eq!(find_end_of_simple_field(b"123,456,789", 0), 3);
-        assert_eq!(find_end_of_simple_field(b" 23", 0), 3);
-        assert_eq!(find_end_of_simple_field(b"", 0), 0);
-        assert_eq!(find_end_of_simple_field(b"\n", 0), 0);
-        assert_eq!(find_end_of_simple_field(b"12\n", 0), 2);
-        assert_eq!(find_end_of_simple_field(b"0,12\n", 0), 1);
-        assert_eq!(find_end_of_simple_field(b"0,12\n", 2), 4);
-        assert_eq!(find_end_of_simple_field(b"\r\n", 0), 0);
-        assert_eq!(find_end_of_simple_field(b"12\r\n", 0), 2);
-        assert_eq!(find_end_of_simple_field(b"0,12\r\n", 0), 1);
-        assert_eq!(find_end_of_simple_field(b"0,12\r\n", 2), 4);
+        assert_eq!(find_end_of_simple_field(b"123", 0, b','), 3);
+        assert_eq!(find_end_of_simple_field(b"123", 1, b','), 3);
+        assert_eq!(find_end_of_simple_field(b"123", 2, b','), 3);
+        assert_eq!(find_end_of_simple_field(b"123", 3, b','), 3);
+        assert_eq!(find_end_of_simple_field(b"1'3", 3, b','), 3);
+        assert_eq!(find_end_of_simple_field(b"123,", 0, b','), 3);
+        assert_eq!(find_end_of_simple_field(b"123,456", 0, b','), 3);
+        assert_eq!(find_end_of_simple_field(b"123,456,789", 0, b','), 3);
+        assert_eq!(find_end_of_simple_field(b" 23", 0, b','), 3);
+        assert_eq!(find_end_of_simple_field(b"", 0, b','), 0);
+        assert_eq!(find_end_of_simple_field(b"\n", 0, b','), 0);
+        assert_eq!(find_end_of_simple_field(b"12\n", 0, b','), 2);
+        assert_eq!(find_end_of_simple_field(b"0,12\n", 0, b','), 1);
+        assert_eq!(find_end_of_simple_field(b"0,12\n", 2, b','), 4);
+        assert_eq!(find_end_of_simple_field(b"\r\n", 0, b','), 0);
+        assert_eq!(find_end_of_simple_field(b"12\r\n", 0, b','), 2);
+        assert_eq!(find_end_of_simple_field(b"0,12\r\n", 0, b','), 1);
+        assert_eq!(find_end_of_simple_field(b"0,12\r\n", 2, b','), 4);
     }
 
     #[test]
     fn test_find_end_of_quoted_field() {
-        assert_eq!(find_end_of_quoted_field(b"", 0), 0);
-        assert_eq!(find_end_of_quoted_field(b"123", 0), 3);
-        assert_eq!(find_end_of_quoted_field(b"123\n", 0), 3);
-        assert_eq!(find_end_of_quoted_field(b"123\r\n", 0), 3);
-        assert_eq!(find_end_of_quoted_field(b"123\"", 0), 3);
-        assert_eq!(find_end_of_quoted_field(b"\"", 0), 0);
-        assert_eq!(find_end_of_quoted_field(b"\"\"", 0), 2);
-        assert_eq!(find_end_of_quoted_field(b"123\"\"", 0), 5);
-        assert_eq!(find_end_of_quoted_field(b"123\"\"\"", 0), 5);
-        assert_eq!(find_end_of_quoted_field(b"123\"\"\"\"", 0), 7);
-        assert_eq!(find_end_of_quoted_field(b"123\"\"\"\"\"", 0), 7);
-        assert_eq!(find_end_of_quoted_field(b"123\"\"0\"\"\"", 0), 8);
-        assert_eq!(find_end_of_quoted_field(b",", 0), 1);
-        assert_eq!(find_end_of_quoted_field(b",\"", 0), 1);
-        assert_eq!(find_end_of_quoted_field(b"0,1\"", 0), 3);
-        assert_eq!(find_end_of_quoted_field(b"0,1\n", 0), 3);
-        assert_eq!(find_end_of_quoted_field(b"0,1\"\"", 0), 5);
-        assert_eq!(find_end_of_quoted_field(b"0,1\"\"\"", 0), 5);
+        assert_eq!(find_end_of_quoted_field(b"", 0, b'"'), 0);
+        assert_eq!(find_end_of_quoted_field(b"123", 0, b'"'), 3);
+        assert_eq!(find_end_of_quoted_field(b"123\n", 0, b'"'), 4);
+        assert_eq!(find_end_of_quoted_field(b"123\r\n", 0, b'"'), 5);
+        assert_eq!(find_end_of_quoted_field(b"123\"", 0, b'"'), 3);
+        assert_eq!(find_end_of_quoted_field(b"\"", 0, b'"'), 0);
+        assert_eq!(find_end_of_quoted_field(b"\"\"", 0, b'"'), 2);
+        assert_eq!(find_end_of_quoted_field(b"123\"\"", 0, b'"'), 5);
+        assert_eq!(find_end_of_quoted_field(b"123\"\"\"", 0, b'"'), 5);
+        assert_eq!(find_end_of_quoted_field(b"123\"\"\"\"", 0, b'"'), 7);
+        assert_eq!(find_end_of_quoted_field(b"123\"\"\"\"\"", 0, b'"'), 7);
+        assert_eq!(find_end_of_quoted_field(b"123\"\"0\"\"\"", 0, b'"'), 8);
+        assert_eq!(find_end_of_quoted_field(b",", 0, b'"'), 1);
+        assert_eq!(find_end_of_quoted_field(b",\"", 0, b'"'), 1);
+        assert_eq!(find_end_of_quoted_field(b"0,1\"", 0, b'"'), 3);
+        assert_eq!(find_end_of_quoted_field(b"0,1\n", 0, b'"'), 4);
+        assert_eq!(find_end_of_quoted_field(b"0,1\"\"", 0, b'"'), 5);
+        assert_eq!(find_end_of_quoted_field(b"0,1\"\"\"", 0, b'"'), 5);
+        assert_eq!(find_end_of_quoted_field(b"0\n1\",", 0, b'"'), 3);
+    }
+
+    #[test]
+    fn test_find_start_of_next_record() {
+        let dialect = CsvDialect::default();
+        assert_eq!(find_start_of_next_record(b"", 0, &dialect), 0);
+        assert_eq!(find_start_of_next_record(b"a,b", 0, &dialect), 3);
+        assert_eq!(find_start_of_next_record(b"a,b\nc", 0, &dialect), 4);
+        assert_eq!(find_start_of_next_record(b"a,\"b\nc\"\nd", 0, &dialect), 8);
+        assert_eq!(find_start_of_next_record(b"\"a\"\"\n\"\nd", 0, &dialect), 7);
+        assert_eq!(find_start_of_next_record(b"a\"b\nc\"\n", 0, &dialect), 4);
+        assert_eq!(find_start_of_next_record(b"\"a\"b\nc", 0, &dialect), 5);
+    }
+
+    #[test]
+    fn test_scan_for_record_start() {
+        let dialect = CsvDialect::default();
+        let buffer = b"1,\"a\nb\",2\n3,c,4\n";
+        // Starting in the middle of the quoted field.
+        let scan = scan_for_record_start(buffer, 5, buffer.len(), true, true, &dialect);
+        assert_eq!(scan.record_start, Some(10));
+        assert!(!scan.in_quotes_at_end);
+        let scan = scan_for_record_start(buffer, 4, 8, false, true, &dialect);
+        assert_eq!(scan.record_start, Some(5));
+        assert!(!scan.in_quotes_at_end);
+        let scan = scan_for_record_start(buffer, 4, 8, true, true, &dialect);
+        assert_eq!(scan.record_start, None);
+        assert!(!scan.in_quotes_at_end);
+        let scan = scan_for_record_start(buffer, 0, 4, false, true, &dialect);
+        assert_eq!(scan.record_start, None);
+        assert!(scan.in_quotes_at_end);
     }
 
     #[test]
@@ -202,11 +307,15 @@ mod tests {
         assert_eq!(get_parsed_record("\"\" "), &[""]);
         assert_eq!(get_parsed_record("0,"), &["0", ""]);
         assert_eq!(get_parsed_record("\" \","), &[" ", ""]);
+        assert_eq!(
+            get_parsed_record("1,\"a\nb\r\nc\",2\n3"),
+            &["1", "a\nb\r\nc", "2"]
+        );
     }
 
     fn get_parsed_record(buffer: &str) -> Vec<&str> {
         let mut fields = vec![];
-        parse_record_fields(buffer.as_bytes(), 0, &mut fields);
+        parse_record_fields(buffer.as_bytes(), 0, &CsvDialect::default(), &mut fields);
         fields
             .iter()
             .map(|f| std::str::from_utf8(f).unwrap())
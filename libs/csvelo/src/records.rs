@@ -1,3 +1,7 @@
+use std::borrow::Cow;
+
+use crate::{unescape_field_str, CsvDialect, Result};
+
 /// A (part) of a CSV file parsed into records and their fields.
 /// One can iterate over the individual records and access the fields as `&[u8]` slices.
 #[derive(Default)]
@@ -8,21 +12,32 @@ pub struct CsvRecords<'buf> {
     offsets: Vec<usize>,
     /// The fields of each record in a flat vector. This is cheaper than having a vector of vectors.
     fields: Vec<&'buf [u8]>,
+    /// Used to unescape the fields.
+    dialect: CsvDialect,
 }
 
 impl<'buf> CsvRecords<'buf> {
     /// Splits the given buffer into records and their fields.
     pub fn from_buffer(buffer: &'buf [u8]) -> Self {
+        Self::from_buffer_with_dialect(buffer, &CsvDialect::default())
+    }
+
+    /// Same as [`CsvRecords::from_buffer`] but with a custom delimiter and quote character.
+    pub fn from_buffer_with_dialect(buffer: &'buf [u8], dialect: &CsvDialect) -> Self {
         let mut offsets = vec![];
         let mut fields = vec![];
 
         offsets.push(0);
         let mut start = 0;
         while start < buffer.len() {
-            start = crate::parse_record::parse_record_fields(buffer, start, &mut fields);
+            start = crate::parse_record::parse_record_fields(buffer, start, dialect, &mut fields);
             offsets.push(fields.len());
         }
-        CsvRecords { offsets, fields }
+        CsvRecords {
+            offsets,
+            fields,
+            dialect: *dialect,
+        }
     }
 
     /// Get an iterator over all the records.
@@ -38,19 +53,28 @@ impl<'buf> CsvRecords<'buf> {
         self.offsets.len() - 1
     }
 
+    /// Check if there are no records.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
     /// Get a specific record by its index. This function panics if the index is out of bounds.
     pub fn record<'rec>(&'rec self, i: usize) -> CsvRecord<'rec, 'buf> {
         let start = self.offsets[i];
         let end = self.offsets[i + 1];
         CsvRecord {
             fields: &self.fields[start..end],
+            quote: self.dialect.quote,
         }
     }
 }
 
 /// Contains the fields of an individual record (i.e. a line/row).
 pub struct CsvRecord<'rec, 'buf> {
+    /// The raw fields. Quoted fields don't contain the enclosing quotes but still contain
+    /// escaped quotes.
     pub fields: &'rec [&'buf [u8]],
+    quote: u8,
 }
 
 impl<'rec, 'buf> CsvRecord<'rec, 'buf> {
@@ -63,6 +87,13 @@ impl<'rec, 'buf> CsvRecord<'rec, 'buf> {
     pub fn column(&self, column_i: usize) -> Option<&'buf [u8]> {
         self.fields.get(column_i).copied()
     }
+
+    /// Get the text of a specific column with escaped quotes replaced. This only allocates if
+    /// the field contains escaped quotes. None if the index is out of bounds.
+    pub fn column_str(&self, column_i: usize) -> Option<Result<Cow<'buf, str>>> {
+        self.column(column_i)
+            .map(|field| unescape_field_str(field, self.quote))
+    }
 }
 
 /// An iterator over the records of a CSV file buffer.
@@ -154,6 +185,20 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_column_str() {
+        let buffer = b"1,\"this,is a \"\" test\",plain\n";
+        let records = CsvRecords::from_buffer(buffer);
+        let record = records.record(0);
+        assert_eq!(record.column(1).unwrap(), b"this,is a \"\" test");
+        assert_eq!(record.column_str(1).unwrap().unwrap(), "this,is a \" test");
+        assert!(matches!(
+            record.column_str(2).unwrap().unwrap(),
+            Cow::Borrowed("plain")
+        ));
+        assert!(record.column_str(3).is_none());
+    }
+
     #[test]
     fn test_records_iterator() {
         let buffer = indoc! {"
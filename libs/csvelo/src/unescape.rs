@@ -0,0 +1,63 @@
+use std::borrow::Cow;
+
+use crate::Result;
+
+/// Replaces two consecutive quotes in a field by a single one. This is how quotes are escaped
+/// within quoted fields. The field is only copied if it contains escaped quotes.
+pub fn unescape_field(field: &[u8], quote: u8) -> Cow<'_, [u8]> {
+    let Some(first_quote_i) = field.iter().position(|&c| c == quote) else {
+        return Cow::Borrowed(field);
+    };
+    let mut unescaped = Vec::with_capacity(field.len());
+    unescaped.extend_from_slice(&field[..first_quote_i]);
+    let mut i = first_quote_i;
+    while i < field.len() {
+        let c = field[i];
+        unescaped.push(c);
+        if c == quote && field.get(i + 1) == Some(&quote) {
+            i += 2;
+        } else {
+            i += 1;
+        }
+    }
+    Cow::Owned(unescaped)
+}
+
+/// Same as [`unescape_field`] but also checks that the field is valid UTF-8.
+pub fn unescape_field_str(field: &[u8], quote: u8) -> Result<Cow<'_, str>> {
+    match unescape_field(field, quote) {
+        Cow::Borrowed(field) => Ok(Cow::Borrowed(std::str::from_utf8(field)?)),
+        Cow::Owned(field) => Ok(Cow::Owned(
+            String::from_utf8(field).map_err(|err| err.utf8_error())?,
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unescape_field() {
+        assert!(matches!(
+            unescape_field(b"abc", b'"'),
+            Cow::Borrowed(b"abc")
+        ));
+        assert!(matches!(unescape_field(b"", b'"'), Cow::Borrowed(b"")));
+        assert_eq!(unescape_field(b"a\"\"b", b'"').as_ref(), b"a\"b");
+        assert_eq!(unescape_field(b"\"\"\"\"", b'"').as_ref(), b"\"\"");
+        assert_eq!(
+            unescape_field(b"this,is a \"\" test", b'"').as_ref(),
+            b"this,is a \" test"
+        );
+        assert_eq!(unescape_field(b"a''b\"\"", b'\'').as_ref(), b"a'b\"\"");
+        // A single quote is kept as is, even though it should not exist in a valid field.
+        assert_eq!(unescape_field(b"a\"b", b'"').as_ref(), b"a\"b");
+    }
+
+    #[test]
+    fn test_unescape_field_str() {
+        assert_eq!(unescape_field_str(b"a\"\"b", b'"').unwrap(), "a\"b");
+        assert!(unescape_field_str(b"a\"\"\xff", b'"').is_err());
+    }
+}
@@ -0,0 +1,150 @@
+use std::io::Write;
+
+use crate::{CsvDialect, WriteCsvField};
+
+/// The writer collects this many bytes before passing them on to the underlying writer.
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// Writes records to CSV. Fields are quoted only when necessary, i.e. when they contain the
+/// delimiter, the quote character or a line break. Records always end with `\n`.
+///
+/// The output is buffered internally. Call [`CsvWriter::flush`] at the end to get notified of
+/// errors. Otherwise the remaining data is written when the writer is dropped.
+pub struct CsvWriter<W: Write> {
+    out: W,
+    dialect: CsvDialect,
+    buffer: Vec<u8>,
+    /// Reused for every field so that it only has to be quoted if necessary.
+    field_buffer: Vec<u8>,
+    fields_in_record: usize,
+}
+
+impl<W: Write> CsvWriter<W> {
+    pub fn new(out: W) -> Self {
+        Self::with_dialect(out, CsvDialect::default())
+    }
+
+    /// Only the delimiter and quote of the dialect are used. The output is always UTF-8 without a
+    /// byte order mark.
+    pub fn with_dialect(out: W, dialect: CsvDialect) -> Self {
+        Self {
+            out,
+            dialect,
+            buffer: Vec::with_capacity(BUFFER_SIZE),
+            field_buffer: vec![],
+            fields_in_record: 0,
+        }
+    }
+
+    /// Appends a field to the current record.
+    pub fn write_field<T: WriteCsvField + ?Sized>(&mut self, value: &T) -> std::io::Result<()> {
+        self.field_buffer.clear();
+        value.write_csv_field(&mut self.field_buffer);
+        if self.fields_in_record > 0 {
+            self.buffer.push(self.dialect.delimiter);
+        }
+        self.fields_in_record += 1;
+        let delimiter = self.dialect.delimiter;
+        let quote = self.dialect.quote;
+        let needs_quotes = self
+            .field_buffer
+            .iter()
+            .any(|&c| c == delimiter || c == quote || c == b'\n' || c == b'\r');
+        if needs_quotes {
+            self.buffer.push(quote);
+            for &c in &self.field_buffer {
+                if c == quote {
+                    self.buffer.push(quote);
+                }
+                self.buffer.push(c);
+            }
+            self.buffer.push(quote);
+        } else {
+            self.buffer.extend_from_slice(&self.field_buffer);
+        }
+        Ok(())
+    }
+
+    /// Finishes the current record.
+    pub fn end_record(&mut self) -> std::io::Result<()> {
+        self.buffer.push(b'\n');
+        self.fields_in_record = 0;
+        if self.buffer.len() >= BUFFER_SIZE {
+            self.out.write_all(&self.buffer)?;
+            self.buffer.clear();
+        }
+        Ok(())
+    }
+
+    /// Writes all fields of a record at once.
+    pub fn write_record<T: WriteCsvField>(
+        &mut self,
+        fields: impl IntoIterator<Item = T>,
+    ) -> std::io::Result<()> {
+        for field in fields {
+            self.write_field(&field)?;
+        }
+        self.end_record()
+    }
+
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        self.out.write_all(&self.buffer)?;
+        self.buffer.clear();
+        self.out.flush()
+    }
+}
+
+impl<W: Write> Drop for CsvWriter<W> {
+    fn drop(&mut self) {
+        // Errors can't be reported here. Use `flush` to handle them.
+        let _ = self.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CsvRecords;
+
+    #[test]
+    fn test_write_records() {
+        let mut output = vec![];
+        {
+            let mut writer = CsvWriter::new(&mut output);
+            writer.write_record(["a", "b", "c"]).unwrap();
+            writer.write_record(["1", "", "x,y"]).unwrap();
+            writer
+                .write_record(["say \"hi\"", "line\nbreak", "\r"])
+                .unwrap();
+            writer.write_field(&42).unwrap();
+            writer.write_field(&1.5f32).unwrap();
+            writer.end_record().unwrap();
+            writer.flush().unwrap();
+        }
+        assert_eq!(
+            std::str::from_utf8(&output).unwrap(),
+            "a,b,c\n1,,\"x,y\"\n\"say \"\"hi\"\"\",\"line\nbreak\",\"\r\"\n42,1.5\n"
+        );
+
+        let records = CsvRecords::from_buffer(&output);
+        assert_eq!(records.len(), 4);
+        let record = records.record(2);
+        assert_eq!(record.column_str(0).unwrap().unwrap(), "say \"hi\"");
+        assert_eq!(record.column_str(1).unwrap().unwrap(), "line\nbreak");
+    }
+
+    #[test]
+    fn test_write_with_dialect() {
+        let dialect = CsvDialect {
+            delimiter: b';',
+            quote: b'\'',
+            ..CsvDialect::default()
+        };
+        let mut output = vec![];
+        {
+            let mut writer = CsvWriter::with_dialect(&mut output, dialect);
+            writer.write_record(["a;b", "it's", "x,\"y\""]).unwrap();
+        }
+        assert_eq!(output, b"'a;b';'it''s';x,\"y\"\n");
+    }
+}
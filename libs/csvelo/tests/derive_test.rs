@@ -1,3 +1,6 @@
+use std::borrow::Cow;
+
+use csvelo::ErrorKind;
 use csvelo_derive::CSVParser;
 use indoc::indoc;
 use rayon::prelude::*;
@@ -29,3 +32,256 @@ fn test_simple() {
     assert_eq!(data.d.unwrap(), vec!["4", "40"]);
     assert!(data.e.is_none());
 }
+
+#[test]
+fn test_invalid_fields() {
+    #[derive(CSVParser, Debug)]
+    struct MyCsvData<'a> {
+        a: Option<Vec<i32>>,
+        b: Option<Vec<u8>>,
+        c: Option<Vec<&'a str>>,
+    }
+
+    let (data, records_num, errors) = MyCsvData::from_csv_buffer_with_errors(
+        indoc! {r#"
+            a,b,c
+            1,2,x
+            abc,300,y
+            3,4,z
+            ,5,w
+        "#}
+        .as_bytes(),
+        &csvelo::CsvDialect::default(),
+    )
+    .unwrap();
+    assert_eq!(records_num, 4);
+    assert_eq!(data.a.unwrap(), vec![1, 0, 3, 0]);
+    assert_eq!(data.b.unwrap(), vec![2, 0, 4, 5]);
+    assert_eq!(data.c.unwrap(), vec!["x", "y", "z", "w"]);
+
+    assert_eq!(errors.errors_num(), 3);
+    let a_errors = errors.column("a").unwrap();
+    assert_eq!(a_errors.errors_num, 2);
+    assert_eq!(a_errors.samples[0].error.record_index, Some(1));
+    assert_eq!(a_errors.samples[0].error.kind, ErrorKind::InvalidNumber);
+    assert_eq!(a_errors.samples[0].raw, b"abc");
+    assert_eq!(a_errors.samples[1].error.record_index, Some(3));
+    let b_error = &errors.column("b").unwrap().samples[0];
+    assert_eq!(b_error.raw, b"300");
+    assert_eq!(b_error.error.kind, ErrorKind::NumberOverflow);
+    assert_eq!(b_error.error.column_name, Some("b"));
+    assert_eq!(b_error.error.byte_offset, Some(16));
+    assert!(errors.column("c").is_none());
+}
+
+#[test]
+fn test_errors() {
+    #[derive(CSVParser, Debug)]
+    struct MyCsvData<'a> {
+        a: Option<Vec<u8>>,
+        b: Option<Vec<&'a str>>,
+    }
+
+    let error = MyCsvData::from_csv_buffer(b"a,b\n1,x\n2,\xff\n-1,z\n").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::InvalidUtf8);
+    assert_eq!(error.record_index, Some(1));
+    assert_eq!(error.column_name, Some("b"));
+    assert_eq!(error.byte_offset, Some(10));
+    assert_eq!(
+        error.to_string(),
+        "invalid UTF-8 in column b in record 1 at byte 10"
+    );
+
+    let error = MyCsvData::from_csv_buffer(b"").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::MissingHeader);
+}
+
+#[test]
+fn test_dialect() {
+    #[derive(CSVParser, Debug)]
+    struct MyCsvData<'a> {
+        stop_id: Option<Vec<u32>>,
+        stop_name: Option<Vec<&'a str>>,
+    }
+
+    let dialect = csvelo::CsvDialect {
+        delimiter: b'\t',
+        ..csvelo::CsvDialect::default()
+    };
+    let (data, records_num) = MyCsvData::from_csv_buffer_with_dialect(
+        b"\xEF\xBB\xBFstop_id\tstop_name\n1\t\"A, B\"\n2\tC\n",
+        &dialect,
+    )
+    .unwrap();
+    assert_eq!(records_num, 2);
+    assert_eq!(data.stop_id.unwrap(), vec![1, 2]);
+    assert_eq!(data.stop_name.unwrap(), vec!["A, B", "C"]);
+}
+
+#[test]
+fn test_latin1() {
+    #[derive(CSVParser, Debug)]
+    struct MyCsvData<'a> {
+        stop_id: Option<Vec<&'a str>>,
+        stop_name: Option<Vec<Cow<'a, str>>>,
+        stop_desc: Option<Vec<String>>,
+    }
+
+    let dialect = csvelo::CsvDialect {
+        encoding: csvelo::CsvEncoding::Latin1,
+        ..csvelo::CsvDialect::default()
+    };
+    let (data, records_num) = MyCsvData::from_csv_buffer_with_dialect(
+        b"stop_id,stop_name,stop_desc\n1,M\xFCnchen,\"\"\"Gr\xFC\xDF\"\"\"\n2,Berlin,\n",
+        &dialect,
+    )
+    .unwrap();
+    assert_eq!(records_num, 2);
+    assert_eq!(data.stop_id.unwrap(), vec!["1", "2"]);
+    let stop_name = data.stop_name.unwrap();
+    assert_eq!(stop_name, vec!["München", "Berlin"]);
+    assert!(matches!(stop_name[1], Cow::Borrowed(_)));
+    assert_eq!(data.stop_desc.unwrap(), vec!["\"Grüß\"", ""]);
+}
+
+#[test]
+fn test_escaped_quotes() {
+    #[derive(CSVParser, Debug)]
+    struct MyCsvData<'a> {
+        raw: Option<Vec<&'a str>>,
+        text: Option<Vec<Cow<'a, str>>>,
+        owned: Option<Vec<String>>,
+    }
+
+    let (data, records_num) = MyCsvData::from_csv_buffer(
+        indoc! {r#"
+            raw,text,owned
+            "a,b","this,is a "" test","""c"""
+            d,e,f
+        "#}
+        .as_bytes(),
+    )
+    .unwrap();
+    assert_eq!(records_num, 2);
+    assert_eq!(data.raw.unwrap(), vec!["a,b", "d"]);
+    let text = data.text.unwrap();
+    assert_eq!(text, vec!["this,is a \" test", "e"]);
+    assert!(matches!(text[1], Cow::Borrowed("e")));
+    assert_eq!(data.owned.unwrap(), vec!["\"c\"", "f"]);
+
+    // Borrowed text can't be unescaped.
+    let (data, _, errors) = MyCsvData::from_csv_buffer_with_errors(
+        b"raw,text\n\"a \"\"b\"\"\",\"a \"\"b\"\"\"\n",
+        &csvelo::CsvDialect::default(),
+    )
+    .unwrap();
+    assert_eq!(data.raw.unwrap(), vec![""]);
+    assert_eq!(data.text.unwrap(), vec!["a \"b\""]);
+    let raw_error = &errors.column("raw").unwrap().samples[0];
+    assert_eq!(raw_error.error.kind, ErrorKind::EscapedQuote);
+
+    let dialect = csvelo::CsvDialect {
+        quote: b'\'',
+        ..csvelo::CsvDialect::default()
+    };
+    let (data, _) =
+        MyCsvData::from_csv_buffer_with_dialect(b"text,owned\n'it''s','a''b'\n", &dialect).unwrap();
+    assert_eq!(data.text.unwrap(), vec!["it's"]);
+    assert_eq!(data.owned.unwrap(), vec!["a'b"]);
+}
+
+#[test]
+fn test_write_csv() {
+    #[derive(CSVParser, csvelo::CSVWriter, Debug)]
+    struct MyCsvData<'a> {
+        a: Option<Vec<i32>>,
+        b: Option<Vec<f32>>,
+        c: Option<Vec<Cow<'a, str>>>,
+        d: Option<Vec<&'a str>>,
+    }
+
+    let data = MyCsvData {
+        a: Some(vec![1, -2]),
+        b: None,
+        c: Some(vec!["x,y".into(), "say \"hi\"".into()]),
+        d: Some(vec!["plain", ""]),
+    };
+    let mut output = vec![];
+    assert_eq!(data.write_csv(&mut output).unwrap(), 2);
+    assert_eq!(
+        std::str::from_utf8(&output).unwrap(),
+        indoc! {r#"
+            a,c,d
+            1,"x,y",plain
+            -2,"say ""hi""",
+        "#}
+    );
+
+    let (parsed, records_num) = MyCsvData::from_csv_buffer(&output).unwrap();
+    assert_eq!(records_num, 2);
+    assert_eq!(parsed.a, data.a);
+    assert!(parsed.b.is_none());
+    assert_eq!(parsed.c, data.c);
+    assert_eq!(parsed.d, data.d);
+
+    let dialect = csvelo::CsvDialect {
+        delimiter: b'\t',
+        ..csvelo::CsvDialect::default()
+    };
+    let mut output = vec![];
+    data.write_csv_with_dialect(&mut output, &dialect).unwrap();
+    assert_eq!(
+        std::str::from_utf8(&output).unwrap(),
+        "a\tc\td\n1\tx,y\tplain\n-2\t\"say \"\"hi\"\"\"\t\n"
+    );
+}
+
+#[test]
+fn test_columns() {
+    use csvelo::CsvColumns;
+
+    #[derive(CSVParser, csvelo::CSVColumns, Debug, Default)]
+    struct MyCsvData<'a> {
+        a: Option<Vec<i32>>,
+        b: Option<Vec<&'a str>>,
+        c: Option<Vec<f32>>,
+    }
+
+    let mut data = MyCsvData {
+        a: Some(vec![1, 2, 3]),
+        b: None,
+        c: Some(vec![1.0, 2.0, 3.0]),
+    };
+    data.retain_records(&[true, false, true]);
+    assert_eq!(data.a, Some(vec![1, 3]));
+    assert_eq!(data.c, Some(vec![1.0, 3.0]));
+
+    let other = MyCsvData {
+        a: Some(vec![4]),
+        b: Some(vec!["x"]),
+        c: None,
+    };
+    data.append_records(2, other, 1);
+    assert_eq!(data.a, Some(vec![1, 3, 4]));
+    assert_eq!(data.b, Some(vec!["", "", "x"]));
+    assert_eq!(data.c, Some(vec![1.0, 3.0, 0.0]));
+
+    data.append_records(3, MyCsvData::default(), 2);
+    assert_eq!(data.a, Some(vec![1, 3, 4, 0, 0]));
+}
+
+#[test]
+#[should_panic(expected = "keep must have one entry per record of column a")]
+fn test_retain_records_with_short_keep() {
+    use csvelo::CsvColumns;
+
+    #[derive(csvelo::CSVColumns)]
+    struct MyCsvData {
+        a: Option<Vec<i32>>,
+    }
+
+    let mut data = MyCsvData {
+        a: Some(vec![1, 2, 3]),
+    };
+    data.retain_records(&[true]);
+}
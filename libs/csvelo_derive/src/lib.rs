@@ -5,7 +5,8 @@ use syn::{parse_macro_input, DeriveInput, GenericParam, Ident, Lifetime};
 /// Derives a CSV parser for a struct.
 ///
 /// The struct is expected to have fields of the type `Option<Vec<T>>` whereby `T` has
-/// to implement the `ParseCsvField` trait.
+/// to implement the `ParseCsvField` and `Default` traits. Fields that can't be parsed get
+/// the default value and are reported by `from_csv_buffer_with_errors`.
 #[proc_macro_derive(CSVParser)]
 pub fn derive(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let input = parse_macro_input!(input as syn::DeriveInput);
@@ -30,6 +31,37 @@ pub fn derive(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     expanded.into()
 }
 
+/// Derives a CSV writer for a struct that has the same layout as the ones for [`CSVParser`].
+///
+/// The field types have to implement the `WriteCsvField` trait. Columns that are `None` are
+/// omitted in the output.
+#[proc_macro_derive(CSVWriter)]
+pub fn derive_writer(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as syn::DeriveInput);
+
+    let source_info = match parse_source_info(&input) {
+        Ok(source_info) => source_info,
+        Err(_) => panic!(),
+    };
+
+    generate_write_function(&source_info).into()
+}
+
+/// Implements `CsvColumns` for a struct that has the same layout as the ones for [`CSVParser`].
+///
+/// The field types have to implement `Default`.
+#[proc_macro_derive(CSVColumns)]
+pub fn derive_columns(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as syn::DeriveInput);
+
+    let source_info = match parse_source_info(&input) {
+        Ok(source_info) => source_info,
+        Err(_) => panic!(),
+    };
+
+    generate_columns_impl(&source_info).into()
+}
+
 struct SourceInfo<'a> {
     main_name: Ident,
     header_name: Ident,
@@ -43,7 +75,7 @@ struct ColumnFieldInfo {
     name: Ident,
 }
 
-fn parse_source_info(input: &DeriveInput) -> Result<SourceInfo, ()> {
+fn parse_source_info(input: &DeriveInput) -> Result<SourceInfo<'_>, ()> {
     let mut csv_struct_fields = Vec::new();
     if let syn::Data::Struct(ref data) = input.data {
         if let syn::Fields::Named(ref fields) = data.fields {
@@ -78,10 +110,10 @@ fn parse_source_info(input: &DeriveInput) -> Result<SourceInfo, ()> {
     };
     Ok(SourceInfo {
         main_name: input.ident.clone(),
-        header_name: header_name,
+        header_name,
         csv_struct_fields,
         input,
-        buffer_lifetimes_bound: buffer_lifetimes_bound,
+        buffer_lifetimes_bound,
     })
 }
 
@@ -110,7 +142,7 @@ fn generate_header_parser_function(source_info: &SourceInfo) -> proc_macro2::Tok
     });
     quote! {
         impl #header_name {
-            fn from_header_chunk(header: csvelo::CsvHeader) -> std::result::Result<Self, ()> {
+            fn from_header_chunk(header: csvelo::CsvHeader) -> csvelo::Result<Self> {
                 Ok(Self {
                     #(#parts),*
                 })
@@ -124,26 +156,33 @@ fn generate_chunk_parsing_function(source_info: &SourceInfo) -> proc_macro2::Tok
     let main_name = &source_info.main_name;
     let parts = source_info.csv_struct_fields.iter().map(|f| {
         let name = &f.name;
-            quote! {
-                #name: if let Some(column_i) = header.#name {
-                    csvelo::parse_column_value(
-                        records, column_i, |buffer| Ok(csvelo::ParseCsvField::parse_csv_field(buffer)?)).ok()
-                } else {
-                    None
-                }
+        quote! {
+            #name: if let Some(column_i) = header.#name {
+                let (values, column_errors) = csvelo::parse_column_value(
+                    buffer, records, column_i, stringify!(#name), dialect, csvelo::ParseCsvField::parse_csv_field_with_dialect);
+                errors.push((stringify!(#name), column_errors));
+                Some(values)
+            } else {
+                None
             }
+        }
     });
     let (impl_generics, ty_generics, where_clause) = source_info.input.generics.split_for_impl();
     let buffer_lifetimes_bound = &source_info.buffer_lifetimes_bound;
     quote! {
         impl #impl_generics #main_name #ty_generics #where_clause {
+            /// Parses the records of a chunk. The errors are returned for every parsed column.
             fn parse_csv_chunk<'buffer>(
                 header: &#header_name,
+                buffer: &'buffer [u8],
                 records: &csvelo::CsvRecords<'buffer>,
-            ) -> std::result::Result<Self, ()> #buffer_lifetimes_bound {
-                Ok(Self {
+                dialect: &csvelo::CsvDialect,
+            ) -> csvelo::Result<(Self, Vec<(&'static str, csvelo::CsvColumnErrors)>)> #buffer_lifetimes_bound {
+                let mut errors = vec![];
+                let parsed = Self {
                     #(#parts),*
-                })
+                };
+                Ok((parsed, errors))
             }
         }
     }
@@ -188,7 +227,7 @@ fn generate_reduce_function(source_info: &SourceInfo) -> proc_macro2::TokenStrea
     let (impl_generics, ty_generics, where_clause) = source_info.input.generics.split_for_impl();
     quote! {
         impl #impl_generics #main_name #ty_generics #where_clause {
-            fn from_csv_parse_chunks(header: &#header_name, chunks: Vec<Self>) -> std::result::Result<Self, ()> {
+            fn from_csv_parse_chunks(header: &#header_name, chunks: Vec<Self>) -> csvelo::Result<Self> {
                 #(#setup_parts)*
                 rayon::scope(|s| {
                     #(#process_parts)*
@@ -208,26 +247,180 @@ fn generate_full_parse_function(source_info: &SourceInfo) -> proc_macro2::TokenS
     let buffer_lifetimes_bound = &source_info.buffer_lifetimes_bound;
     quote! {
         impl #impl_generics #main_name #ty_generics #where_clause {
-            pub fn from_csv_buffer<'buffer>(buffer: &'buffer [u8])  -> std::result::Result<(Self, usize), ()> #buffer_lifetimes_bound {
-                let sections = csvelo::split_header_and_data(buffer);
-                let header = csvelo::parse_header(sections.header);
+            /// Parses the buffer and fails on the first field that can't be parsed.
+            pub fn from_csv_buffer<'buffer>(buffer: &'buffer [u8])  -> csvelo::Result<(Self, usize)> #buffer_lifetimes_bound {
+                #main_name::from_csv_buffer_with_dialect(buffer, &csvelo::CsvDialect::default())
+            }
+
+            /// Same as `from_csv_buffer` but for buffers that use a different CSV dialect.
+            pub fn from_csv_buffer_with_dialect<'buffer>(buffer: &'buffer [u8], dialect: &csvelo::CsvDialect)  -> csvelo::Result<(Self, usize)> #buffer_lifetimes_bound {
+                let (parsed, records_num, errors) = #main_name::from_csv_buffer_with_errors(buffer, dialect)?;
+                let first_error = errors
+                    .columns
+                    .into_iter()
+                    .filter_map(|(_, column_errors)| column_errors.samples.into_iter().next())
+                    .map(|sample| sample.error)
+                    .min_by_key(|error| (error.record_index, error.byte_offset));
+                match first_error {
+                    Some(error) => Err(error),
+                    None => Ok((parsed, records_num)),
+                }
+            }
+
+            /// Same as `from_csv_buffer_with_dialect` but also returns the fields that could not be
+            /// parsed instead of failing. These fields have the default value in the parsed data.
+            pub fn from_csv_buffer_with_errors<'buffer>(buffer: &'buffer [u8], dialect: &csvelo::CsvDialect)  -> csvelo::Result<(Self, usize, csvelo::CsvParseErrors)> #buffer_lifetimes_bound {
+                let sections = csvelo::split_header_and_data_with_dialect(buffer, dialect);
+                if sections.header.trim_ascii().is_empty() {
+                    return Err(csvelo::Error {
+                        byte_offset: Some(0),
+                        ..csvelo::Error::new(csvelo::ErrorKind::MissingHeader)
+                    });
+                }
+                let header = csvelo::parse_header_with_dialect(sections.header, dialect);
                 let header = #header_name::from_header_chunk(header)?;
-                let data_chunks = csvelo::split_csv_buffer_into_record_aligned_chunks(sections.data, 256 * 1024);
+                let data_chunks = csvelo::split_csv_buffer_into_record_aligned_chunks_with_dialect(sections.data, 256 * 1024, dialect);
                 let parsed_chunks = data_chunks.par_iter().map(|chunk| {
-                    let records = csvelo::CsvRecords::from_buffer(chunk);
+                    let records = csvelo::CsvRecords::from_buffer_with_dialect(chunk, dialect);
                     let size = records.len();
-                    match #main_name::parse_csv_chunk(&header, &records) {
-                        Ok(parsed_chunk) => Ok((parsed_chunk, size)),
+                    match #main_name::parse_csv_chunk(&header, buffer, &records, dialect) {
+                        Ok((parsed_chunk, errors)) => Ok((parsed_chunk, size, errors)),
                         Err(err) => Err(err),
                     }
                 }).collect::<std::result::Result<Vec<_>, _>>()?;
-                let records_num = parsed_chunks.iter().map(|(_, size)| *size).sum::<usize>();
-                let parsed_chunks = parsed_chunks.into_iter().map(|(chunk, _)| chunk).collect();
-                match #main_name::from_csv_parse_chunks(&header, parsed_chunks) {
-                    Ok(parsed) => Ok((parsed, records_num)),
+
+                let mut errors = csvelo::CsvParseErrors::default();
+                let mut records_num = 0;
+                let mut chunks = Vec::with_capacity(parsed_chunks.len());
+                for (chunk, size, chunk_errors) in parsed_chunks {
+                    for (column_name, column_errors) in chunk_errors {
+                        if column_errors.is_empty() {
+                            continue;
+                        }
+                        match errors.columns.iter_mut().find(|(name, _)| *name == column_name) {
+                            Some((_, errors)) => errors.extend_from_chunk(column_errors, records_num),
+                            None => {
+                                let mut errors_for_column = csvelo::CsvColumnErrors::default();
+                                errors_for_column.extend_from_chunk(column_errors, records_num);
+                                errors.columns.push((column_name, errors_for_column));
+                            }
+                        }
+                    }
+                    records_num += size;
+                    chunks.push(chunk);
+                }
+                match #main_name::from_csv_parse_chunks(&header, chunks) {
+                    Ok(parsed) => Ok((parsed, records_num, errors)),
                     Err(err) => Err(err),
                 }
             }
         }
     }
 }
+
+fn generate_write_function(source_info: &SourceInfo) -> proc_macro2::TokenStream {
+    let main_name = &source_info.main_name;
+    let column_title_parts = source_info.csv_struct_fields.iter().map(|f| {
+        let name = &f.name;
+        quote! {
+            if self.#name.is_some() {
+                column_titles.push(stringify!(#name));
+            }
+        }
+    });
+    let column_len_parts = source_info.csv_struct_fields.iter().map(|f| {
+        let name = &f.name;
+        quote! {
+            self.#name.as_ref().map(|column| column.len())
+        }
+    });
+    let write_field_parts = source_info.csv_struct_fields.iter().map(|f| {
+        let name = &f.name;
+        quote! {
+            if let Some(column) = self.#name.as_ref() {
+                writer.write_field(&column.get(record_i))?;
+            }
+        }
+    });
+    let (impl_generics, ty_generics, where_clause) = source_info.input.generics.split_for_impl();
+    quote! {
+        impl #impl_generics #main_name #ty_generics #where_clause {
+            /// Writes all columns that are not `None` as CSV including the header. Nothing is
+            /// written if there are no columns. Returns the number of written records.
+            pub fn write_csv<W: std::io::Write>(&self, out: W) -> std::io::Result<usize> {
+                self.write_csv_with_dialect(out, &csvelo::CsvDialect::default())
+            }
+
+            /// Same as `write_csv` but uses the delimiter and quote of the given dialect.
+            pub fn write_csv_with_dialect<W: std::io::Write>(&self, out: W, dialect: &csvelo::CsvDialect) -> std::io::Result<usize> {
+                let mut column_titles: Vec<&'static str> = vec![];
+                #(#column_title_parts)*
+                if column_titles.is_empty() {
+                    return Ok(0);
+                }
+                // All columns should have the same length. Shorter columns are padded with
+                // empty fields.
+                let records_num = [#(#column_len_parts),*].into_iter().flatten().max().unwrap_or(0);
+                let mut writer = csvelo::CsvWriter::with_dialect(out, *dialect);
+                writer.write_record(column_titles)?;
+                for record_i in 0..records_num {
+                    #(#write_field_parts)*
+                    writer.end_record()?;
+                }
+                writer.flush()?;
+                Ok(records_num)
+            }
+        }
+    }
+}
+
+fn generate_columns_impl(source_info: &SourceInfo) -> proc_macro2::TokenStream {
+    let main_name = &source_info.main_name;
+    let retain_parts = source_info.csv_struct_fields.iter().map(|f| {
+        let name = &f.name;
+        quote! {
+            if let Some(column) = self.#name.as_mut() {
+                assert_eq!(
+                    column.len(),
+                    keep.len(),
+                    "keep must have one entry per record of column {}",
+                    stringify!(#name)
+                );
+                let mut keep_iter = keep.iter();
+                column.retain(|_| *keep_iter.next().unwrap());
+            }
+        }
+    });
+    let append_parts = source_info.csv_struct_fields.iter().map(|f| {
+        let name = &f.name;
+        quote! {
+            match (self.#name.as_mut(), other.#name) {
+                (Some(column), Some(mut other_column)) => {
+                    column.append(&mut other_column);
+                }
+                (Some(column), None) => {
+                    column.resize_with(len + other_len, Default::default);
+                }
+                (None, Some(other_column)) => {
+                    let mut column = Vec::with_capacity(len + other_len);
+                    column.resize_with(len, Default::default);
+                    column.extend(other_column);
+                    self.#name = Some(column);
+                }
+                (None, None) => {}
+            }
+        }
+    });
+    let (impl_generics, ty_generics, where_clause) = source_info.input.generics.split_for_impl();
+    quote! {
+        impl #impl_generics csvelo::CsvColumns for #main_name #ty_generics #where_clause {
+            fn retain_records(&mut self, keep: &[bool]) {
+                #(#retain_parts)*
+            }
+
+            fn append_records(&mut self, len: usize, other: Self, other_len: usize) {
+                #(#append_parts)*
+            }
+        }
+    }
+}
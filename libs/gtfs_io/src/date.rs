@@ -0,0 +1,180 @@
+use std::io::Write;
+
+/// A calendar date as used in GTFS, e.g. `20250131`.
+///
+/// The default value is not a valid date. It's used for fields that could not be parsed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Weekday {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+impl Weekday {
+    /// All weekdays in the order of the columns in calendar.txt.
+    pub const ALL: [Weekday; 7] = [
+        Weekday::Monday,
+        Weekday::Tuesday,
+        Weekday::Wednesday,
+        Weekday::Thursday,
+        Weekday::Friday,
+        Weekday::Saturday,
+        Weekday::Sunday,
+    ];
+
+    /// Monday is 0 and Sunday is 6.
+    pub fn index(self) -> usize {
+        self as usize
+    }
+}
+
+impl Date {
+    /// Returns `None` if the day does not exist in the given month.
+    pub fn new(year: u16, month: u8, day: u8) -> Option<Self> {
+        let date = Self { year, month, day };
+        date.is_valid().then_some(date)
+    }
+
+    pub fn is_valid(&self) -> bool {
+        (1..=12).contains(&self.month) && self.day >= 1 && self.day <= self.days_in_month()
+    }
+
+    pub fn is_leap_year(&self) -> bool {
+        let year = self.year as u32;
+        (year.is_multiple_of(4) && !year.is_multiple_of(100)) || year.is_multiple_of(400)
+    }
+
+    /// Only meaningful for valid dates.
+    pub fn days_in_month(&self) -> u8 {
+        match self.month {
+            1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+            4 | 6 | 9 | 11 => 30,
+            2 if self.is_leap_year() => 29,
+            2 => 28,
+            _ => 0,
+        }
+    }
+
+    /// Number of days since 1970-01-01, which is negative for earlier dates.
+    pub fn days_since_epoch(&self) -> i32 {
+        // Based on the algorithms in http://howardhinnant.github.io/date_algorithms.html.
+        let (month, day) = (self.month as i32, self.day as i32);
+        let year = self.year as i32 - (month <= 2) as i32;
+        let era = year.div_euclid(400);
+        let year_of_era = year - era * 400;
+        let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        era * 146097 + day_of_era - 719468
+    }
+
+    /// The inverse of [`Date::days_since_epoch`].
+    pub fn from_days_since_epoch(days: i32) -> Self {
+        let days = days + 719468;
+        let era = days.div_euclid(146097);
+        let day_of_era = days - era * 146097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month_part = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * month_part + 2) / 5 + 1;
+        let month = if month_part < 10 {
+            month_part + 3
+        } else {
+            month_part - 9
+        };
+        let year = year_of_era + era * 400 + (month <= 2) as i32;
+        Self {
+            year: year as u16,
+            month: month as u8,
+            day: day as u8,
+        }
+    }
+
+    pub fn add_days(self, days: i32) -> Self {
+        Self::from_days_since_epoch(self.days_since_epoch() + days)
+    }
+
+    /// Number of days from this date to the other date. It's negative if the other date is
+    /// earlier.
+    pub fn days_until(&self, other: &Date) -> i32 {
+        other.days_since_epoch() - self.days_since_epoch()
+    }
+
+    pub fn weekday(&self) -> Weekday {
+        // The unix epoch was a Thursday.
+        Weekday::ALL[(self.days_since_epoch() + 3).rem_euclid(7) as usize]
+    }
+
+    /// Iterates over all days from `first` to `last`, including both. The iterator is empty if
+    /// `last` is before `first`.
+    pub fn range_inclusive(first: Date, last: Date) -> impl Iterator<Item = Date> {
+        (first.days_since_epoch()..=last.days_since_epoch()).map(Date::from_days_since_epoch)
+    }
+}
+
+impl std::fmt::Display for Date {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+impl<'a> csvelo::ParseCsvField<'a> for Date {
+    fn parse_csv_field(buffer: &'a [u8]) -> csvelo::Result<Self>
+    where
+        Self: 'a,
+    {
+        let buffer = buffer.trim_ascii();
+        if buffer.len() != 8 || !buffer.iter().all(u8::is_ascii_digit) {
+            return Err(csvelo::Error::new(csvelo::ErrorKind::InvalidValue));
+        }
+        let number = |range: std::ops::Range<usize>| {
+            buffer[range]
+                .iter()
+                .fold(0u16, |value, c| value * 10 + (c - b'0') as u16)
+        };
+        Date::new(number(0..4), number(4..6) as u8, number(6..8) as u8)
+            .ok_or_else(|| csvelo::Error::new(csvelo::ErrorKind::InvalidValue))
+    }
+}
+
+impl csvelo::WriteCsvField for Date {
+    fn write_csv_field(&self, buffer: &mut Vec<u8>) {
+        write!(buffer, "{:04}{:02}{:02}", self.year, self.month, self.day).unwrap();
+    }
+}
+
+/// A date in a column where empty fields are allowed. Fields that are not empty still have to be
+/// valid dates.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OptionalDate(pub Option<Date>);
+
+impl<'a> csvelo::ParseCsvField<'a> for OptionalDate {
+    fn parse_csv_field(buffer: &'a [u8]) -> csvelo::Result<Self>
+    where
+        Self: 'a,
+    {
+        if buffer.trim_ascii().is_empty() {
+            return Ok(OptionalDate(None));
+        }
+        Date::parse_csv_field(buffer).map(|date| OptionalDate(Some(date)))
+    }
+}
+
+impl csvelo::WriteCsvField for OptionalDate {
+    fn write_csv_field(&self, buffer: &mut Vec<u8>) {
+        if let Some(date) = &self.0 {
+            date.write_csv_field(buffer);
+        }
+    }
+}
@@ -0,0 +1,241 @@
+use std::fmt::Debug;
+
+use crate::*;
+
+/// Whether a file has to be part of a GTFS feed according to the specification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GtfsFilePresence {
+    Required,
+    Optional,
+    /// Required or forbidden depending on other files or fields in the feed.
+    ConditionallyRequired,
+}
+
+/// Generates [`Gtfs`], [`GtfsFile`] and the buffer and filter structs that have one entry per
+/// file, so that all of them agree on the supported files and their names.
+macro_rules! define_gtfs_files {
+    ($(($name:ident, $ty:ident, $file_name:literal, $presence:ident)),* $(,)?) => {
+        pub struct Gtfs<'a> {
+            $(pub $name: File<$ty<'a>>,)*
+        }
+
+        /// Identifies a file in a GTFS feed.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum GtfsFile {
+            $($ty,)*
+        }
+
+        impl GtfsFile {
+            /// All files that are supported by this crate.
+            pub const ALL: &[GtfsFile] = &[$(GtfsFile::$ty,)*];
+
+            /// Name of the file in a GTFS archive, e.g. `stop_times.txt`.
+            pub fn file_name(self) -> &'static str {
+                match self {
+                    $(GtfsFile::$ty => $file_name,)*
+                }
+            }
+
+            pub fn presence(self) -> GtfsFilePresence {
+                match self {
+                    $(GtfsFile::$ty => GtfsFilePresence::$presence,)*
+                }
+            }
+        }
+
+        impl<'a> Gtfs<'a> {
+            /// Parses the provided buffers into GTFS data.
+            pub fn from_buffers(buffers: GtfsBufferSlices<'a>) -> anyhow::Result<Self> {
+                Self::from_buffers_with_dialect(buffers, &csvelo::CsvDialect::default())
+            }
+
+            /// Parses buffers that don't follow the CSV format required by the specification,
+            /// e.g. because they use a different delimiter or encoding. Ids are borrowed from the
+            /// buffers, so they have to be ASCII if the encoding is not UTF-8.
+            pub fn from_buffers_with_dialect(
+                buffers: GtfsBufferSlices<'a>,
+                dialect: &csvelo::CsvDialect,
+            ) -> anyhow::Result<Self> {
+                Ok(Self {
+                    $($name: match buffers.$name {
+                        Some(buffer) => match <$ty>::from_csv_buffer_with_errors(buffer, dialect) {
+                            Ok((data, len, parse_errors)) => File {
+                                len,
+                                data: Some(data),
+                                parse_errors,
+                                error: None,
+                            },
+                            Err(error) => File {
+                                error: Some(error),
+                                ..File::default()
+                            },
+                        },
+                        None => File::default(),
+                    },)*
+                })
+            }
+
+            /// Get the error for each file that could not be parsed at all.
+            pub fn file_errors(&self) -> Vec<(GtfsFile, Option<&csvelo::Error>)> {
+                vec![$((GtfsFile::$ty, self.$name.error.as_ref()),)*]
+            }
+
+            /// Get the fields that could not be parsed in each file.
+            pub fn file_parse_errors(&self) -> Vec<(GtfsFile, &csvelo::CsvParseErrors)> {
+                vec![$((GtfsFile::$ty, &self.$name.parse_errors),)*]
+            }
+
+            /// Get the number of records in each file. Files that could not be loaded have zero records.
+            pub fn file_lens(&self) -> Vec<(GtfsFile, usize)> {
+                vec![$((GtfsFile::$ty, self.$name.len),)*]
+            }
+
+            /// Writes the columns that are available in the given file as CSV. Nothing is
+            /// written if the file is not loaded or has no columns. Returns the number of
+            /// written records.
+            pub fn write_file_csv<W: std::io::Write>(
+                &self,
+                file: GtfsFile,
+                out: W,
+            ) -> std::io::Result<usize> {
+                match file {
+                    $(GtfsFile::$ty => match self.$name.data.as_ref() {
+                        Some(data) => data.write_csv(out),
+                        None => Ok(0),
+                    },)*
+                }
+            }
+        }
+
+        /// A feed without any loaded files.
+        impl Default for Gtfs<'_> {
+            fn default() -> Self {
+                Self {
+                    $($name: File::default(),)*
+                }
+            }
+        }
+
+        impl Debug for Gtfs<'_> {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.debug_struct("Gtfs")
+                    $(.field(stringify!($name), &self.$name.len))*
+                    .finish()
+            }
+        }
+
+        /// Contains references to buffers which generally wrap the .txt files in a GTFS archive.
+        /// This is usually created with [`GtfsBuffers::from_dir`] or [`GtfsBuffersMmap::from_dir`]
+        /// and their `.to_slices()` method.
+        #[derive(Debug, Default, Clone, Copy)]
+        pub struct GtfsBufferSlices<'a> {
+            $(pub $name: Option<&'a [u8]>,)*
+        }
+
+        impl<'a> GtfsBufferSlices<'a> {
+            /// Get the buffer of the given file if it is available.
+            pub fn get(&self, file: GtfsFile) -> Option<&'a [u8]> {
+                match file {
+                    $(GtfsFile::$ty => self.$name,)*
+                }
+            }
+        }
+
+        /// Owns a vector for each file in a GTFS archive.
+        pub struct GtfsBuffers {
+            $(pub $name: Option<Vec<u8>>,)*
+        }
+
+        /// Similar to [`GtfsBuffers`] but does not make copies of the buffers.
+        /// This can be much more efficient with large datasets but is unsafe when
+        /// the underlying file is changed while it is read.
+        pub struct GtfsBuffersMmap {
+            $(pub $name: Option<memmap2::Mmap>,)*
+        }
+
+        #[derive(Debug, Clone)]
+        pub struct GtfsFilter {
+            $(pub $name: bool,)*
+        }
+
+        impl GtfsFilter {
+            pub fn all() -> Self {
+                Self {
+                    $($name: true,)*
+                }
+            }
+
+            pub fn none() -> Self {
+                Self {
+                    $($name: false,)*
+                }
+            }
+
+            pub fn contains(&self, file: GtfsFile) -> bool {
+                match file {
+                    $(GtfsFile::$ty => self.$name,)*
+                }
+            }
+        }
+
+        impl GtfsBuffers {
+            /// Creates the buffers by calling the given function for every supported file.
+            pub fn from_fn(mut load: impl FnMut(GtfsFile) -> Option<Vec<u8>>) -> Self {
+                Self {
+                    $($name: load(GtfsFile::$ty),)*
+                }
+            }
+
+            /// Get the slices owned by this instance to use with [`Gtfs::from_buffers`].
+            pub fn to_slices(&self) -> GtfsBufferSlices<'_> {
+                GtfsBufferSlices {
+                    $($name: self.$name.as_deref(),)*
+                }
+            }
+        }
+
+        impl GtfsBuffersMmap {
+            /// Creates the buffers by calling the given function for every supported file.
+            pub fn from_fn(mut load: impl FnMut(GtfsFile) -> Option<memmap2::Mmap>) -> Self {
+                Self {
+                    $($name: load(GtfsFile::$ty),)*
+                }
+            }
+
+            /// Get the slices owned by this instance to use with [`Gtfs::from_buffers`].
+            pub fn to_slices(&self) -> GtfsBufferSlices<'_> {
+                GtfsBufferSlices {
+                    $($name: self.$name.as_deref(),)*
+                }
+            }
+        }
+    };
+}
+
+define_gtfs_files! {
+    (agencies, Agencies, "agency.txt", Required),
+    (stops, Stops, "stops.txt", ConditionallyRequired),
+    (routes, Routes, "routes.txt", Required),
+    (trips, Trips, "trips.txt", Required),
+    (stop_times, StopTimes, "stop_times.txt", Required),
+    (calendars, Calendar, "calendar.txt", ConditionallyRequired),
+    (calendar_dates, CalendarDates, "calendar_dates.txt", ConditionallyRequired),
+    (fare_attributes, FareAttributes, "fare_attributes.txt", Optional),
+    (fare_rules, FareRules, "fare_rules.txt", Optional),
+    (timeframes, Timeframes, "timeframes.txt", Optional),
+    (rider_categories, RiderCategories, "rider_categories.txt", Optional),
+    (fare_media, FareMedia, "fare_media.txt", Optional),
+    (fare_products, FareProducts, "fare_products.txt", Optional),
+    (fare_leg_rules, FareLegRules, "fare_leg_rules.txt", Optional),
+    (fare_transfer_rules, FareTransferRules, "fare_transfer_rules.txt", Optional),
+    (areas, Areas, "areas.txt", Optional),
+    (stop_areas, StopAreas, "stop_areas.txt", Optional),
+    (shapes, Shapes, "shapes.txt", Optional),
+    (frequencies, Frequencies, "frequencies.txt", Optional),
+    (transfers, Transfers, "transfers.txt", Optional),
+    (pathways, Pathways, "pathways.txt", Optional),
+    (levels, Levels, "levels.txt", ConditionallyRequired),
+    (translations, Translations, "translations.txt", Optional),
+    (feed_infos, FeedInfos, "feed_info.txt", ConditionallyRequired),
+    (attributions, Attributions, "attributions.txt", Optional),
+}
@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+
+use crate::{Frequencies, Gtfs, ServiceDayTime, StopTimes};
+
+/// A concrete trip instance generated from a headway-based template trip in frequencies.txt.
+#[derive(Debug, Clone)]
+pub struct ExpandedTrip<'a> {
+    /// The id of the template trip.
+    pub trip_id: &'a str,
+    /// Index of the row in frequencies.txt that this instance was generated from.
+    pub frequency_i: usize,
+    /// Departure time at the first stop of this instance.
+    pub start_time: ServiceDayTime,
+    /// Stop times of this instance, sorted by `stop_sequence`.
+    pub stop_times: Vec<ExpandedStopTime>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ExpandedStopTime {
+    /// Index of the template row in stop_times.txt.
+    pub stop_time_i: usize,
+    pub arrival_time: Option<ServiceDayTime>,
+    pub departure_time: Option<ServiceDayTime>,
+}
+
+impl Frequencies<'_> {
+    /// Computes the start times of all trip instances described by the given row.
+    /// The `end_time` is exclusive. Rows with missing times or a zero headway produce no trips.
+    pub fn trip_start_times(&self, frequency_i: usize) -> Vec<ServiceDayTime> {
+        let (Some(start_times), Some(end_times), Some(headways)) = (
+            self.start_time.as_ref(),
+            self.end_time.as_ref(),
+            self.headway_secs.as_ref(),
+        ) else {
+            return vec![];
+        };
+        let (Some(start), Some(end)) = (start_times[frequency_i].0, end_times[frequency_i].0)
+        else {
+            return vec![];
+        };
+        let headway = headways[frequency_i];
+        if headway == 0 {
+            return vec![];
+        }
+        (start.seconds()..end.seconds())
+            .step_by(headway as usize)
+            .map(ServiceDayTime::from_seconds)
+            .collect()
+    }
+}
+
+impl<'a> Gtfs<'a> {
+    /// Turns the template trips referenced by frequencies.txt into concrete trip instances.
+    /// The times of the template trip are shifted so that each instance departs at one of
+    /// the start times computed by [`Frequencies::trip_start_times`].
+    pub fn expand_frequencies(&self) -> Vec<ExpandedTrip<'a>> {
+        let (Some(frequencies), Some(stop_times)) = (
+            self.frequencies.data.as_ref(),
+            self.stop_times.data.as_ref(),
+        ) else {
+            return vec![];
+        };
+        expand_frequencies(frequencies, stop_times)
+    }
+}
+
+/// See [`Gtfs::expand_frequencies`].
+pub fn expand_frequencies<'a>(
+    frequencies: &Frequencies<'a>,
+    stop_times: &StopTimes<'a>,
+) -> Vec<ExpandedTrip<'a>> {
+    let Some(frequency_trip_ids) = frequencies.trip_id.as_ref() else {
+        return vec![];
+    };
+    let (Some(trip_ids), Some(stop_sequences)) = (
+        stop_times.trip_id.as_ref(),
+        stop_times.stop_sequence.as_ref(),
+    ) else {
+        return vec![];
+    };
+
+    // Find the stop time rows of all template trips.
+    let mut template_rows: HashMap<&str, Vec<usize>> = frequency_trip_ids
+        .iter()
+        .map(|trip_id| (*trip_id, vec![]))
+        .collect();
+    for (stop_time_i, trip_id) in trip_ids.iter().enumerate() {
+        if let Some(rows) = template_rows.get_mut(trip_id) {
+            rows.push(stop_time_i);
+        }
+    }
+    for rows in template_rows.values_mut() {
+        rows.sort_by_key(|stop_time_i| stop_sequences[*stop_time_i]);
+    }
+
+    let get_time = |times: &Option<Vec<crate::OptionalServiceDayTime>>, stop_time_i: usize| {
+        times.as_ref().and_then(|times| times[stop_time_i].0)
+    };
+
+    let mut expanded_trips = vec![];
+    for (frequency_i, trip_id) in frequency_trip_ids.iter().enumerate() {
+        let rows = &template_rows[trip_id];
+        let Some(template_start) = rows.first().and_then(|stop_time_i| {
+            get_time(&stop_times.departure_time, *stop_time_i)
+                .or_else(|| get_time(&stop_times.arrival_time, *stop_time_i))
+        }) else {
+            continue;
+        };
+        for start_time in frequencies.trip_start_times(frequency_i) {
+            let shift = |time: Option<ServiceDayTime>| {
+                time.and_then(|time| {
+                    (time.seconds() + start_time.seconds())
+                        .checked_sub(template_start.seconds())
+                        .map(ServiceDayTime::from_seconds)
+                })
+            };
+            expanded_trips.push(ExpandedTrip {
+                trip_id,
+                frequency_i,
+                start_time,
+                stop_times: rows
+                    .iter()
+                    .map(|stop_time_i| ExpandedStopTime {
+                        stop_time_i: *stop_time_i,
+                        arrival_time: shift(get_time(&stop_times.arrival_time, *stop_time_i)),
+                        departure_time: shift(get_time(&stop_times.departure_time, *stop_time_i)),
+                    })
+                    .collect(),
+            });
+        }
+    }
+    expanded_trips
+}
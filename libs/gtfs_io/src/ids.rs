@@ -0,0 +1,196 @@
+use nonmax::NonMaxU32;
+use rayon::prelude::*;
+use std::{collections::HashMap, marker::PhantomData};
+
+use crate::Gtfs;
+
+macro_rules! define_index_type {
+    ($(#[$meta:meta])* $name:ident) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        pub struct $name(NonMaxU32);
+
+        impl $name {
+            /// This panics if the index is `u32::MAX`, which is reserved so that an
+            /// `Option<Self>` takes no more space than the index itself.
+            pub fn new(i: u32) -> Self {
+                Self(NonMaxU32::new(i).expect("index out of range"))
+            }
+
+            pub fn get(self) -> u32 {
+                self.0.get()
+            }
+        }
+
+        impl From<usize> for $name {
+            fn from(i: usize) -> Self {
+                Self::new(u32::try_from(i).expect("index out of range"))
+            }
+        }
+
+        impl From<$name> for usize {
+            fn from(idx: $name) -> Self {
+                idx.get() as usize
+            }
+        }
+    };
+}
+
+define_index_type!(
+    /// Dense index of a `stop_id`.
+    StopIdx
+);
+define_index_type!(
+    /// Dense index of a `trip_id`.
+    TripIdx
+);
+define_index_type!(
+    /// Dense index of a `route_id`.
+    RouteIdx
+);
+define_index_type!(
+    /// Dense index of a `service_id`.
+    ServiceIdx
+);
+
+/// Bidirectional mapping between string ids and dense indices.
+#[derive(Debug, Clone)]
+pub struct IdMap<'a, Idx> {
+    ids: Vec<&'a str>,
+    indices: HashMap<&'a str, u32>,
+    _phantom: PhantomData<Idx>,
+}
+
+impl<Idx> Default for IdMap<'_, Idx> {
+    fn default() -> Self {
+        Self {
+            ids: vec![],
+            indices: HashMap::new(),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<'a, Idx: Copy + From<usize> + Into<usize>> IdMap<'a, Idx> {
+    /// Adds the id if it does not exist yet and returns its index.
+    pub fn insert(&mut self, id: &'a str) -> Idx {
+        let next_i = self.ids.len() as u32;
+        let i = *self.indices.entry(id).or_insert(next_i);
+        if i == next_i {
+            self.ids.push(id);
+        }
+        Idx::from(i as usize)
+    }
+
+    pub fn get(&self, id: &str) -> Option<Idx> {
+        self.indices.get(id).map(|i| Idx::from(*i as usize))
+    }
+
+    /// Get the original id of an index. This panics if the index does not belong to this map.
+    pub fn id(&self, idx: Idx) -> &'a str {
+        self.ids[idx.into()]
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// Resolves all ids of a column. Unknown ids are `None`.
+    fn resolve_column(&self, column: &Option<Vec<&str>>) -> Option<Vec<Option<Idx>>>
+    where
+        Idx: Send + Sync,
+    {
+        column
+            .as_ref()
+            .map(|column| column.par_iter().map(|id| self.get(id)).collect())
+    }
+}
+
+/// Ids of the most important files replaced by dense indices. This allows resolving the
+/// references between files without string lookups. Created with [`Gtfs::intern_ids`].
+///
+/// Every `Option` of an index takes 4 bytes, half of a `&str`. The maps keep each distinct id
+/// once, so the interned string columns of [`Gtfs`] (e.g. `stop_times.trip_id`) can be set to
+/// `None` afterwards to actually reduce memory usage.
+#[derive(Debug, Default)]
+pub struct InternedIds<'a> {
+    pub stops: IdMap<'a, StopIdx>,
+    pub trips: IdMap<'a, TripIdx>,
+    pub routes: IdMap<'a, RouteIdx>,
+    pub services: IdMap<'a, ServiceIdx>,
+
+    pub stop_times_trip_id: Option<Vec<Option<TripIdx>>>,
+    pub stop_times_stop_id: Option<Vec<Option<StopIdx>>>,
+    pub stops_stop_id: Option<Vec<Option<StopIdx>>>,
+    pub stops_parent_station: Option<Vec<Option<StopIdx>>>,
+    pub trips_trip_id: Option<Vec<Option<TripIdx>>>,
+    pub trips_route_id: Option<Vec<Option<RouteIdx>>>,
+    pub trips_service_id: Option<Vec<Option<ServiceIdx>>>,
+    pub routes_route_id: Option<Vec<Option<RouteIdx>>>,
+    pub calendar_service_id: Option<Vec<Option<ServiceIdx>>>,
+    pub calendar_dates_service_id: Option<Vec<Option<ServiceIdx>>>,
+}
+
+impl<'a> Gtfs<'a> {
+    /// Interns the stop, trip, route and service ids. The indices are assigned in the order
+    /// in which the ids are defined in stops.txt, trips.txt, routes.txt and in calendar.txt
+    /// followed by calendar_dates.txt. References to ids that are not defined are `None`.
+    pub fn intern_ids(&self) -> InternedIds<'a> {
+        let stops = self.stops.data.as_ref();
+        let trips = self.trips.data.as_ref();
+        let routes = self.routes.data.as_ref();
+        let calendars = self.calendars.data.as_ref();
+        let calendar_dates = self.calendar_dates.data.as_ref();
+        let stop_times = self.stop_times.data.as_ref();
+
+        let mut ids = InternedIds::default();
+        for id in stops.and_then(|s| s.stop_id.as_ref()).into_iter().flatten() {
+            ids.stops.insert(id);
+        }
+        for id in trips.and_then(|t| t.trip_id.as_ref()).into_iter().flatten() {
+            ids.trips.insert(id);
+        }
+        for id in routes
+            .and_then(|r| r.route_id.as_ref())
+            .into_iter()
+            .flatten()
+        {
+            ids.routes.insert(id);
+        }
+        let service_ids = [
+            calendars.and_then(|c| c.service_id.as_ref()),
+            calendar_dates.and_then(|c| c.service_id.as_ref()),
+        ];
+        for id in service_ids.into_iter().flatten().flatten() {
+            ids.services.insert(id);
+        }
+
+        if let Some(stop_times) = stop_times {
+            ids.stop_times_trip_id = ids.trips.resolve_column(&stop_times.trip_id);
+            ids.stop_times_stop_id = ids.stops.resolve_column(&stop_times.stop_id);
+        }
+        if let Some(stops) = stops {
+            ids.stops_stop_id = ids.stops.resolve_column(&stops.stop_id);
+            ids.stops_parent_station = ids.stops.resolve_column(&stops.parent_station);
+        }
+        if let Some(trips) = trips {
+            ids.trips_trip_id = ids.trips.resolve_column(&trips.trip_id);
+            ids.trips_route_id = ids.routes.resolve_column(&trips.route_id);
+            ids.trips_service_id = ids.services.resolve_column(&trips.service_id);
+        }
+        if let Some(routes) = routes {
+            ids.routes_route_id = ids.routes.resolve_column(&routes.route_id);
+        }
+        if let Some(calendars) = calendars {
+            ids.calendar_service_id = ids.services.resolve_column(&calendars.service_id);
+        }
+        if let Some(calendar_dates) = calendar_dates {
+            ids.calendar_dates_service_id = ids.services.resolve_column(&calendar_dates.service_id);
+        }
+        ids
+    }
+}
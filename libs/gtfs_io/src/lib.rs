@@ -1,116 +1,27 @@
+mod date;
+mod files;
+mod frequencies;
+mod ids;
+mod service_calendar;
+mod shapes;
 mod structures;
+mod translations;
+pub mod validate;
 
 use anyhow::Result;
 use std::{
-    io::{Read, Seek},
+    io::{Read, Seek, Write},
     path::Path,
 };
 
+pub use date::*;
+pub use files::*;
+pub use frequencies::*;
+pub use ids::*;
+pub use service_calendar::*;
+pub use shapes::*;
 pub use structures::*;
-
-impl<'a> Gtfs<'a> {
-    /// Parses the provided buffers into GTFS data.
-    pub fn from_buffers(buffers: GtfsBufferSlices<'a>) -> Result<Self> {
-        macro_rules! do_parse {
-            ($name:ident, $ty:ty) => {
-                match buffers.$name {
-                    Some(buffer) => match <$ty>::from_csv_buffer(buffer) {
-                        Ok((data, len)) => File {
-                            len,
-                            data: Some(data),
-                        },
-                        Err(_) => File { len: 0, data: None },
-                    },
-                    None => File { len: 0, data: None },
-                }
-            };
-        }
-
-        Ok(Self {
-            stop_times: do_parse!(stop_times, StopTimes),
-            stops: do_parse!(stops, Stops),
-            trips: do_parse!(trips, Trips),
-            routes: do_parse!(routes, Routes),
-            calendars: do_parse!(calendar, Calendar),
-            calendar_dates: do_parse!(calendar_dates, CalendarDates),
-            agencies: do_parse!(agencies, Agencies),
-            feed_infos: do_parse!(feed_infos, FeedInfos),
-            attributions: do_parse!(attributions, Attributions),
-        })
-    }
-}
-
-/// Contains references to buffers which generally wrap the .txt files in a GTFS archive.
-/// This is usually created with [`GtfsBuffers::from_dir`] or [`GtfsBuffersMmap::from_dir`]
-/// and their `.to_slices()` method.
-#[derive(Debug)]
-pub struct GtfsBufferSlices<'a> {
-    pub stop_times: Option<&'a [u8]>,
-    pub stops: Option<&'a [u8]>,
-    pub trips: Option<&'a [u8]>,
-    pub routes: Option<&'a [u8]>,
-    pub calendar: Option<&'a [u8]>,
-    pub calendar_dates: Option<&'a [u8]>,
-    pub agencies: Option<&'a [u8]>,
-    pub feed_infos: Option<&'a [u8]>,
-    pub attributions: Option<&'a [u8]>,
-}
-
-/// Owns a vector for each file in a GTFS archive.
-pub struct GtfsBuffers {
-    pub stop_times: Option<Vec<u8>>,
-    pub stops: Option<Vec<u8>>,
-    pub trips: Option<Vec<u8>>,
-    pub routes: Option<Vec<u8>>,
-    pub calendar: Option<Vec<u8>>,
-    pub calendar_dates: Option<Vec<u8>>,
-    pub agencies: Option<Vec<u8>>,
-    pub feed_infos: Option<Vec<u8>>,
-    pub attributions: Option<Vec<u8>>,
-}
-
-#[derive(Debug, Clone)]
-pub struct GtfsFilter {
-    pub stop_times: bool,
-    pub stops: bool,
-    pub trips: bool,
-    pub routes: bool,
-    pub calendar: bool,
-    pub calendar_dates: bool,
-    pub agencies: bool,
-    pub feed_infos: bool,
-    pub attributions: bool,
-}
-
-impl GtfsFilter {
-    pub fn all() -> Self {
-        Self {
-            stop_times: true,
-            stops: true,
-            trips: true,
-            routes: true,
-            calendar: true,
-            calendar_dates: true,
-            agencies: true,
-            feed_infos: true,
-            attributions: true,
-        }
-    }
-
-    pub fn none() -> Self {
-        Self {
-            stop_times: false,
-            stops: false,
-            trips: false,
-            routes: false,
-            calendar: false,
-            calendar_dates: false,
-            agencies: false,
-            feed_infos: false,
-            attributions: false,
-        }
-    }
-}
+pub use translations::*;
 
 impl Default for GtfsFilter {
     fn default() -> Self {
@@ -130,27 +41,13 @@ impl GtfsBuffers {
 
     /// Load available GTFS files from the given directory.
     pub fn from_dir(gtfs_dir: &Path, filter: &GtfsFilter) -> Self {
-        macro_rules! load_from_dir {
-            ($name:ident) => {
-                if filter.$name {
-                    std::fs::read(gtfs_dir.join(format!("{}.txt", stringify!($name)))).ok()
-                } else {
-                    None
-                }
-            };
-        }
-
-        Self {
-            stop_times: load_from_dir!(stop_times),
-            stops: load_from_dir!(stops),
-            trips: load_from_dir!(trips),
-            routes: load_from_dir!(routes),
-            calendar: load_from_dir!(calendar),
-            calendar_dates: load_from_dir!(calendar_dates),
-            agencies: load_from_dir!(agencies),
-            feed_infos: load_from_dir!(feed_infos),
-            attributions: load_from_dir!(attributions),
-        }
+        Self::from_fn(|file| {
+            if filter.contains(file) {
+                std::fs::read(gtfs_dir.join(file.file_name())).ok()
+            } else {
+                None
+            }
+        })
     }
 
     /// Load the available GTFS files from a zip file.
@@ -161,8 +58,11 @@ impl GtfsBuffers {
     }
 
     /// Load the available GTFS files from a zip file using memory-mapped IO.
-    /// That can be slightly more efficient than [`Self::from_zip_file_path`] but
-    /// is unsafe when the underlying file is changed while it is read.
+    /// That can be slightly more efficient than [`Self::from_zip_file_path`].
+    ///
+    /// # Safety
+    ///
+    /// The underlying file must not be changed while it is read.
     pub unsafe fn from_zip_file_path_mmap(
         gtfs_zip_path: &Path,
         filter: &GtfsFilter,
@@ -183,26 +83,13 @@ impl GtfsBuffers {
         archive: &mut zip::ZipArchive<R>,
         filter: &GtfsFilter,
     ) -> Self {
-        macro_rules! load_from_zip {
-            ($name:ident) => {
-                if filter.$name {
-                    Self::read_archive_file(archive, &format!("{}.txt", stringify!($name))).ok()
-                } else {
-                    None
-                }
-            };
-        }
-        Self {
-            stop_times: load_from_zip!(stop_times),
-            stops: load_from_zip!(stops),
-            trips: load_from_zip!(trips),
-            routes: load_from_zip!(routes),
-            calendar: load_from_zip!(calendar),
-            calendar_dates: load_from_zip!(calendar_dates),
-            agencies: load_from_zip!(agencies),
-            feed_infos: load_from_zip!(feed_infos),
-            attributions: load_from_zip!(attributions),
-        }
+        Self::from_fn(|file| {
+            if filter.contains(file) {
+                Self::read_archive_file(archive, file.file_name()).ok()
+            } else {
+                None
+            }
+        })
     }
 
     fn read_archive_file<R: Read + Seek>(
@@ -214,63 +101,82 @@ impl GtfsBuffers {
         file.read_to_end(&mut buf)?;
         Ok(buf)
     }
+}
 
-    /// Get the slices owned by this instance to use with [`Gtfs::from_buffers`].
-    pub fn to_slices<'a>(&'a self) -> GtfsBufferSlices<'a> {
-        GtfsBufferSlices {
-            stop_times: self.stop_times.as_ref().map(|s| &s[..]),
-            stops: self.stops.as_ref().map(|s| &s[..]),
-            trips: self.trips.as_ref().map(|s| &s[..]),
-            routes: self.routes.as_ref().map(|s| &s[..]),
-            calendar: self.calendar.as_ref().map(|s| &s[..]),
-            calendar_dates: self.calendar_dates.as_ref().map(|s| &s[..]),
-            agencies: self.agencies.as_ref().map(|s| &s[..]),
-            feed_infos: self.feed_infos.as_ref().map(|s| &s[..]),
-            attributions: self.attributions.as_ref().map(|s| &s[..]),
+impl Gtfs<'_> {
+    /// Writes the GTFS either to a directory or to a zip file, depending on whether the path
+    /// has a `.zip` extension.
+    pub fn write_to_path(&self, gtfs_path: &Path) -> Result<()> {
+        if gtfs_path.extension().is_some_and(|ext| ext == "zip") {
+            self.write_to_zip(gtfs_path)
+        } else {
+            self.write_to_dir(gtfs_path)
         }
     }
-}
 
-/// Similar to [`GtfsBuffers`] but does not make copies of the buffers.
-/// This can be much more efficient with large datasets but is unsafe when
-/// the underlying file is changed while it is read.
-pub struct GtfsBuffersMmap {
-    pub stop_times: Option<memmap2::Mmap>,
-    pub stops: Option<memmap2::Mmap>,
-    pub trips: Option<memmap2::Mmap>,
-    pub routes: Option<memmap2::Mmap>,
-    pub calendar: Option<memmap2::Mmap>,
-    pub calendar_dates: Option<memmap2::Mmap>,
-    pub agencies: Option<memmap2::Mmap>,
-    pub feed_infos: Option<memmap2::Mmap>,
-    pub attributions: Option<memmap2::Mmap>,
+    /// Writes every loaded file to the given directory, which is created if it does not exist.
+    /// Files and columns that are not available are omitted.
+    pub fn write_to_dir(&self, gtfs_dir: &Path) -> Result<()> {
+        std::fs::create_dir_all(gtfs_dir)?;
+        self.write_files(|file, buffer| {
+            std::fs::write(gtfs_dir.join(file.file_name()), buffer)?;
+            Ok(())
+        })
+    }
+
+    /// Writes every loaded file into a new zip file at the given path.
+    pub fn write_to_zip(&self, gtfs_zip_path: &Path) -> Result<()> {
+        let file = std::fs::File::create(gtfs_zip_path)?;
+        let mut archive = zip::ZipWriter::new(std::io::BufWriter::new(file));
+        self.write_to_zip_archive(&mut archive)?;
+        archive.finish()?.flush()?;
+        Ok(())
+    }
+
+    /// Adds every loaded file to the archive. The caller is responsible for finishing it.
+    pub fn write_to_zip_archive<W: Write + Seek>(
+        &self,
+        archive: &mut zip::ZipWriter<W>,
+    ) -> Result<()> {
+        let options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Deflated);
+        self.write_files(|file, buffer| {
+            archive.start_file(file.file_name(), options)?;
+            archive.write_all(buffer)?;
+            Ok(())
+        })
+    }
+
+    fn write_files(&self, mut write: impl FnMut(GtfsFile, &[u8]) -> Result<()>) -> Result<()> {
+        // Files are serialized to a buffer first, because it's only known afterwards whether
+        // they have any columns.
+        let mut buffer = vec![];
+        for &file in GtfsFile::ALL {
+            buffer.clear();
+            self.write_file_csv(file, &mut buffer)?;
+            if !buffer.is_empty() {
+                write(file, &buffer)?;
+            }
+        }
+        Ok(())
+    }
 }
 
 impl GtfsBuffersMmap {
     /// Load available GTFS files from the given directory.
-    /// This can be much more efficient with large datasets but is unsafe when
-    /// the underlying file is changed while it is read.
+    /// This can be much more efficient with large datasets.
+    ///
+    /// # Safety
+    ///
+    /// The underlying files must not be changed while they are read.
     pub unsafe fn from_dir(gtfs_dir: &Path, filter: &GtfsFilter) -> Self {
-        macro_rules! load_from_dir_mmap {
-            ($name:ident) => {
-                if filter.$name {
-                    Self::load(gtfs_dir, &format!("{}.txt", stringify!($name)))
-                } else {
-                    None
-                }
-            };
-        }
-        Self {
-            stop_times: load_from_dir_mmap!(stop_times),
-            stops: load_from_dir_mmap!(stops),
-            trips: load_from_dir_mmap!(trips),
-            routes: load_from_dir_mmap!(routes),
-            calendar: load_from_dir_mmap!(calendar),
-            calendar_dates: load_from_dir_mmap!(calendar_dates),
-            agencies: load_from_dir_mmap!(agencies),
-            feed_infos: load_from_dir_mmap!(feed_infos),
-            attributions: load_from_dir_mmap!(attributions),
-        }
+        Self::from_fn(|file| {
+            if filter.contains(file) {
+                Self::load(gtfs_dir, file.file_name())
+            } else {
+                None
+            }
+        })
     }
 
     unsafe fn load(gtfs_dir: &Path, file_name: &str) -> Option<memmap2::Mmap> {
@@ -280,20 +186,3 @@ impl GtfsBuffersMmap {
         }
     }
 }
-
-impl GtfsBuffersMmap {
-    /// Get the slices owned by this instance to use with [`Gtfs::from_buffers`].
-    pub fn to_slices<'a>(&'a self) -> GtfsBufferSlices<'a> {
-        GtfsBufferSlices {
-            stop_times: self.stop_times.as_ref().map(|s| &s[..]),
-            stops: self.stops.as_ref().map(|s| &s[..]),
-            trips: self.trips.as_ref().map(|s| &s[..]),
-            routes: self.routes.as_ref().map(|s| &s[..]),
-            calendar: self.calendar.as_ref().map(|s| &s[..]),
-            calendar_dates: self.calendar_dates.as_ref().map(|s| &s[..]),
-            agencies: self.agencies.as_ref().map(|s| &s[..]),
-            feed_infos: self.feed_infos.as_ref().map(|s| &s[..]),
-            attributions: self.attributions.as_ref().map(|s| &s[..]),
-        }
-    }
-}
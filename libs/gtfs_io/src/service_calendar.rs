@@ -0,0 +1,273 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{Calendar, CalendarDates, Date, ExceptionType, Gtfs, ServiceAvailable, Weekday};
+
+/// Resolves on which days services run by combining calendar.txt and calendar_dates.txt.
+///
+/// The days are stored as one bitset per service over the validity period of the feed, which
+/// starts at the earliest and ends at the latest date mentioned in either file. Invalid dates,
+/// e.g. from fields that could not be parsed, are ignored.
+#[derive(Debug, Clone, Default)]
+pub struct ServiceCalendar<'a> {
+    /// Days since the unix epoch of the first day in the validity period.
+    first_day: i32,
+    days_num: usize,
+    service_ids: Vec<&'a str>,
+    service_indices: HashMap<&'a str, usize>,
+    service_days: Vec<ServiceDays>,
+}
+
+/// A bitset with one bit per day in the validity period of a [`ServiceCalendar`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ServiceDays {
+    words: Vec<u64>,
+}
+
+impl<'a> Gtfs<'a> {
+    /// Builds a calendar that tells which services run on a given date.
+    pub fn service_calendar(&self) -> ServiceCalendar<'a> {
+        ServiceCalendar::new(
+            self.calendars.data.as_ref(),
+            self.calendar_dates.data.as_ref(),
+        )
+    }
+}
+
+impl<'a> ServiceCalendar<'a> {
+    pub fn new(
+        calendars: Option<&Calendar<'a>>,
+        calendar_dates: Option<&CalendarDates<'a>>,
+    ) -> Self {
+        let calendars = calendars.filter(|calendars| {
+            calendars.service_id.is_some()
+                && calendars.start_date.is_some()
+                && calendars.end_date.is_some()
+        });
+        let calendar_dates = calendar_dates.filter(|calendar_dates| {
+            calendar_dates.service_id.is_some()
+                && calendar_dates.date.is_some()
+                && calendar_dates.exception_type.is_some()
+        });
+
+        let mut all_dates: Vec<&Date> = vec![];
+        if let Some(calendars) = calendars {
+            all_dates.extend(calendars.start_date.iter().flatten());
+            all_dates.extend(calendars.end_date.iter().flatten());
+        }
+        if let Some(calendar_dates) = calendar_dates {
+            all_dates.extend(calendar_dates.date.iter().flatten());
+        }
+        let all_days = all_dates
+            .into_iter()
+            .filter(|date| date.is_valid())
+            .map(Date::days_since_epoch);
+        let Some((first_day, last_day)) = all_days.fold(None, |range, day| match range {
+            Some((first, last)) => Some((day.min(first), day.max(last))),
+            None => Some((day, day)),
+        }) else {
+            return Self::default();
+        };
+
+        let mut calendar = Self {
+            first_day,
+            days_num: (last_day - first_day + 1) as usize,
+            ..Self::default()
+        };
+        if let Some(calendars) = calendars {
+            calendar.add_calendars(calendars);
+        }
+        // Exceptions are applied last, because they take precedence over the regular schedule.
+        if let Some(calendar_dates) = calendar_dates {
+            calendar.add_calendar_dates(calendar_dates);
+        }
+        calendar
+    }
+
+    fn add_calendars(&mut self, calendars: &Calendar<'a>) {
+        let (Some(service_ids), Some(start_dates), Some(end_dates)) = (
+            calendars.service_id.as_ref(),
+            calendars.start_date.as_ref(),
+            calendars.end_date.as_ref(),
+        ) else {
+            return;
+        };
+        for (i, service_id) in service_ids.iter().enumerate() {
+            let service_i = self.get_or_add_service(service_id);
+            let (start_date, end_date) = (start_dates[i], end_dates[i]);
+            if !start_date.is_valid() || !end_date.is_valid() {
+                continue;
+            }
+            let runs_on_weekday = Weekday::ALL.map(|weekday| {
+                calendars
+                    .weekday(weekday)
+                    .is_some_and(|column| column[i] == ServiceAvailable::Yes)
+            });
+            for date in Date::range_inclusive(start_date, end_date) {
+                if runs_on_weekday[date.weekday().index()] {
+                    let day_i = (date.days_since_epoch() - self.first_day) as usize;
+                    self.service_days[service_i].set(day_i, true);
+                }
+            }
+        }
+    }
+
+    fn add_calendar_dates(&mut self, calendar_dates: &CalendarDates<'a>) {
+        let (Some(service_ids), Some(dates), Some(exception_types)) = (
+            calendar_dates.service_id.as_ref(),
+            calendar_dates.date.as_ref(),
+            calendar_dates.exception_type.as_ref(),
+        ) else {
+            return;
+        };
+        for ((service_id, date), exception_type) in
+            service_ids.iter().zip(dates).zip(exception_types)
+        {
+            let service_i = self.get_or_add_service(service_id);
+            if !date.is_valid() {
+                continue;
+            }
+            let day_i = (date.days_since_epoch() - self.first_day) as usize;
+            match exception_type {
+                ExceptionType::Added => self.service_days[service_i].set(day_i, true),
+                ExceptionType::Removed => self.service_days[service_i].set(day_i, false),
+                ExceptionType::Unknown => {}
+            }
+        }
+    }
+
+    fn get_or_add_service(&mut self, service_id: &'a str) -> usize {
+        if let Some(service_i) = self.service_indices.get(service_id) {
+            return *service_i;
+        }
+        let service_i = self.service_ids.len();
+        self.service_ids.push(service_id);
+        self.service_indices.insert(service_id, service_i);
+        self.service_days.push(ServiceDays::new(self.days_num));
+        service_i
+    }
+
+    /// First day on which any service may run or `None` if there are no services.
+    pub fn first_date(&self) -> Option<Date> {
+        (self.days_num > 0).then(|| Date::from_days_since_epoch(self.first_day))
+    }
+
+    /// Last day on which any service may run or `None` if there are no services.
+    pub fn last_date(&self) -> Option<Date> {
+        (self.days_num > 0)
+            .then(|| Date::from_days_since_epoch(self.first_day + self.days_num as i32 - 1))
+    }
+
+    /// Number of days in the validity period, which is the length of all [`ServiceDays`].
+    pub fn days_num(&self) -> usize {
+        self.days_num
+    }
+
+    /// All services that are mentioned in calendar.txt or calendar_dates.txt.
+    pub fn service_ids(&self) -> &[&'a str] {
+        &self.service_ids
+    }
+
+    /// Get the days on which the service runs. Bit `i` corresponds to [`Self::first_date`]
+    /// plus `i` days.
+    pub fn service_days(&self, service_id: &str) -> Option<&ServiceDays> {
+        self.service_indices
+            .get(service_id)
+            .map(|service_i| &self.service_days[*service_i])
+    }
+
+    /// Checks whether the service runs on the given date.
+    pub fn is_active(&self, service_id: &str, date: &Date) -> bool {
+        match (self.service_days(service_id), self.day_index(date)) {
+            (Some(days), Some(day_i)) => days.get(day_i),
+            _ => false,
+        }
+    }
+
+    /// Get the ids of all services that run on the given date.
+    pub fn active_services(&self, date: &Date) -> HashSet<&'a str> {
+        let Some(day_i) = self.day_index(date) else {
+            return HashSet::new();
+        };
+        self.service_ids
+            .iter()
+            .zip(&self.service_days)
+            .filter(|(_, days)| days.get(day_i))
+            .map(|(service_id, _)| *service_id)
+            .collect()
+    }
+
+    /// Get the ids of all services that run on at least one day between the given dates,
+    /// including both of them.
+    pub fn active_services_between(&self, first_date: &Date, last_date: &Date) -> HashSet<&'a str> {
+        let first_day = (first_date.days_since_epoch() - self.first_day).max(0);
+        let last_day =
+            (last_date.days_since_epoch() - self.first_day).min(self.days_num as i32 - 1);
+        if first_day > last_day {
+            return HashSet::new();
+        }
+        self.service_ids
+            .iter()
+            .zip(&self.service_days)
+            .filter(|(_, days)| (first_day..=last_day).any(|day_i| days.get(day_i as usize)))
+            .map(|(service_id, _)| *service_id)
+            .collect()
+    }
+
+    fn day_index(&self, date: &Date) -> Option<usize> {
+        let day_i = date.days_since_epoch() - self.first_day;
+        (0..self.days_num as i32)
+            .contains(&day_i)
+            .then_some(day_i as usize)
+    }
+}
+
+impl<'a> Calendar<'a> {
+    /// Get the column of calendar.txt that corresponds to the weekday.
+    pub fn weekday(&self, weekday: Weekday) -> Option<&Vec<ServiceAvailable>> {
+        match weekday {
+            Weekday::Monday => self.monday.as_ref(),
+            Weekday::Tuesday => self.tuesday.as_ref(),
+            Weekday::Wednesday => self.wednesday.as_ref(),
+            Weekday::Thursday => self.thursday.as_ref(),
+            Weekday::Friday => self.friday.as_ref(),
+            Weekday::Saturday => self.saturday.as_ref(),
+            Weekday::Sunday => self.sunday.as_ref(),
+        }
+    }
+}
+
+impl ServiceDays {
+    fn new(days_num: usize) -> Self {
+        Self {
+            words: vec![0; days_num.div_ceil(64)],
+        }
+    }
+
+    pub fn get(&self, day_i: usize) -> bool {
+        self.words
+            .get(day_i / 64)
+            .is_some_and(|word| word & (1 << (day_i % 64)) != 0)
+    }
+
+    fn set(&mut self, day_i: usize, value: bool) {
+        let word = &mut self.words[day_i / 64];
+        if value {
+            *word |= 1 << (day_i % 64);
+        } else {
+            *word &= !(1 << (day_i % 64));
+        }
+    }
+
+    /// Number of days on which the service runs.
+    pub fn active_days_num(&self) -> usize {
+        self.words
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    /// The raw bitset. The lowest bit of the first word is the first day.
+    pub fn words(&self) -> &[u64] {
+        &self.words
+    }
+}
@@ -0,0 +1,63 @@
+use std::collections::HashMap;
+
+use crate::Shapes;
+
+/// The points of a single shape in the order in which they are traversed.
+#[derive(Debug, Clone)]
+pub struct ShapePolyline<'a> {
+    pub shape_id: &'a str,
+    pub points: Vec<ShapePoint>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShapePoint {
+    pub latitude: f32,
+    pub longitude: f32,
+    pub dist_traveled: Option<f32>,
+}
+
+impl<'a> Shapes<'a> {
+    /// Groups the points by `shape_id` and sorts them by `shape_pt_sequence`.
+    /// The shapes are returned in the order in which they first appear in the file.
+    /// Points without valid coordinates are skipped.
+    pub fn polylines(&self) -> Vec<ShapePolyline<'a>> {
+        let (Some(shape_ids), Some(lats), Some(lons), Some(sequences)) = (
+            self.shape_id.as_ref(),
+            self.shape_pt_lat.as_ref(),
+            self.shape_pt_lon.as_ref(),
+            self.shape_pt_sequence.as_ref(),
+        ) else {
+            return vec![];
+        };
+        let dists = self.shape_dist_traveled.as_ref();
+
+        let mut polyline_by_id: HashMap<&'a str, usize> = HashMap::new();
+        let mut polylines: Vec<(&'a str, Vec<(u32, ShapePoint)>)> = vec![];
+        for (i, shape_id) in shape_ids.iter().enumerate() {
+            let (Some(latitude), Some(longitude)) = (lats[i].0, lons[i].0) else {
+                continue;
+            };
+            let point = ShapePoint {
+                latitude,
+                longitude,
+                dist_traveled: dists.and_then(|d| d[i].0),
+            };
+            let polyline_i = *polyline_by_id.entry(shape_id).or_insert_with(|| {
+                polylines.push((shape_id, vec![]));
+                polylines.len() - 1
+            });
+            polylines[polyline_i].1.push((sequences[i], point));
+        }
+
+        polylines
+            .into_iter()
+            .map(|(shape_id, mut points)| {
+                points.sort_by_key(|(sequence, _)| *sequence);
+                ShapePolyline {
+                    shape_id,
+                    points: points.into_iter().map(|(_, point)| point).collect(),
+                }
+            })
+            .collect()
+    }
+}
@@ -1,44 +1,68 @@
-use csvelo::CSVParser;
+use csvelo::{CSVColumns, CSVParser, CSVWriter};
 use rayon::prelude::*;
+use std::borrow::Cow;
 use std::fmt::Debug;
+use std::io::Write;
+
+use crate::{Date, OptionalDate};
 
 // GTFS Reference: https://gtfs.org/documentation/schedule/reference/
+//
+// Ids are borrowed from the buffer directly, so ids with escaped quotes are parse errors.
+// Texts that may contain escaped quotes use `Cow`, so that they are only copied when they have
+// to be unescaped.
+//
+// Enum values that are `Unknown` are written as empty fields, because the original value is
+// not preserved when parsing. Route types are the exception, since feeds commonly use the
+// extended route types that are not part of the specification.
 
-pub struct Gtfs<'a> {
-    pub stop_times: File<StopTimes<'a>>,
-    pub stops: File<Stops<'a>>,
-    pub trips: File<Trips<'a>>,
-    pub routes: File<Routes<'a>>,
-    pub calendars: File<Calendar<'a>>,
-    pub calendar_dates: File<CalendarDates<'a>>,
-    pub agencies: File<Agencies<'a>>,
-    pub feed_infos: File<FeedInfos<'a>>,
-    pub attributions: File<Attributions<'a>>,
+#[derive(Debug)]
+pub struct File<T> {
+    pub len: usize,
+    pub data: Option<T>,
+    /// Fields that could not be parsed. They have the default value in `data`.
+    pub parse_errors: csvelo::CsvParseErrors,
+    /// Set when the file exists but could not be parsed at all.
+    pub error: Option<csvelo::Error>,
 }
 
-impl Debug for Gtfs<'_> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("Gtfs")
-            .field("stop_times", &self.stop_times.len)
-            .field("stops", &self.stops.len)
-            .field("trips", &self.trips.len)
-            .field("routes", &self.routes.len)
-            .field("calendar", &self.calendars.len)
-            .field("calendar_dates", &self.calendar_dates.len)
-            .field("agencies", &self.agencies.len)
-            .field("feed_infos", &self.feed_infos.len)
-            .field("attributions", &self.attributions.len)
-            .finish()
+impl<T> Default for File<T> {
+    fn default() -> Self {
+        Self {
+            len: 0,
+            data: None,
+            parse_errors: csvelo::CsvParseErrors::default(),
+            error: None,
+        }
     }
 }
 
-#[derive(Debug)]
-pub struct File<T> {
-    pub len: usize,
-    pub data: Option<T>,
+// The parse errors are cleared when records are changed, because their record indices refer to
+// the original buffer.
+impl<T: csvelo::CsvColumns + Default> File<T> {
+    /// Keeps only the records for which `keep` is true.
+    pub fn retain_records(&mut self, keep: &[bool]) {
+        if let Some(data) = self.data.as_mut() {
+            data.retain_records(keep);
+            self.len = keep.iter().filter(|k| **k).count();
+        }
+        self.parse_errors = csvelo::CsvParseErrors::default();
+    }
+
+    /// Appends the records of another file. Nothing is appended if the other file is not loaded.
+    pub fn append_records(&mut self, other: File<T>) {
+        let Some(other_data) = other.data else {
+            return;
+        };
+        self.data
+            .get_or_insert_with(T::default)
+            .append_records(self.len, other_data, other.len);
+        self.len += other.len;
+        self.parse_errors = csvelo::CsvParseErrors::default();
+    }
 }
 
-#[derive(CSVParser, Debug, Clone, Default)]
+#[derive(CSVParser, CSVWriter, CSVColumns, Debug, Clone, Default)]
 pub struct StopTimes<'a> {
     pub trip_id: Option<Vec<&'a str>>,
     pub stop_id: Option<Vec<&'a str>>,
@@ -48,7 +72,7 @@ pub struct StopTimes<'a> {
 
     pub location_group_id: Option<Vec<&'a str>>,
     pub location_id: Option<Vec<&'a str>>,
-    pub stop_headsign: Option<Vec<&'a str>>,
+    pub stop_headsign: Option<Vec<Cow<'a, str>>>,
     pub start_pickup_drop_off_window: Option<Vec<OptionalServiceDayTime>>,
     pub end_pickup_drop_off_window: Option<Vec<OptionalServiceDayTime>>,
     pub pickup_type: Option<Vec<PickupType>>,
@@ -61,13 +85,13 @@ pub struct StopTimes<'a> {
     pub drop_off_booking_rule_id: Option<Vec<&'a str>>,
 }
 
-#[derive(CSVParser, Debug, Clone, Default)]
+#[derive(CSVParser, CSVWriter, CSVColumns, Debug, Clone, Default)]
 pub struct Stops<'a> {
     pub stop_id: Option<Vec<&'a str>>,
     pub stop_code: Option<Vec<&'a str>>,
-    pub stop_name: Option<Vec<&'a str>>,
-    pub tts_stop_name: Option<Vec<&'a str>>,
-    pub stop_desc: Option<Vec<&'a str>>,
+    pub stop_name: Option<Vec<Cow<'a, str>>>,
+    pub tts_stop_name: Option<Vec<Cow<'a, str>>>,
+    pub stop_desc: Option<Vec<Cow<'a, str>>>,
     pub stop_lat: Option<Vec<OptionalF32>>,
     pub stop_lon: Option<Vec<OptionalF32>>,
     pub zone_id: Option<Vec<&'a str>>,
@@ -80,13 +104,13 @@ pub struct Stops<'a> {
     pub platform_code: Option<Vec<&'a str>>,
 }
 
-#[derive(CSVParser, Debug, Clone, Default)]
+#[derive(CSVParser, CSVWriter, CSVColumns, Debug, Clone, Default)]
 pub struct Trips<'a> {
     pub route_id: Option<Vec<&'a str>>,
     pub service_id: Option<Vec<&'a str>>,
     pub trip_id: Option<Vec<&'a str>>,
-    pub trip_headsign: Option<Vec<&'a str>>,
-    pub trip_short_name: Option<Vec<&'a str>>,
+    pub trip_headsign: Option<Vec<Cow<'a, str>>>,
+    pub trip_short_name: Option<Vec<Cow<'a, str>>>,
     pub direction_id: Option<Vec<DirectionId>>,
     pub block_id: Option<Vec<&'a str>>,
     pub shape_id: Option<Vec<&'a str>>,
@@ -94,24 +118,24 @@ pub struct Trips<'a> {
     pub bikes_allowed: Option<Vec<BikesAllowed>>,
 }
 
-#[derive(CSVParser, Debug, Clone, Default)]
+#[derive(CSVParser, CSVWriter, CSVColumns, Debug, Clone, Default)]
 pub struct Routes<'a> {
     pub route_id: Option<Vec<&'a str>>,
     pub agency_id: Option<Vec<&'a str>>,
-    pub route_short_name: Option<Vec<&'a str>>,
-    pub route_long_name: Option<Vec<&'a str>>,
-    pub route_desc: Option<Vec<&'a str>>,
+    pub route_short_name: Option<Vec<Cow<'a, str>>>,
+    pub route_long_name: Option<Vec<Cow<'a, str>>>,
+    pub route_desc: Option<Vec<Cow<'a, str>>>,
     pub route_type: Option<Vec<RouteType>>,
     pub route_url: Option<Vec<&'a str>>,
-    pub route_color: Option<Vec<Color>>,
-    pub route_text_color: Option<Vec<Color>>,
-    pub route_sort_order: Option<Vec<u32>>,
+    pub route_color: Option<Vec<OptionalColor>>,
+    pub route_text_color: Option<Vec<OptionalColor>>,
+    pub route_sort_order: Option<Vec<OptionalU32>>,
     pub continuous_pickup: Option<Vec<ContinuousPickupType>>,
     pub continuous_drop_off: Option<Vec<ContinuousDropOffType>>,
     pub network_id: Option<Vec<&'a str>>,
 }
 
-#[derive(CSVParser, Debug, Clone, Default)]
+#[derive(CSVParser, CSVWriter, CSVColumns, Debug, Clone, Default)]
 pub struct Calendar<'a> {
     pub service_id: Option<Vec<&'a str>>,
     pub monday: Option<Vec<ServiceAvailable>>,
@@ -125,17 +149,17 @@ pub struct Calendar<'a> {
     pub end_date: Option<Vec<Date>>,
 }
 
-#[derive(CSVParser, Debug, Clone, Default)]
+#[derive(CSVParser, CSVWriter, CSVColumns, Debug, Clone, Default)]
 pub struct CalendarDates<'a> {
     pub service_id: Option<Vec<&'a str>>,
     pub date: Option<Vec<Date>>,
     pub exception_type: Option<Vec<ExceptionType>>,
 }
 
-#[derive(CSVParser, Debug, Clone, Default)]
+#[derive(CSVParser, CSVWriter, CSVColumns, Debug, Clone, Default)]
 pub struct Agencies<'a> {
     pub agency_id: Option<Vec<&'a str>>,
-    pub agency_name: Option<Vec<&'a str>>,
+    pub agency_name: Option<Vec<Cow<'a, str>>>,
     pub agency_url: Option<Vec<&'a str>>,
     pub agency_timezone: Option<Vec<&'a str>>,
     pub agency_lang: Option<Vec<&'a str>>,
@@ -144,32 +168,184 @@ pub struct Agencies<'a> {
     pub agency_email: Option<Vec<&'a str>>,
 }
 
-#[derive(CSVParser, Debug, Clone, Default)]
+#[derive(CSVParser, CSVWriter, CSVColumns, Debug, Clone, Default)]
 pub struct FeedInfos<'a> {
-    pub feed_publisher_name: Option<Vec<&'a str>>,
+    pub feed_publisher_name: Option<Vec<Cow<'a, str>>>,
     pub feed_publisher_url: Option<Vec<&'a str>>,
     pub feed_lang: Option<Vec<&'a str>>,
     pub default_lang: Option<Vec<&'a str>>,
-    pub feed_start_date: Option<Vec<Date>>,
-    pub feed_end_date: Option<Vec<Date>>,
+    pub feed_start_date: Option<Vec<OptionalDate>>,
+    pub feed_end_date: Option<Vec<OptionalDate>>,
     pub feed_version: Option<Vec<&'a str>>,
     pub feed_contact_email: Option<Vec<&'a str>>,
     pub feed_contact_url: Option<Vec<&'a str>>,
 }
 
-#[derive(CSVParser, Debug, Clone, Default)]
+#[derive(CSVParser, CSVWriter, CSVColumns, Debug, Clone, Default)]
 pub struct Attributions<'a> {
-    attribution_id: Option<Vec<&'a str>>,
-    agency_id: Option<Vec<&'a str>>,
-    route_id: Option<Vec<&'a str>>,
-    trip_id: Option<Vec<&'a str>>,
-    organization_name: Option<Vec<&'a str>>,
-    is_producer: Option<Vec<YesOrNo>>,
-    is_operator: Option<Vec<YesOrNo>>,
-    is_authority: Option<Vec<YesOrNo>>,
-    attribution_url: Option<Vec<&'a str>>,
-    attribution_email: Option<Vec<&'a str>>,
-    attribution_phone: Option<Vec<&'a str>>,
+    pub attribution_id: Option<Vec<&'a str>>,
+    pub agency_id: Option<Vec<&'a str>>,
+    pub route_id: Option<Vec<&'a str>>,
+    pub trip_id: Option<Vec<&'a str>>,
+    pub organization_name: Option<Vec<Cow<'a, str>>>,
+    pub is_producer: Option<Vec<YesOrNo>>,
+    pub is_operator: Option<Vec<YesOrNo>>,
+    pub is_authority: Option<Vec<YesOrNo>>,
+    pub attribution_url: Option<Vec<&'a str>>,
+    pub attribution_email: Option<Vec<&'a str>>,
+    pub attribution_phone: Option<Vec<&'a str>>,
+}
+
+#[derive(CSVParser, CSVWriter, CSVColumns, Debug, Clone, Default)]
+pub struct Shapes<'a> {
+    pub shape_id: Option<Vec<&'a str>>,
+    pub shape_pt_lat: Option<Vec<OptionalF32>>,
+    pub shape_pt_lon: Option<Vec<OptionalF32>>,
+    pub shape_pt_sequence: Option<Vec<u32>>,
+    pub shape_dist_traveled: Option<Vec<OptionalF32>>,
+}
+
+#[derive(CSVParser, CSVWriter, CSVColumns, Debug, Clone, Default)]
+pub struct Frequencies<'a> {
+    pub trip_id: Option<Vec<&'a str>>,
+    pub start_time: Option<Vec<OptionalServiceDayTime>>,
+    pub end_time: Option<Vec<OptionalServiceDayTime>>,
+    pub headway_secs: Option<Vec<u32>>,
+    pub exact_times: Option<Vec<ExactTimes>>,
+}
+
+#[derive(CSVParser, CSVWriter, CSVColumns, Debug, Clone, Default)]
+pub struct Transfers<'a> {
+    pub from_stop_id: Option<Vec<&'a str>>,
+    pub to_stop_id: Option<Vec<&'a str>>,
+    pub from_route_id: Option<Vec<&'a str>>,
+    pub to_route_id: Option<Vec<&'a str>>,
+    pub from_trip_id: Option<Vec<&'a str>>,
+    pub to_trip_id: Option<Vec<&'a str>>,
+    pub transfer_type: Option<Vec<TransferType>>,
+    pub min_transfer_time: Option<Vec<OptionalU32>>,
+}
+
+#[derive(CSVParser, CSVWriter, CSVColumns, Debug, Clone, Default)]
+pub struct Pathways<'a> {
+    pub pathway_id: Option<Vec<&'a str>>,
+    pub from_stop_id: Option<Vec<&'a str>>,
+    pub to_stop_id: Option<Vec<&'a str>>,
+    pub pathway_mode: Option<Vec<PathwayMode>>,
+    pub is_bidirectional: Option<Vec<IsBidirectional>>,
+    pub length: Option<Vec<OptionalF32>>,
+    pub traversal_time: Option<Vec<OptionalU32>>,
+    pub stair_count: Option<Vec<OptionalI32>>,
+    pub max_slope: Option<Vec<OptionalF32>>,
+    pub min_width: Option<Vec<OptionalF32>>,
+    pub signposted_as: Option<Vec<Cow<'a, str>>>,
+    pub reversed_signposted_as: Option<Vec<Cow<'a, str>>>,
+}
+
+#[derive(CSVParser, CSVWriter, CSVColumns, Debug, Clone, Default)]
+pub struct Levels<'a> {
+    pub level_id: Option<Vec<&'a str>>,
+    pub level_index: Option<Vec<OptionalF32>>,
+    pub level_name: Option<Vec<Cow<'a, str>>>,
+}
+
+#[derive(CSVParser, CSVWriter, CSVColumns, Debug, Clone, Default)]
+pub struct FareAttributes<'a> {
+    pub fare_id: Option<Vec<&'a str>>,
+    pub price: Option<Vec<OptionalF64>>,
+    pub currency_type: Option<Vec<&'a str>>,
+    pub payment_method: Option<Vec<PaymentMethod>>,
+    pub transfers: Option<Vec<FareTransfers>>,
+    pub agency_id: Option<Vec<&'a str>>,
+    pub transfer_duration: Option<Vec<OptionalU32>>,
+}
+
+#[derive(CSVParser, CSVWriter, CSVColumns, Debug, Clone, Default)]
+pub struct FareRules<'a> {
+    pub fare_id: Option<Vec<&'a str>>,
+    pub route_id: Option<Vec<&'a str>>,
+    pub origin_id: Option<Vec<&'a str>>,
+    pub destination_id: Option<Vec<&'a str>>,
+    pub contains_id: Option<Vec<&'a str>>,
+}
+
+#[derive(CSVParser, CSVWriter, CSVColumns, Debug, Clone, Default)]
+pub struct FareMedia<'a> {
+    pub fare_media_id: Option<Vec<&'a str>>,
+    pub fare_media_name: Option<Vec<Cow<'a, str>>>,
+    pub fare_media_type: Option<Vec<FareMediaType>>,
+}
+
+#[derive(CSVParser, CSVWriter, CSVColumns, Debug, Clone, Default)]
+pub struct FareProducts<'a> {
+    pub fare_product_id: Option<Vec<&'a str>>,
+    pub fare_product_name: Option<Vec<Cow<'a, str>>>,
+    pub rider_category_id: Option<Vec<&'a str>>,
+    pub fare_media_id: Option<Vec<&'a str>>,
+    pub amount: Option<Vec<OptionalF64>>,
+    pub currency: Option<Vec<&'a str>>,
+}
+
+#[derive(CSVParser, CSVWriter, CSVColumns, Debug, Clone, Default)]
+pub struct FareLegRules<'a> {
+    pub leg_group_id: Option<Vec<&'a str>>,
+    pub network_id: Option<Vec<&'a str>>,
+    pub from_area_id: Option<Vec<&'a str>>,
+    pub to_area_id: Option<Vec<&'a str>>,
+    pub from_timeframe_group_id: Option<Vec<&'a str>>,
+    pub to_timeframe_group_id: Option<Vec<&'a str>>,
+    pub fare_product_id: Option<Vec<&'a str>>,
+    pub rule_priority: Option<Vec<OptionalU32>>,
+}
+
+#[derive(CSVParser, CSVWriter, CSVColumns, Debug, Clone, Default)]
+pub struct FareTransferRules<'a> {
+    pub from_leg_group_id: Option<Vec<&'a str>>,
+    pub to_leg_group_id: Option<Vec<&'a str>>,
+    pub transfer_count: Option<Vec<OptionalI32>>,
+    pub duration_limit: Option<Vec<OptionalU32>>,
+    pub duration_limit_type: Option<Vec<DurationLimitType>>,
+    pub fare_transfer_type: Option<Vec<FareTransferType>>,
+    pub fare_product_id: Option<Vec<&'a str>>,
+}
+
+#[derive(CSVParser, CSVWriter, CSVColumns, Debug, Clone, Default)]
+pub struct Areas<'a> {
+    pub area_id: Option<Vec<&'a str>>,
+    pub area_name: Option<Vec<Cow<'a, str>>>,
+}
+
+#[derive(CSVParser, CSVWriter, CSVColumns, Debug, Clone, Default)]
+pub struct StopAreas<'a> {
+    pub area_id: Option<Vec<&'a str>>,
+    pub stop_id: Option<Vec<&'a str>>,
+}
+
+#[derive(CSVParser, CSVWriter, CSVColumns, Debug, Clone, Default)]
+pub struct Timeframes<'a> {
+    pub timeframe_group_id: Option<Vec<&'a str>>,
+    pub start_time: Option<Vec<OptionalServiceDayTime>>,
+    pub end_time: Option<Vec<OptionalServiceDayTime>>,
+    pub service_id: Option<Vec<&'a str>>,
+}
+
+#[derive(CSVParser, CSVWriter, CSVColumns, Debug, Clone, Default)]
+pub struct RiderCategories<'a> {
+    pub rider_category_id: Option<Vec<&'a str>>,
+    pub rider_category_name: Option<Vec<Cow<'a, str>>>,
+    pub is_default_fare_category: Option<Vec<YesOrNo>>,
+    pub eligibility_url: Option<Vec<&'a str>>,
+}
+
+#[derive(CSVParser, CSVWriter, CSVColumns, Debug, Clone, Default)]
+pub struct Translations<'a> {
+    pub table_name: Option<Vec<&'a str>>,
+    pub field_name: Option<Vec<&'a str>>,
+    pub language: Option<Vec<&'a str>>,
+    pub translation: Option<Vec<Cow<'a, str>>>,
+    pub record_id: Option<Vec<&'a str>>,
+    pub record_sub_id: Option<Vec<&'a str>>,
+    pub field_value: Option<Vec<Cow<'a, str>>>,
 }
 
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
@@ -183,7 +359,7 @@ pub enum PickupType {
 }
 
 impl<'a> csvelo::ParseCsvField<'a> for PickupType {
-    fn parse_csv_field(buffer: &'a [u8]) -> std::result::Result<Self, ()>
+    fn parse_csv_field(buffer: &'a [u8]) -> csvelo::Result<Self>
     where
         Self: 'a,
     {
@@ -197,6 +373,18 @@ impl<'a> csvelo::ParseCsvField<'a> for PickupType {
     }
 }
 
+impl csvelo::WriteCsvField for PickupType {
+    fn write_csv_field(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(match self {
+            PickupType::Regular => b"0",
+            PickupType::NotAvailable => b"1",
+            PickupType::MustPhone => b"2",
+            PickupType::MustCoordinateWithDriver => b"3",
+            PickupType::Unknown => b"",
+        });
+    }
+}
+
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub enum DropOffType {
     #[default]
@@ -208,7 +396,7 @@ pub enum DropOffType {
 }
 
 impl<'a> csvelo::ParseCsvField<'a> for DropOffType {
-    fn parse_csv_field(buffer: &'a [u8]) -> std::result::Result<Self, ()>
+    fn parse_csv_field(buffer: &'a [u8]) -> csvelo::Result<Self>
     where
         Self: 'a,
     {
@@ -222,6 +410,18 @@ impl<'a> csvelo::ParseCsvField<'a> for DropOffType {
     }
 }
 
+impl csvelo::WriteCsvField for DropOffType {
+    fn write_csv_field(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(match self {
+            DropOffType::Regular => b"0",
+            DropOffType::NotAvailable => b"1",
+            DropOffType::MustPhone => b"2",
+            DropOffType::MustCoordinateWithDriver => b"3",
+            DropOffType::Unknown => b"",
+        });
+    }
+}
+
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub enum ContinuousPickupType {
     #[default]
@@ -233,7 +433,7 @@ pub enum ContinuousPickupType {
 }
 
 impl<'a> csvelo::ParseCsvField<'a> for ContinuousPickupType {
-    fn parse_csv_field(buffer: &'a [u8]) -> std::result::Result<Self, ()>
+    fn parse_csv_field(buffer: &'a [u8]) -> csvelo::Result<Self>
     where
         Self: 'a,
     {
@@ -247,6 +447,18 @@ impl<'a> csvelo::ParseCsvField<'a> for ContinuousPickupType {
     }
 }
 
+impl csvelo::WriteCsvField for ContinuousPickupType {
+    fn write_csv_field(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(match self {
+            ContinuousPickupType::Regular => b"0",
+            ContinuousPickupType::NotAvailable => b"1",
+            ContinuousPickupType::MustPhone => b"2",
+            ContinuousPickupType::MustCoordinateWithDriver => b"3",
+            ContinuousPickupType::Unknown => b"",
+        });
+    }
+}
+
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub enum ContinuousDropOffType {
     #[default]
@@ -258,7 +470,7 @@ pub enum ContinuousDropOffType {
 }
 
 impl<'a> csvelo::ParseCsvField<'a> for ContinuousDropOffType {
-    fn parse_csv_field(buffer: &'a [u8]) -> std::result::Result<Self, ()>
+    fn parse_csv_field(buffer: &'a [u8]) -> csvelo::Result<Self>
     where
         Self: 'a,
     {
@@ -272,6 +484,18 @@ impl<'a> csvelo::ParseCsvField<'a> for ContinuousDropOffType {
     }
 }
 
+impl csvelo::WriteCsvField for ContinuousDropOffType {
+    fn write_csv_field(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(match self {
+            ContinuousDropOffType::Regular => b"0",
+            ContinuousDropOffType::NotAvailable => b"1",
+            ContinuousDropOffType::MustPhone => b"2",
+            ContinuousDropOffType::MustCoordinateWithDriver => b"3",
+            ContinuousDropOffType::Unknown => b"",
+        });
+    }
+}
+
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub enum TimePointType {
     Approximate,
@@ -281,7 +505,7 @@ pub enum TimePointType {
 }
 
 impl<'a> csvelo::ParseCsvField<'a> for TimePointType {
-    fn parse_csv_field(buffer: &'a [u8]) -> std::result::Result<Self, ()>
+    fn parse_csv_field(buffer: &'a [u8]) -> csvelo::Result<Self>
     where
         Self: 'a,
     {
@@ -293,6 +517,349 @@ impl<'a> csvelo::ParseCsvField<'a> for TimePointType {
     }
 }
 
+impl csvelo::WriteCsvField for TimePointType {
+    fn write_csv_field(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(match self {
+            TimePointType::Approximate => b"0",
+            TimePointType::Exact => b"1",
+            TimePointType::Unknown => b"",
+        });
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum ExactTimes {
+    #[default]
+    FrequencyBased,
+    ScheduleBased,
+    Unknown,
+}
+
+impl<'a> csvelo::ParseCsvField<'a> for ExactTimes {
+    fn parse_csv_field(buffer: &'a [u8]) -> csvelo::Result<Self>
+    where
+        Self: 'a,
+    {
+        match buffer.trim_ascii() {
+            b"" | b"0" => Ok(ExactTimes::FrequencyBased),
+            b"1" => Ok(ExactTimes::ScheduleBased),
+            _ => Ok(ExactTimes::Unknown),
+        }
+    }
+}
+
+impl csvelo::WriteCsvField for ExactTimes {
+    fn write_csv_field(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(match self {
+            ExactTimes::FrequencyBased => b"0",
+            ExactTimes::ScheduleBased => b"1",
+            ExactTimes::Unknown => b"",
+        });
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum TransferType {
+    #[default]
+    Recommended,
+    Timed,
+    MinimumTime,
+    NotPossible,
+    InSeat,
+    ReBoard,
+    Unknown,
+}
+
+impl<'a> csvelo::ParseCsvField<'a> for TransferType {
+    fn parse_csv_field(buffer: &'a [u8]) -> csvelo::Result<Self>
+    where
+        Self: 'a,
+    {
+        match buffer.trim_ascii() {
+            b"" | b"0" => Ok(TransferType::Recommended),
+            b"1" => Ok(TransferType::Timed),
+            b"2" => Ok(TransferType::MinimumTime),
+            b"3" => Ok(TransferType::NotPossible),
+            b"4" => Ok(TransferType::InSeat),
+            b"5" => Ok(TransferType::ReBoard),
+            _ => Ok(TransferType::Unknown),
+        }
+    }
+}
+
+impl csvelo::WriteCsvField for TransferType {
+    fn write_csv_field(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(match self {
+            TransferType::Recommended => b"0",
+            TransferType::Timed => b"1",
+            TransferType::MinimumTime => b"2",
+            TransferType::NotPossible => b"3",
+            TransferType::InSeat => b"4",
+            TransferType::ReBoard => b"5",
+            TransferType::Unknown => b"",
+        });
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum PathwayMode {
+    Walkway,
+    Stairs,
+    MovingSidewalk,
+    Escalator,
+    Elevator,
+    FareGate,
+    ExitGate,
+    #[default]
+    Unknown,
+}
+
+impl<'a> csvelo::ParseCsvField<'a> for PathwayMode {
+    fn parse_csv_field(buffer: &'a [u8]) -> csvelo::Result<Self>
+    where
+        Self: 'a,
+    {
+        match buffer.trim_ascii() {
+            b"1" => Ok(PathwayMode::Walkway),
+            b"2" => Ok(PathwayMode::Stairs),
+            b"3" => Ok(PathwayMode::MovingSidewalk),
+            b"4" => Ok(PathwayMode::Escalator),
+            b"5" => Ok(PathwayMode::Elevator),
+            b"6" => Ok(PathwayMode::FareGate),
+            b"7" => Ok(PathwayMode::ExitGate),
+            _ => Ok(PathwayMode::Unknown),
+        }
+    }
+}
+
+impl csvelo::WriteCsvField for PathwayMode {
+    fn write_csv_field(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(match self {
+            PathwayMode::Walkway => b"1",
+            PathwayMode::Stairs => b"2",
+            PathwayMode::MovingSidewalk => b"3",
+            PathwayMode::Escalator => b"4",
+            PathwayMode::Elevator => b"5",
+            PathwayMode::FareGate => b"6",
+            PathwayMode::ExitGate => b"7",
+            PathwayMode::Unknown => b"",
+        });
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum IsBidirectional {
+    Unidirectional,
+    Bidirectional,
+    #[default]
+    Unknown,
+}
+
+impl<'a> csvelo::ParseCsvField<'a> for IsBidirectional {
+    fn parse_csv_field(buffer: &'a [u8]) -> csvelo::Result<Self>
+    where
+        Self: 'a,
+    {
+        match buffer.trim_ascii() {
+            b"0" => Ok(IsBidirectional::Unidirectional),
+            b"1" => Ok(IsBidirectional::Bidirectional),
+            _ => Ok(IsBidirectional::Unknown),
+        }
+    }
+}
+
+impl csvelo::WriteCsvField for IsBidirectional {
+    fn write_csv_field(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(match self {
+            IsBidirectional::Unidirectional => b"0",
+            IsBidirectional::Bidirectional => b"1",
+            IsBidirectional::Unknown => b"",
+        });
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum PaymentMethod {
+    OnBoard,
+    BeforeBoarding,
+    #[default]
+    Unknown,
+}
+
+impl<'a> csvelo::ParseCsvField<'a> for PaymentMethod {
+    fn parse_csv_field(buffer: &'a [u8]) -> csvelo::Result<Self>
+    where
+        Self: 'a,
+    {
+        match buffer.trim_ascii() {
+            b"0" => Ok(PaymentMethod::OnBoard),
+            b"1" => Ok(PaymentMethod::BeforeBoarding),
+            _ => Ok(PaymentMethod::Unknown),
+        }
+    }
+}
+
+impl csvelo::WriteCsvField for PaymentMethod {
+    fn write_csv_field(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(match self {
+            PaymentMethod::OnBoard => b"0",
+            PaymentMethod::BeforeBoarding => b"1",
+            PaymentMethod::Unknown => b"",
+        });
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum FareTransfers {
+    NotAllowed,
+    Once,
+    Twice,
+    #[default]
+    Unlimited,
+    Unknown,
+}
+
+impl<'a> csvelo::ParseCsvField<'a> for FareTransfers {
+    fn parse_csv_field(buffer: &'a [u8]) -> csvelo::Result<Self>
+    where
+        Self: 'a,
+    {
+        match buffer.trim_ascii() {
+            b"0" => Ok(FareTransfers::NotAllowed),
+            b"1" => Ok(FareTransfers::Once),
+            b"2" => Ok(FareTransfers::Twice),
+            b"" => Ok(FareTransfers::Unlimited),
+            _ => Ok(FareTransfers::Unknown),
+        }
+    }
+}
+
+impl csvelo::WriteCsvField for FareTransfers {
+    fn write_csv_field(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(match self {
+            FareTransfers::NotAllowed => b"0",
+            FareTransfers::Once => b"1",
+            FareTransfers::Twice => b"2",
+            FareTransfers::Unlimited => b"",
+            FareTransfers::Unknown => b"",
+        });
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum FareMediaType {
+    None,
+    PaperTicket,
+    TransitCard,
+    ContactlessEmv,
+    MobileApp,
+    #[default]
+    Unknown,
+}
+
+impl<'a> csvelo::ParseCsvField<'a> for FareMediaType {
+    fn parse_csv_field(buffer: &'a [u8]) -> csvelo::Result<Self>
+    where
+        Self: 'a,
+    {
+        match buffer.trim_ascii() {
+            b"0" => Ok(FareMediaType::None),
+            b"1" => Ok(FareMediaType::PaperTicket),
+            b"2" => Ok(FareMediaType::TransitCard),
+            b"3" => Ok(FareMediaType::ContactlessEmv),
+            b"4" => Ok(FareMediaType::MobileApp),
+            _ => Ok(FareMediaType::Unknown),
+        }
+    }
+}
+
+impl csvelo::WriteCsvField for FareMediaType {
+    fn write_csv_field(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(match self {
+            FareMediaType::None => b"0",
+            FareMediaType::PaperTicket => b"1",
+            FareMediaType::TransitCard => b"2",
+            FareMediaType::ContactlessEmv => b"3",
+            FareMediaType::MobileApp => b"4",
+            FareMediaType::Unknown => b"",
+        });
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum DurationLimitType {
+    DepartureToArrival,
+    DepartureToDeparture,
+    ArrivalToDeparture,
+    ArrivalToArrival,
+    #[default]
+    Unknown,
+}
+
+impl<'a> csvelo::ParseCsvField<'a> for DurationLimitType {
+    fn parse_csv_field(buffer: &'a [u8]) -> csvelo::Result<Self>
+    where
+        Self: 'a,
+    {
+        match buffer.trim_ascii() {
+            b"0" => Ok(DurationLimitType::DepartureToArrival),
+            b"1" => Ok(DurationLimitType::DepartureToDeparture),
+            b"2" => Ok(DurationLimitType::ArrivalToDeparture),
+            b"3" => Ok(DurationLimitType::ArrivalToArrival),
+            _ => Ok(DurationLimitType::Unknown),
+        }
+    }
+}
+
+impl csvelo::WriteCsvField for DurationLimitType {
+    fn write_csv_field(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(match self {
+            DurationLimitType::DepartureToArrival => b"0",
+            DurationLimitType::DepartureToDeparture => b"1",
+            DurationLimitType::ArrivalToDeparture => b"2",
+            DurationLimitType::ArrivalToArrival => b"3",
+            DurationLimitType::Unknown => b"",
+        });
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum FareTransferType {
+    /// The cost is the sum of the first leg and the transfer (A + AB).
+    FromLegPlusTransfer,
+    /// The cost is the sum of both legs and the transfer (A + AB + B).
+    FromLegPlusTransferPlusToLeg,
+    /// The cost is the transfer only (AB).
+    TransferOnly,
+    #[default]
+    Unknown,
+}
+
+impl<'a> csvelo::ParseCsvField<'a> for FareTransferType {
+    fn parse_csv_field(buffer: &'a [u8]) -> csvelo::Result<Self>
+    where
+        Self: 'a,
+    {
+        match buffer.trim_ascii() {
+            b"0" => Ok(FareTransferType::FromLegPlusTransfer),
+            b"1" => Ok(FareTransferType::FromLegPlusTransferPlusToLeg),
+            b"2" => Ok(FareTransferType::TransferOnly),
+            _ => Ok(FareTransferType::Unknown),
+        }
+    }
+}
+
+impl csvelo::WriteCsvField for FareTransferType {
+    fn write_csv_field(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(match self {
+            FareTransferType::FromLegPlusTransfer => b"0",
+            FareTransferType::FromLegPlusTransferPlusToLeg => b"1",
+            FareTransferType::TransferOnly => b"2",
+            FareTransferType::Unknown => b"",
+        });
+    }
+}
+
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub enum LocationType {
     #[default]
@@ -305,7 +872,7 @@ pub enum LocationType {
 }
 
 impl<'a> csvelo::ParseCsvField<'a> for LocationType {
-    fn parse_csv_field(buffer: &'a [u8]) -> std::result::Result<Self, ()>
+    fn parse_csv_field(buffer: &'a [u8]) -> csvelo::Result<Self>
     where
         Self: 'a,
     {
@@ -320,6 +887,19 @@ impl<'a> csvelo::ParseCsvField<'a> for LocationType {
     }
 }
 
+impl csvelo::WriteCsvField for LocationType {
+    fn write_csv_field(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(match self {
+            LocationType::Stop => b"0",
+            LocationType::Station => b"1",
+            LocationType::Entrance => b"2",
+            LocationType::Generic => b"3",
+            LocationType::BoardingArea => b"4",
+            LocationType::Unknown => b"",
+        });
+    }
+}
+
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub enum WheelchairBoarding {
     #[default]
@@ -330,7 +910,7 @@ pub enum WheelchairBoarding {
 }
 
 impl<'a> csvelo::ParseCsvField<'a> for WheelchairBoarding {
-    fn parse_csv_field(buffer: &'a [u8]) -> std::result::Result<Self, ()>
+    fn parse_csv_field(buffer: &'a [u8]) -> csvelo::Result<Self>
     where
         Self: 'a,
     {
@@ -343,15 +923,27 @@ impl<'a> csvelo::ParseCsvField<'a> for WheelchairBoarding {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+impl csvelo::WriteCsvField for WheelchairBoarding {
+    fn write_csv_field(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(match self {
+            WheelchairBoarding::NoInfoOrSeeParent => b"0",
+            WheelchairBoarding::SomeAccessibility => b"1",
+            WheelchairBoarding::NoAccessibility => b"2",
+            WheelchairBoarding::Unknown => b"",
+        });
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub enum DirectionId {
     Outbound,
     Inbound,
+    #[default]
     Unknown,
 }
 
 impl<'a> csvelo::ParseCsvField<'a> for DirectionId {
-    fn parse_csv_field(buffer: &'a [u8]) -> std::result::Result<Self, ()>
+    fn parse_csv_field(buffer: &'a [u8]) -> csvelo::Result<Self>
     where
         Self: 'a,
     {
@@ -363,6 +955,16 @@ impl<'a> csvelo::ParseCsvField<'a> for DirectionId {
     }
 }
 
+impl csvelo::WriteCsvField for DirectionId {
+    fn write_csv_field(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(match self {
+            DirectionId::Outbound => b"0",
+            DirectionId::Inbound => b"1",
+            DirectionId::Unknown => b"",
+        });
+    }
+}
+
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub enum WheelchairAccessible {
     #[default]
@@ -373,7 +975,7 @@ pub enum WheelchairAccessible {
 }
 
 impl<'a> csvelo::ParseCsvField<'a> for WheelchairAccessible {
-    fn parse_csv_field(buffer: &'a [u8]) -> std::result::Result<Self, ()>
+    fn parse_csv_field(buffer: &'a [u8]) -> csvelo::Result<Self>
     where
         Self: 'a,
     {
@@ -386,6 +988,17 @@ impl<'a> csvelo::ParseCsvField<'a> for WheelchairAccessible {
     }
 }
 
+impl csvelo::WriteCsvField for WheelchairAccessible {
+    fn write_csv_field(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(match self {
+            WheelchairAccessible::NoInfo => b"0",
+            WheelchairAccessible::AtLeastOne => b"1",
+            WheelchairAccessible::No => b"2",
+            WheelchairAccessible::Unknown => b"",
+        });
+    }
+}
+
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub enum BikesAllowed {
     #[default]
@@ -396,7 +1009,7 @@ pub enum BikesAllowed {
 }
 
 impl<'a> csvelo::ParseCsvField<'a> for BikesAllowed {
-    fn parse_csv_field(buffer: &'a [u8]) -> std::result::Result<Self, ()>
+    fn parse_csv_field(buffer: &'a [u8]) -> csvelo::Result<Self>
     where
         Self: 'a,
     {
@@ -409,6 +1022,17 @@ impl<'a> csvelo::ParseCsvField<'a> for BikesAllowed {
     }
 }
 
+impl csvelo::WriteCsvField for BikesAllowed {
+    fn write_csv_field(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(match self {
+            BikesAllowed::NoInfo => b"0",
+            BikesAllowed::AtLeastOne => b"1",
+            BikesAllowed::No => b"2",
+            BikesAllowed::Unknown => b"",
+        });
+    }
+}
+
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub enum RouteType {
     #[default]
@@ -422,11 +1046,14 @@ pub enum RouteType {
     Funicular,
     Trolleybus,
     Monorail,
+    /// A numeric route type that is not part of the specification, e.g. one of the extended
+    /// route types like 700 for bus services.
+    Other(u16),
     Unknown,
 }
 
 impl<'a> csvelo::ParseCsvField<'a> for RouteType {
-    fn parse_csv_field(buffer: &'a [u8]) -> std::result::Result<Self, ()>
+    fn parse_csv_field(buffer: &'a [u8]) -> csvelo::Result<Self>
     where
         Self: 'a,
     {
@@ -441,12 +1068,35 @@ impl<'a> csvelo::ParseCsvField<'a> for RouteType {
             b"7" => Ok(RouteType::Funicular),
             b"11" => Ok(RouteType::Trolleybus),
             b"12" => Ok(RouteType::Monorail),
-            _ => Ok(RouteType::Unknown),
+            other => Ok(std::str::from_utf8(other)
+                .ok()
+                .and_then(|other| other.parse().ok())
+                .map_or(RouteType::Unknown, RouteType::Other)),
         }
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+impl csvelo::WriteCsvField for RouteType {
+    fn write_csv_field(&self, buffer: &mut Vec<u8>) {
+        let route_type: u16 = match self {
+            RouteType::Tram => 0,
+            RouteType::Subway => 1,
+            RouteType::Rail => 2,
+            RouteType::Bus => 3,
+            RouteType::Ferry => 4,
+            RouteType::CableTram => 5,
+            RouteType::AerialLift => 6,
+            RouteType::Funicular => 7,
+            RouteType::Trolleybus => 11,
+            RouteType::Monorail => 12,
+            RouteType::Other(route_type) => *route_type,
+            RouteType::Unknown => return,
+        };
+        route_type.write_csv_field(buffer);
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct Color {
     pub r: u8,
     pub g: u8,
@@ -454,13 +1104,13 @@ pub struct Color {
 }
 
 impl<'a> csvelo::ParseCsvField<'a> for Color {
-    fn parse_csv_field(buffer: &'a [u8]) -> std::result::Result<Self, ()>
+    fn parse_csv_field(buffer: &'a [u8]) -> csvelo::Result<Self>
     where
         Self: 'a,
     {
         let buffer = buffer.trim_ascii();
         if buffer.len() != 6 {
-            return Err(());
+            return Err(csvelo::Error::new(csvelo::ErrorKind::InvalidValue));
         }
         let r = hex_char_to_number(buffer[0]) * 16 + hex_char_to_number(buffer[1]);
         let g = hex_char_to_number(buffer[2]) * 16 + hex_char_to_number(buffer[3]);
@@ -469,6 +1119,37 @@ impl<'a> csvelo::ParseCsvField<'a> for Color {
     }
 }
 
+impl csvelo::WriteCsvField for Color {
+    fn write_csv_field(&self, buffer: &mut Vec<u8>) {
+        write!(buffer, "{:02X}{:02X}{:02X}", self.r, self.g, self.b).unwrap();
+    }
+}
+
+/// A color in a column where empty fields are allowed. Fields that are not empty still have to
+/// be valid colors.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OptionalColor(pub Option<Color>);
+
+impl<'a> csvelo::ParseCsvField<'a> for OptionalColor {
+    fn parse_csv_field(buffer: &'a [u8]) -> csvelo::Result<Self>
+    where
+        Self: 'a,
+    {
+        if buffer.trim_ascii().is_empty() {
+            return Ok(OptionalColor(None));
+        }
+        Color::parse_csv_field(buffer).map(|color| OptionalColor(Some(color)))
+    }
+}
+
+impl csvelo::WriteCsvField for OptionalColor {
+    fn write_csv_field(&self, buffer: &mut Vec<u8>) {
+        if let Some(color) = &self.0 {
+            color.write_csv_field(buffer);
+        }
+    }
+}
+
 fn hex_char_to_number(c: u8) -> u8 {
     match c {
         b'0'..=b'9' => c - b'0',
@@ -478,15 +1159,16 @@ fn hex_char_to_number(c: u8) -> u8 {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub enum ServiceAvailable {
     Yes,
     No,
+    #[default]
     Unknown,
 }
 
 impl<'a> csvelo::ParseCsvField<'a> for ServiceAvailable {
-    fn parse_csv_field(buffer: &'a [u8]) -> std::result::Result<Self, ()>
+    fn parse_csv_field(buffer: &'a [u8]) -> csvelo::Result<Self>
     where
         Self: 'a,
     {
@@ -498,42 +1180,26 @@ impl<'a> csvelo::ParseCsvField<'a> for ServiceAvailable {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Date {
-    pub year: u16,
-    pub month: u8,
-    pub day: u8,
-}
-
-impl<'a> csvelo::ParseCsvField<'a> for Date {
-    fn parse_csv_field(buffer: &'a [u8]) -> std::result::Result<Self, ()>
-    where
-        Self: 'a,
-    {
-        let buffer = buffer.trim_ascii();
-        if buffer.len() != 8 {
-            return Err(());
-        }
-
-        let year = (parse_digit(buffer[0]) as u16) * 1000
-            + (parse_digit(buffer[1]) as u16) * 100
-            + (parse_digit(buffer[2]) as u16) * 10
-            + (parse_digit(buffer[3]) as u16);
-        let month = parse_digit(buffer[4]) * 10 + parse_digit(buffer[5]);
-        let day = parse_digit(buffer[6]) * 10 + parse_digit(buffer[7]);
-        Ok(Date { year, month, day })
+impl csvelo::WriteCsvField for ServiceAvailable {
+    fn write_csv_field(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(match self {
+            ServiceAvailable::Yes => b"1",
+            ServiceAvailable::No => b"0",
+            ServiceAvailable::Unknown => b"",
+        });
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub enum ExceptionType {
     Added,
     Removed,
+    #[default]
     Unknown,
 }
 
 impl<'a> csvelo::ParseCsvField<'a> for ExceptionType {
-    fn parse_csv_field(buffer: &'a [u8]) -> std::result::Result<Self, ()>
+    fn parse_csv_field(buffer: &'a [u8]) -> csvelo::Result<Self>
     where
         Self: 'a,
     {
@@ -545,15 +1211,26 @@ impl<'a> csvelo::ParseCsvField<'a> for ExceptionType {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+impl csvelo::WriteCsvField for ExceptionType {
+    fn write_csv_field(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(match self {
+            ExceptionType::Added => b"1",
+            ExceptionType::Removed => b"2",
+            ExceptionType::Unknown => b"",
+        });
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub enum YesOrNo {
     Yes,
     No,
+    #[default]
     Unknown,
 }
 
 impl<'a> csvelo::ParseCsvField<'a> for YesOrNo {
-    fn parse_csv_field(buffer: &'a [u8]) -> std::result::Result<Self, ()>
+    fn parse_csv_field(buffer: &'a [u8]) -> csvelo::Result<Self>
     where
         Self: 'a,
     {
@@ -565,30 +1242,121 @@ impl<'a> csvelo::ParseCsvField<'a> for YesOrNo {
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+impl csvelo::WriteCsvField for YesOrNo {
+    fn write_csv_field(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(match self {
+            YesOrNo::No => b"0",
+            YesOrNo::Yes => b"1",
+            YesOrNo::Unknown => b"",
+        });
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default)]
 pub struct OptionalF32(pub Option<f32>);
 
 impl<'a> csvelo::ParseCsvField<'a> for OptionalF32 {
-    fn parse_csv_field(buffer: &'a [u8]) -> std::result::Result<Self, ()>
+    fn parse_csv_field(buffer: &'a [u8]) -> csvelo::Result<Self>
     where
         Self: 'a,
     {
-        let s = std::str::from_utf8(buffer).map_err(|_| ())?;
-        let f = s.parse::<f32>().map_err(|_| ()).ok();
+        let s = std::str::from_utf8(buffer)?;
+        let f = s.parse::<f32>().ok();
         Ok(OptionalF32(f))
     }
 }
 
-#[derive(Copy, Clone, PartialEq, Eq)]
+impl csvelo::WriteCsvField for OptionalF32 {
+    fn write_csv_field(&self, buffer: &mut Vec<u8>) {
+        self.0.write_csv_field(buffer);
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default)]
+pub struct OptionalF64(pub Option<f64>);
+
+impl<'a> csvelo::ParseCsvField<'a> for OptionalF64 {
+    fn parse_csv_field(buffer: &'a [u8]) -> csvelo::Result<Self>
+    where
+        Self: 'a,
+    {
+        if buffer.trim_ascii().is_empty() {
+            return Ok(OptionalF64(None));
+        }
+        f64::parse_csv_field(buffer).map(|value| OptionalF64(Some(value)))
+    }
+}
+
+impl csvelo::WriteCsvField for OptionalF64 {
+    fn write_csv_field(&self, buffer: &mut Vec<u8>) {
+        self.0.write_csv_field(buffer);
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default)]
+pub struct OptionalU32(pub Option<u32>);
+
+impl<'a> csvelo::ParseCsvField<'a> for OptionalU32 {
+    fn parse_csv_field(buffer: &'a [u8]) -> csvelo::Result<Self>
+    where
+        Self: 'a,
+    {
+        if buffer.trim_ascii().is_empty() {
+            return Ok(OptionalU32(None));
+        }
+        u32::parse_csv_field(buffer).map(|value| OptionalU32(Some(value)))
+    }
+}
+
+impl csvelo::WriteCsvField for OptionalU32 {
+    fn write_csv_field(&self, buffer: &mut Vec<u8>) {
+        self.0.write_csv_field(buffer);
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default)]
+pub struct OptionalI32(pub Option<i32>);
+
+impl<'a> csvelo::ParseCsvField<'a> for OptionalI32 {
+    fn parse_csv_field(buffer: &'a [u8]) -> csvelo::Result<Self>
+    where
+        Self: 'a,
+    {
+        if buffer.trim_ascii().is_empty() {
+            return Ok(OptionalI32(None));
+        }
+        i32::parse_csv_field(buffer).map(|value| OptionalI32(Some(value)))
+    }
+}
+
+impl csvelo::WriteCsvField for OptionalI32 {
+    fn write_csv_field(&self, buffer: &mut Vec<u8>) {
+        self.0.write_csv_field(buffer);
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct ServiceDayTime {
     seconds: u32,
 }
 
-#[derive(Debug, Copy, Clone)]
+impl ServiceDayTime {
+    /// Creates a time from the number of seconds since noon minus 12h of the service day.
+    /// The value may be larger than 24h for trips that continue after midnight.
+    pub fn from_seconds(seconds: u32) -> Self {
+        Self { seconds }
+    }
+
+    pub fn seconds(&self) -> u32 {
+        self.seconds
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default)]
 pub struct OptionalServiceDayTime(pub Option<ServiceDayTime>);
 
 impl<'a> csvelo::ParseCsvField<'a> for OptionalServiceDayTime {
-    fn parse_csv_field(buffer: &'a [u8]) -> std::result::Result<Self, ()>
+    fn parse_csv_field(buffer: &'a [u8]) -> csvelo::Result<Self>
     where
         Self: 'a,
     {
@@ -599,8 +1367,28 @@ impl<'a> csvelo::ParseCsvField<'a> for OptionalServiceDayTime {
     }
 }
 
+impl csvelo::WriteCsvField for OptionalServiceDayTime {
+    fn write_csv_field(&self, buffer: &mut Vec<u8>) {
+        self.0.write_csv_field(buffer);
+    }
+}
+
+/// Hours are not wrapped, so times after midnight are written as e.g. `25:10:00`.
+impl csvelo::WriteCsvField for ServiceDayTime {
+    fn write_csv_field(&self, buffer: &mut Vec<u8>) {
+        write!(
+            buffer,
+            "{:02}:{:02}:{:02}",
+            self.seconds / 3600,
+            (self.seconds / 60) % 60,
+            self.seconds % 60
+        )
+        .unwrap();
+    }
+}
+
 fn parse_two_digit_int(buffer: &[u8]) -> u8 {
-    return parse_digit(buffer[0]) * 10 + parse_digit(buffer[1]);
+    parse_digit(buffer[0]) * 10 + parse_digit(buffer[1])
 }
 
 fn parse_digit(c: u8) -> u8 {
@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+
+use crate::{Gtfs, Translations};
+
+/// Index over translations.txt for fast lookups of translated field values.
+#[derive(Debug, Default)]
+pub struct TranslationLookup<'a> {
+    /// Key: (table_name, field_name, language, record_id, record_sub_id).
+    by_record: HashMap<(&'a str, &'a str, &'a str, &'a str, &'a str), &'a str>,
+    /// Key: (table_name, field_name, language, field_value).
+    by_field_value: HashMap<(&'a str, &'a str, &'a str, &'a str), &'a str>,
+}
+
+impl Gtfs<'_> {
+    /// Builds an index that allows looking up translations of names and other texts in the feed.
+    pub fn translation_lookup(&self) -> TranslationLookup<'_> {
+        match self.translations.data.as_ref() {
+            Some(translations) => TranslationLookup::new(translations),
+            None => TranslationLookup::default(),
+        }
+    }
+}
+
+impl<'a> TranslationLookup<'a> {
+    pub fn new(translations: &'a Translations<'_>) -> Self {
+        let mut lookup = Self::default();
+        let (Some(table_names), Some(field_names), Some(languages), Some(texts)) = (
+            translations.table_name.as_ref(),
+            translations.field_name.as_ref(),
+            translations.language.as_ref(),
+            translations.translation.as_ref(),
+        ) else {
+            return lookup;
+        };
+        let get = |column: &'a Option<Vec<&str>>, i: usize| {
+            column
+                .as_ref()
+                .map(|column| column[i].trim())
+                .filter(|value| !value.is_empty())
+        };
+        let field_value = |i: usize| {
+            translations
+                .field_value
+                .as_ref()
+                .map(|column| column[i].trim())
+                .filter(|value| !value.is_empty())
+        };
+
+        for i in 0..table_names.len() {
+            let table_name = table_names[i].trim();
+            let field_name = field_names[i].trim();
+            let language = languages[i].trim();
+            if let Some(record_id) = get(&translations.record_id, i) {
+                let record_sub_id = get(&translations.record_sub_id, i).unwrap_or("");
+                lookup.by_record.insert(
+                    (table_name, field_name, language, record_id, record_sub_id),
+                    &texts[i],
+                );
+            } else if let Some(field_value) = field_value(i) {
+                lookup
+                    .by_field_value
+                    .insert((table_name, field_name, language, field_value), &texts[i]);
+            } else {
+                // Used for feed_info.txt which has only a single record.
+                lookup
+                    .by_record
+                    .insert((table_name, field_name, language, "", ""), &texts[i]);
+            }
+        }
+        lookup
+    }
+
+    /// Get the translation of a field of the given record.
+    /// `record_sub_id` is only used for stop_times.txt where it is the `stop_sequence`.
+    pub fn translate_record(
+        &self,
+        table_name: &str,
+        field_name: &str,
+        record_id: &str,
+        record_sub_id: Option<&str>,
+        language: &str,
+    ) -> Option<&'a str> {
+        for language in language_candidates(language) {
+            let key = (
+                table_name,
+                field_name,
+                language,
+                record_id,
+                record_sub_id.unwrap_or(""),
+            );
+            if let Some(text) = self.by_record.get(&key) {
+                return Some(text);
+            }
+        }
+        None
+    }
+
+    /// Get the translation for all fields that have the given original value.
+    pub fn translate_field_value(
+        &self,
+        table_name: &str,
+        field_name: &str,
+        field_value: &str,
+        language: &str,
+    ) -> Option<&'a str> {
+        for language in language_candidates(language) {
+            let key = (table_name, field_name, language, field_value);
+            if let Some(text) = self.by_field_value.get(&key) {
+                return Some(text);
+            }
+        }
+        None
+    }
+
+    /// Get the translation of a field, first by the record id and then by the original value.
+    /// E.g. `translate("stops", "stop_name", stop_id, stop_name, "de")`.
+    pub fn translate(
+        &self,
+        table_name: &str,
+        field_name: &str,
+        record_id: &str,
+        field_value: &str,
+        language: &str,
+    ) -> Option<&'a str> {
+        self.translate_record(table_name, field_name, record_id, None, language)
+            .or_else(|| self.translate_field_value(table_name, field_name, field_value, language))
+    }
+}
+
+/// The language itself and, if it has a region like `de-CH`, its primary language `de`.
+fn language_candidates(language: &str) -> impl Iterator<Item = &str> {
+    let primary = language
+        .split_once('-')
+        .map(|(primary, _)| primary)
+        .filter(|primary| !primary.is_empty());
+    std::iter::once(language).chain(primary)
+}
@@ -0,0 +1,583 @@
+//! Checks a loaded feed for problems that make it unusable or inconsistent, like references to
+//! ids that are not defined or stop times that go back in time.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{Gtfs, GtfsFile, GtfsFilePresence, LocationType, ServiceDayTime};
+
+/// When a check fails for more rows than this, only the first rows are reported individually.
+const MAX_ROWS_PER_CHECK: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FindingKind {
+    MissingRequiredFile,
+    MissingRequiredColumn,
+    /// A column that is only required in some cases, e.g. `agency_id` with multiple agencies.
+    MissingConditionallyRequiredColumn,
+    /// A field that could not be parsed. It has the default value in the loaded data.
+    InvalidValue,
+    DuplicateKey,
+    ForeignKeyViolation,
+    DuplicateStopSequence,
+    ArrivalAfterDeparture,
+    DecreasingStopTime,
+    /// A stop or platform that is not served by any trip.
+    UnusedStop,
+    /// Summarizes further rows that have the same problem as the previously reported ones.
+    MoreRows,
+}
+
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub severity: Severity,
+    pub kind: FindingKind,
+    pub file: GtfsFile,
+    /// Index of the record in the file, not counting the header.
+    /// `None` if the finding is about the file as a whole.
+    pub row: Option<usize>,
+    pub column: Option<&'static str>,
+    pub message: String,
+}
+
+/// Validates the feed. This expects that all files have been loaded, i.e. that the buffers
+/// were created with [`crate::GtfsFilter::all`]. Otherwise, the filtered files are reported as
+/// missing.
+pub fn validate_gtfs(gtfs: &Gtfs) -> Vec<Finding> {
+    let mut findings = vec![];
+    check_required_files(gtfs, &mut findings);
+    check_required_columns(gtfs, &mut findings);
+    check_parse_errors(gtfs, &mut findings);
+    check_primary_keys(gtfs, &mut findings);
+    check_foreign_keys(gtfs, &mut findings);
+    check_stop_time_order(gtfs, &mut findings);
+    check_conditionally_required_columns(gtfs, &mut findings);
+    check_unused_stops(gtfs, &mut findings);
+    findings
+}
+
+fn check_required_files(gtfs: &Gtfs, findings: &mut Vec<Finding>) {
+    for ((file, len), (_, error)) in gtfs.file_lens().into_iter().zip(gtfs.file_errors()) {
+        if file.presence() == GtfsFilePresence::Required && len == 0 {
+            let message = match error {
+                Some(error) => format!("{} could not be parsed: {}", file.file_name(), error),
+                None => format!("{} is missing or empty", file.file_name()),
+            };
+            findings.push(Finding {
+                severity: Severity::Error,
+                kind: FindingKind::MissingRequiredFile,
+                file,
+                row: None,
+                column: None,
+                message,
+            });
+        }
+    }
+    let has_calendar = gtfs.calendars.len > 0 || gtfs.calendar_dates.len > 0;
+    if !has_calendar {
+        findings.push(Finding {
+            severity: Severity::Error,
+            kind: FindingKind::MissingRequiredFile,
+            file: GtfsFile::Calendar,
+            row: None,
+            column: None,
+            message: "Either calendar.txt or calendar_dates.txt is required".to_string(),
+        });
+    }
+}
+
+fn check_required_columns(gtfs: &Gtfs, findings: &mut Vec<Finding>) {
+    macro_rules! required_columns {
+        ($name:ident, $file:ident, [$($column:ident),*]) => {
+            if let Some(data) = gtfs.$name.data.as_ref() {
+                $(
+                    if gtfs.$name.len > 0 && data.$column.is_none() {
+                        findings.push(Finding {
+                            severity: Severity::Error,
+                            kind: FindingKind::MissingRequiredColumn,
+                            file: GtfsFile::$file,
+                            row: None,
+                            column: Some(stringify!($column)),
+                            message: format!(
+                                "Required column {} in {} is missing",
+                                stringify!($column),
+                                GtfsFile::$file.file_name(),
+                            ),
+                        });
+                    }
+                )*
+            }
+        };
+    }
+
+    required_columns!(
+        agencies,
+        Agencies,
+        [agency_name, agency_url, agency_timezone]
+    );
+    required_columns!(stops, Stops, [stop_id]);
+    required_columns!(routes, Routes, [route_id, route_type]);
+    required_columns!(trips, Trips, [route_id, service_id, trip_id]);
+    required_columns!(stop_times, StopTimes, [trip_id, stop_sequence]);
+    required_columns!(
+        calendars,
+        Calendar,
+        [
+            service_id, monday, tuesday, wednesday, thursday, friday, saturday, sunday, start_date,
+            end_date
+        ]
+    );
+    required_columns!(
+        calendar_dates,
+        CalendarDates,
+        [service_id, date, exception_type]
+    );
+    required_columns!(
+        fare_attributes,
+        FareAttributes,
+        [fare_id, price, currency_type, payment_method, transfers]
+    );
+    required_columns!(fare_rules, FareRules, [fare_id]);
+    required_columns!(timeframes, Timeframes, [timeframe_group_id, service_id]);
+    required_columns!(
+        rider_categories,
+        RiderCategories,
+        [
+            rider_category_id,
+            rider_category_name,
+            is_default_fare_category
+        ]
+    );
+    required_columns!(fare_media, FareMedia, [fare_media_id, fare_media_type]);
+    required_columns!(
+        fare_products,
+        FareProducts,
+        [fare_product_id, amount, currency]
+    );
+    required_columns!(fare_leg_rules, FareLegRules, [fare_product_id]);
+    required_columns!(fare_transfer_rules, FareTransferRules, [fare_transfer_type]);
+    required_columns!(areas, Areas, [area_id]);
+    required_columns!(stop_areas, StopAreas, [area_id, stop_id]);
+    required_columns!(
+        shapes,
+        Shapes,
+        [shape_id, shape_pt_lat, shape_pt_lon, shape_pt_sequence]
+    );
+    required_columns!(
+        frequencies,
+        Frequencies,
+        [trip_id, start_time, end_time, headway_secs]
+    );
+    required_columns!(transfers, Transfers, [transfer_type]);
+    required_columns!(
+        pathways,
+        Pathways,
+        [
+            pathway_id,
+            from_stop_id,
+            to_stop_id,
+            pathway_mode,
+            is_bidirectional
+        ]
+    );
+    required_columns!(levels, Levels, [level_id, level_index]);
+    required_columns!(
+        translations,
+        Translations,
+        [table_name, field_name, language, translation]
+    );
+    required_columns!(
+        feed_infos,
+        FeedInfos,
+        [feed_publisher_name, feed_publisher_url, feed_lang]
+    );
+    required_columns!(attributions, Attributions, [organization_name]);
+}
+
+fn check_parse_errors(gtfs: &Gtfs, findings: &mut Vec<Finding>) {
+    for (file, parse_errors) in gtfs.file_parse_errors() {
+        for (column, column_errors) in &parse_errors.columns {
+            for sample in &column_errors.samples {
+                findings.push(Finding {
+                    severity: Severity::Error,
+                    kind: FindingKind::InvalidValue,
+                    file,
+                    row: sample.error.record_index,
+                    column: Some(column),
+                    message: format!(
+                        "Invalid {} {:?}: {}",
+                        column,
+                        String::from_utf8_lossy(&sample.raw),
+                        sample.error.kind
+                    ),
+                });
+            }
+            let remaining_num = column_errors.errors_num - column_errors.samples.len();
+            if remaining_num > 0 {
+                findings.push(Finding {
+                    severity: Severity::Error,
+                    kind: FindingKind::MoreRows,
+                    file,
+                    row: None,
+                    column: Some(column),
+                    message: format!("{} more rows with the same problem", remaining_num),
+                });
+            }
+        }
+    }
+}
+
+fn check_primary_keys(gtfs: &Gtfs, findings: &mut Vec<Finding>) {
+    macro_rules! primary_key {
+        ($name:ident, $file:ident, $column:ident) => {
+            if let Some(ids) = gtfs.$name.data.as_ref().and_then(|d| d.$column.as_ref()) {
+                let mut seen = HashSet::new();
+                let duplicate_rows = ids
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, id)| !seen.insert(id.trim()))
+                    .map(|(row, _)| row);
+                report_rows(
+                    findings,
+                    duplicate_rows,
+                    Severity::Error,
+                    FindingKind::DuplicateKey,
+                    GtfsFile::$file,
+                    stringify!($column),
+                    |row| format!("Duplicate {} {:?}", stringify!($column), ids[row]),
+                );
+            }
+        };
+    }
+
+    primary_key!(agencies, Agencies, agency_id);
+    primary_key!(stops, Stops, stop_id);
+    primary_key!(routes, Routes, route_id);
+    primary_key!(trips, Trips, trip_id);
+    primary_key!(calendars, Calendar, service_id);
+    primary_key!(pathways, Pathways, pathway_id);
+    primary_key!(levels, Levels, level_id);
+    primary_key!(fare_attributes, FareAttributes, fare_id);
+    primary_key!(areas, Areas, area_id);
+}
+
+fn check_foreign_keys(gtfs: &Gtfs, findings: &mut Vec<Finding>) {
+    let stops = gtfs.stops.data.as_ref();
+    let trips = gtfs.trips.data.as_ref();
+    let routes = gtfs.routes.data.as_ref();
+    let agencies = gtfs.agencies.data.as_ref();
+    let stop_times = gtfs.stop_times.data.as_ref();
+
+    let stop_ids = collect_keys([stops.and_then(|s| s.stop_id.as_ref())]);
+    let trip_ids = collect_keys([trips.and_then(|t| t.trip_id.as_ref())]);
+    let route_ids = collect_keys([routes.and_then(|r| r.route_id.as_ref())]);
+    let agency_ids = collect_keys([agencies.and_then(|a| a.agency_id.as_ref())]);
+    let service_ids = collect_keys([
+        gtfs.calendars
+            .data
+            .as_ref()
+            .and_then(|c| c.service_id.as_ref()),
+        gtfs.calendar_dates
+            .data
+            .as_ref()
+            .and_then(|c| c.service_id.as_ref()),
+    ]);
+
+    let mut check = |file: GtfsFile,
+                     column: &'static str,
+                     values: Option<&Vec<&str>>,
+                     keys: &HashSet<&str>,
+                     target: GtfsFile,
+                     allow_empty: bool| {
+        let Some(values) = values else {
+            return;
+        };
+        let invalid_rows = values.iter().enumerate().filter_map(|(row, value)| {
+            let value = value.trim();
+            if (allow_empty && value.is_empty()) || keys.contains(value) {
+                None
+            } else {
+                Some(row)
+            }
+        });
+        report_rows(
+            findings,
+            invalid_rows,
+            Severity::Error,
+            FindingKind::ForeignKeyViolation,
+            file,
+            column,
+            |row| {
+                format!(
+                    "{} {:?} is not defined in {}",
+                    column,
+                    values[row],
+                    target.file_name()
+                )
+            },
+        );
+    };
+
+    check(
+        GtfsFile::StopTimes,
+        "trip_id",
+        stop_times.and_then(|s| s.trip_id.as_ref()),
+        &trip_ids,
+        GtfsFile::Trips,
+        false,
+    );
+    // The stop may be empty when a location group or GeoJSON location is used instead.
+    check(
+        GtfsFile::StopTimes,
+        "stop_id",
+        stop_times.and_then(|s| s.stop_id.as_ref()),
+        &stop_ids,
+        GtfsFile::Stops,
+        true,
+    );
+    check(
+        GtfsFile::Trips,
+        "route_id",
+        trips.and_then(|t| t.route_id.as_ref()),
+        &route_ids,
+        GtfsFile::Routes,
+        false,
+    );
+    check(
+        GtfsFile::Trips,
+        "service_id",
+        trips.and_then(|t| t.service_id.as_ref()),
+        &service_ids,
+        GtfsFile::Calendar,
+        false,
+    );
+    // The agency may be omitted when there is only a single agency.
+    check(
+        GtfsFile::Routes,
+        "agency_id",
+        routes.and_then(|r| r.agency_id.as_ref()),
+        &agency_ids,
+        GtfsFile::Agencies,
+        agency_ids.len() <= 1,
+    );
+    check(
+        GtfsFile::Stops,
+        "parent_station",
+        stops.and_then(|s| s.parent_station.as_ref()),
+        &stop_ids,
+        GtfsFile::Stops,
+        true,
+    );
+}
+
+fn check_stop_time_order(gtfs: &Gtfs, findings: &mut Vec<Finding>) {
+    let Some(stop_times) = gtfs.stop_times.data.as_ref() else {
+        return;
+    };
+    let (Some(trip_ids), Some(stop_sequences)) = (
+        stop_times.trip_id.as_ref(),
+        stop_times.stop_sequence.as_ref(),
+    ) else {
+        return;
+    };
+    let get_time = |times: &Option<Vec<crate::OptionalServiceDayTime>>, row: usize| {
+        times.as_ref().and_then(|times| times[row].0)
+    };
+
+    let mut rows_by_trip: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (row, trip_id) in trip_ids.iter().enumerate() {
+        rows_by_trip.entry(trip_id).or_default().push(row);
+    }
+    let mut trips: Vec<Vec<usize>> = rows_by_trip.into_values().collect();
+    // Makes the order of the findings deterministic.
+    trips.sort_unstable_by_key(|rows| rows[0]);
+
+    let mut duplicate_rows = vec![];
+    let mut arrival_after_departure_rows = vec![];
+    let mut decreasing_rows = vec![];
+    for mut rows in trips {
+        rows.sort_by_key(|row| stop_sequences[*row]);
+        let mut previous: Option<(usize, Option<ServiceDayTime>)> = None;
+        for row in rows {
+            let arrival = get_time(&stop_times.arrival_time, row);
+            let departure = get_time(&stop_times.departure_time, row);
+            if let (Some(arrival), Some(departure)) = (arrival, departure) {
+                if arrival > departure {
+                    arrival_after_departure_rows.push(row);
+                }
+            }
+            if let Some((previous_row, previous_departure)) = previous {
+                if stop_sequences[previous_row] == stop_sequences[row] {
+                    duplicate_rows.push(row);
+                }
+                if let (Some(previous_departure), Some(arrival)) =
+                    (previous_departure, arrival.or(departure))
+                {
+                    if arrival < previous_departure {
+                        decreasing_rows.push(row);
+                    }
+                }
+            }
+            let departure = departure.or(arrival);
+            previous = match (previous, departure) {
+                // Keep the last known time when the current stop has no times.
+                (Some((_, previous_departure)), None) => Some((row, previous_departure)),
+                _ => Some((row, departure)),
+            };
+        }
+    }
+
+    duplicate_rows.sort_unstable();
+    arrival_after_departure_rows.sort_unstable();
+    decreasing_rows.sort_unstable();
+    report_rows(
+        findings,
+        duplicate_rows.into_iter(),
+        Severity::Error,
+        FindingKind::DuplicateStopSequence,
+        GtfsFile::StopTimes,
+        "stop_sequence",
+        |row| {
+            format!(
+                "stop_sequence {} is used multiple times in trip {:?}",
+                stop_sequences[row], trip_ids[row]
+            )
+        },
+    );
+    report_rows(
+        findings,
+        arrival_after_departure_rows.into_iter(),
+        Severity::Error,
+        FindingKind::ArrivalAfterDeparture,
+        GtfsFile::StopTimes,
+        "arrival_time",
+        |row| format!("Arrival is after departure in trip {:?}", trip_ids[row]),
+    );
+    report_rows(
+        findings,
+        decreasing_rows.into_iter(),
+        Severity::Error,
+        FindingKind::DecreasingStopTime,
+        GtfsFile::StopTimes,
+        "arrival_time",
+        |row| {
+            format!(
+                "Arrival is before the departure at the previous stop in trip {:?}",
+                trip_ids[row]
+            )
+        },
+    );
+}
+
+fn collect_keys<'a, const N: usize>(columns: [Option<&Vec<&'a str>>; N]) -> HashSet<&'a str> {
+    columns
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|id| id.trim())
+        .collect()
+}
+
+/// Columns that are only required when the feed has more than one agency: agency_id in
+/// agency.txt and routes.txt.
+fn check_conditionally_required_columns(gtfs: &Gtfs, findings: &mut Vec<Finding>) {
+    if gtfs.agencies.len <= 1 {
+        return;
+    }
+    let agencies = gtfs.agencies.data.as_ref();
+    let routes = gtfs.routes.data.as_ref();
+    let mut report = |file: GtfsFile, column: &'static str| {
+        findings.push(Finding {
+            severity: Severity::Warning,
+            kind: FindingKind::MissingConditionallyRequiredColumn,
+            file,
+            row: None,
+            column: Some(column),
+            message: format!(
+                "Column {} in {} is required because there are multiple agencies",
+                column,
+                file.file_name()
+            ),
+        });
+    };
+    if agencies.is_some_and(|a| a.agency_id.is_none()) {
+        report(GtfsFile::Agencies, "agency_id");
+    }
+    if gtfs.routes.len > 0 && routes.is_some_and(|r| r.agency_id.is_none()) {
+        report(GtfsFile::Routes, "agency_id");
+    }
+}
+
+/// Stations, entrances and other locations are not expected in stop_times.txt, so only stops
+/// and platforms are checked.
+fn check_unused_stops(gtfs: &Gtfs, findings: &mut Vec<Finding>) {
+    let Some(stops) = gtfs.stops.data.as_ref() else {
+        return;
+    };
+    let Some(stop_ids) = stops.stop_id.as_ref() else {
+        return;
+    };
+    let used_stop_ids = collect_keys([gtfs
+        .stop_times
+        .data
+        .as_ref()
+        .and_then(|s| s.stop_id.as_ref())]);
+    let unused_rows = stop_ids.iter().enumerate().filter_map(|(row, stop_id)| {
+        let location_type = stops.location_type.as_ref().map(|types| &types[row]);
+        let is_stop = matches!(location_type, None | Some(LocationType::Stop));
+        (is_stop && !used_stop_ids.contains(stop_id.trim())).then_some(row)
+    });
+    report_rows(
+        findings,
+        unused_rows,
+        Severity::Warning,
+        FindingKind::UnusedStop,
+        GtfsFile::Stops,
+        "stop_id",
+        |row| format!("Stop {:?} is not used by any trip", stop_ids[row]),
+    );
+}
+
+/// Adds a finding for every row, but at most [`MAX_ROWS_PER_CHECK`] individual ones.
+fn report_rows(
+    findings: &mut Vec<Finding>,
+    rows: impl Iterator<Item = usize>,
+    severity: Severity,
+    kind: FindingKind,
+    file: GtfsFile,
+    column: &'static str,
+    message: impl Fn(usize) -> String,
+) {
+    let mut rows_num = 0;
+    for row in rows {
+        if rows_num < MAX_ROWS_PER_CHECK {
+            findings.push(Finding {
+                severity,
+                kind,
+                file,
+                row: Some(row),
+                column: Some(column),
+                message: message(row),
+            });
+        }
+        rows_num += 1;
+    }
+    if rows_num > MAX_ROWS_PER_CHECK {
+        findings.push(Finding {
+            severity,
+            kind: FindingKind::MoreRows,
+            file,
+            row: None,
+            column: Some(column),
+            message: format!(
+                "{} more rows with the same problem",
+                rows_num - MAX_ROWS_PER_CHECK
+            ),
+        });
+    }
+}
@@ -1,14 +1,21 @@
 use std::path::Path;
 
-use gtfs_io::*;
+use gtfs_io::{
+    validate::{validate_gtfs, FindingKind, Severity},
+    *,
+};
 
-#[test]
-fn test_load_gtfs_dummy() {
+fn load_gtfs_dummy_buffers(filter: &GtfsFilter) -> GtfsBuffers {
     let path = Path::new(env!("CARGO_MANIFEST_DIR"))
         .join("tests")
         .join("testdata")
         .join("gtfs_dummy");
-    let buffers = GtfsBuffers::from_dir(&path, &GtfsFilter::all());
+    GtfsBuffers::from_dir(&path, filter)
+}
+
+#[test]
+fn test_load_gtfs_dummy() {
+    let buffers = load_gtfs_dummy_buffers(&GtfsFilter::all());
     let gtfs = Gtfs::from_buffers(buffers.to_slices()).unwrap();
     assert_eq!(gtfs.stops.len, 2);
 
@@ -42,3 +49,864 @@ fn test_load_gtfs_dummy() {
         vec![LocationType::Station, LocationType::Station]
     );
 }
+
+#[test]
+fn test_load_gtfs_dummy_shapes() {
+    let buffers = load_gtfs_dummy_buffers(&GtfsFilter::all());
+    let gtfs = Gtfs::from_buffers(buffers.to_slices()).unwrap();
+    assert_eq!(gtfs.shapes.len, 5);
+
+    let polylines = gtfs.shapes.data.unwrap().polylines();
+    assert_eq!(polylines.len(), 2);
+    assert_eq!(polylines[0].shape_id, "A");
+    assert_eq!(
+        polylines[0]
+            .points
+            .iter()
+            .map(|p| (p.latitude as i32, p.longitude as i32, p.dist_traveled))
+            .collect::<Vec<_>>(),
+        vec![
+            (41, 23, Some(0.0)),
+            (42, 24, Some(1.5)),
+            (43, 25, Some(3.0))
+        ]
+    );
+    assert_eq!(polylines[1].shape_id, "B");
+    assert_eq!(
+        polylines[1]
+            .points
+            .iter()
+            .map(|p| (p.latitude as i32, p.longitude as i32, p.dist_traveled))
+            .collect::<Vec<_>>(),
+        vec![(10, 11, None), (11, 12, None)]
+    );
+}
+
+#[test]
+fn test_load_gtfs_dummy_frequencies() {
+    let buffers = load_gtfs_dummy_buffers(&GtfsFilter::all());
+    let gtfs = Gtfs::from_buffers(buffers.to_slices()).unwrap();
+    assert_eq!(gtfs.frequencies.len, 1);
+    assert_eq!(
+        gtfs.frequencies.data.as_ref().unwrap().exact_times,
+        Some(vec![ExactTimes::ScheduleBased])
+    );
+
+    let expanded_trips = gtfs.expand_frequencies();
+    assert_eq!(expanded_trips.len(), 3);
+    assert_eq!(
+        expanded_trips
+            .iter()
+            .map(|t| t.start_time.seconds())
+            .collect::<Vec<_>>(),
+        vec![8 * 3600, 8 * 3600 + 1200, 8 * 3600 + 2400]
+    );
+    let second_trip = &expanded_trips[1];
+    assert_eq!(second_trip.trip_id, "T1");
+    assert_eq!(
+        second_trip
+            .stop_times
+            .iter()
+            .map(|s| (
+                s.stop_time_i,
+                s.arrival_time.unwrap().seconds(),
+                s.departure_time.unwrap().seconds()
+            ))
+            .collect::<Vec<_>>(),
+        vec![
+            (1, 8 * 3600 + 1200, 8 * 3600 + 1200),
+            (0, 8 * 3600 + 1500, 8 * 3600 + 1560)
+        ]
+    );
+}
+
+#[test]
+fn test_load_gtfs_dummy_station_files() {
+    let buffers = load_gtfs_dummy_buffers(&GtfsFilter::all());
+    let gtfs = Gtfs::from_buffers(buffers.to_slices()).unwrap();
+
+    let transfers = gtfs.transfers.data.unwrap();
+    assert_eq!(
+        transfers.transfer_type.unwrap(),
+        vec![TransferType::MinimumTime, TransferType::Recommended]
+    );
+    assert_eq!(
+        transfers
+            .min_transfer_time
+            .unwrap()
+            .iter()
+            .map(|v| v.0)
+            .collect::<Vec<_>>(),
+        vec![Some(180), None]
+    );
+
+    let pathways = gtfs.pathways.data.unwrap();
+    assert_eq!(
+        pathways.pathway_mode.unwrap(),
+        vec![PathwayMode::Stairs, PathwayMode::Elevator]
+    );
+    assert_eq!(
+        pathways.is_bidirectional.unwrap(),
+        vec![
+            IsBidirectional::Bidirectional,
+            IsBidirectional::Unidirectional
+        ]
+    );
+    assert_eq!(
+        pathways
+            .stair_count
+            .unwrap()
+            .iter()
+            .map(|v| v.0)
+            .collect::<Vec<_>>(),
+        vec![Some(-12), None]
+    );
+
+    let levels = gtfs.levels.data.unwrap();
+    assert_eq!(levels.level_id.unwrap(), vec!["L0", "L1"]);
+    assert_eq!(
+        levels
+            .level_index
+            .unwrap()
+            .iter()
+            .map(|v| v.0)
+            .collect::<Vec<_>>(),
+        vec![Some(0.0), Some(-1.5)]
+    );
+}
+
+#[test]
+fn test_load_gtfs_dummy_fares() {
+    let buffers = load_gtfs_dummy_buffers(&GtfsFilter::all());
+    let gtfs = Gtfs::from_buffers(buffers.to_slices()).unwrap();
+    assert_eq!(gtfs.fare_rules.len, 1);
+    assert_eq!(gtfs.fare_leg_rules.len, 1);
+    assert_eq!(gtfs.areas.len, 1);
+    assert_eq!(gtfs.stop_areas.len, 2);
+    assert_eq!(gtfs.timeframes.len, 1);
+
+    let fare_attributes = gtfs.fare_attributes.data.unwrap();
+    assert_eq!(
+        fare_attributes
+            .price
+            .unwrap()
+            .iter()
+            .map(|v| v.0)
+            .collect::<Vec<_>>(),
+        vec![Some(2.5), Some(1.2)]
+    );
+    assert_eq!(
+        fare_attributes.payment_method.unwrap(),
+        vec![PaymentMethod::OnBoard, PaymentMethod::BeforeBoarding]
+    );
+    assert_eq!(
+        fare_attributes.transfers.unwrap(),
+        vec![FareTransfers::Unlimited, FareTransfers::NotAllowed]
+    );
+
+    let fare_media = gtfs.fare_media.data.unwrap();
+    assert_eq!(
+        fare_media.fare_media_type.unwrap(),
+        vec![FareMediaType::PaperTicket, FareMediaType::MobileApp]
+    );
+
+    let fare_products = gtfs.fare_products.data.unwrap();
+    assert_eq!(
+        fare_products
+            .amount
+            .unwrap()
+            .iter()
+            .map(|v| v.0)
+            .collect::<Vec<_>>(),
+        vec![Some(2.5), Some(-0.5)]
+    );
+
+    let fare_transfer_rules = gtfs.fare_transfer_rules.data.unwrap();
+    assert_eq!(fare_transfer_rules.transfer_count.unwrap()[0].0, Some(-1));
+    assert_eq!(
+        fare_transfer_rules.duration_limit_type.unwrap(),
+        vec![DurationLimitType::DepartureToDeparture]
+    );
+    assert_eq!(
+        fare_transfer_rules.fare_transfer_type.unwrap(),
+        vec![FareTransferType::FromLegPlusTransfer]
+    );
+
+    let rider_categories = gtfs.rider_categories.data.unwrap();
+    assert_eq!(
+        rider_categories.is_default_fare_category.unwrap(),
+        vec![YesOrNo::Yes, YesOrNo::No]
+    );
+}
+
+#[test]
+fn test_load_gtfs_dummy_translations() {
+    let buffers = load_gtfs_dummy_buffers(&GtfsFilter::all());
+    let gtfs = Gtfs::from_buffers(buffers.to_slices()).unwrap();
+    assert_eq!(gtfs.translations.len, 3);
+
+    let lookup = gtfs.translation_lookup();
+    assert_eq!(
+        lookup.translate("stops", "stop_name", "1", "My Station", "de"),
+        Some("Mein Bahnhof")
+    );
+    assert_eq!(
+        lookup.translate("stops", "stop_name", "1", "My Station", "de-CH"),
+        Some("Mein Bahnhof")
+    );
+    assert_eq!(
+        lookup.translate("stops", "stop_name", "1", "My Station", "fr"),
+        None
+    );
+    assert_eq!(
+        lookup.translate("stops", "stop_name", "2", "Another Station", "fr"),
+        Some("Une Autre Gare")
+    );
+    assert_eq!(
+        lookup.translate_record("feed_info", "feed_publisher_name", "", None, "de"),
+        Some("Herausgeber")
+    );
+}
+
+#[test]
+fn test_load_gtfs_dummy_spec_file_names() {
+    let buffers = load_gtfs_dummy_buffers(&GtfsFilter::all());
+    let gtfs = Gtfs::from_buffers(buffers.to_slices()).unwrap();
+    assert_eq!(gtfs.agencies.len, 1);
+    assert_eq!(
+        gtfs.agencies.data.unwrap().agency_name.unwrap(),
+        vec!["My Agency"]
+    );
+    assert_eq!(gtfs.feed_infos.len, 1);
+    assert_eq!(
+        gtfs.feed_infos.data.unwrap().default_lang.unwrap(),
+        vec!["en"]
+    );
+}
+
+#[test]
+fn test_gtfs_file_table() {
+    assert_eq!(GtfsFile::Agencies.file_name(), "agency.txt");
+    assert_eq!(GtfsFile::FeedInfos.file_name(), "feed_info.txt");
+    assert_eq!(GtfsFile::Calendar.file_name(), "calendar.txt");
+    assert_eq!(GtfsFile::StopTimes.presence(), GtfsFilePresence::Required);
+
+    let filter = GtfsFilter {
+        stops: true,
+        ..GtfsFilter::none()
+    };
+    for file in GtfsFile::ALL {
+        assert_eq!(filter.contains(*file), *file == GtfsFile::Stops);
+    }
+}
+
+#[test]
+fn test_intern_ids() {
+    let buffers = load_gtfs_dummy_buffers(&GtfsFilter::all());
+    let gtfs = Gtfs::from_buffers(buffers.to_slices()).unwrap();
+    let ids = gtfs.intern_ids();
+
+    assert_eq!(ids.stops.len(), 2);
+    assert_eq!(ids.stops.get("2"), Some(StopIdx::new(1)));
+    assert_eq!(ids.stops.id(StopIdx::new(0)), "1");
+    assert_eq!(ids.stops.get("unknown"), None);
+    assert_eq!(ids.services.len(), 2);
+    assert_eq!(ids.services.get("S2"), Some(ServiceIdx::new(1)));
+
+    assert_eq!(
+        ids.stop_times_stop_id.unwrap(),
+        vec![Some(StopIdx::new(1)), Some(StopIdx::new(0))]
+    );
+    assert_eq!(
+        ids.stop_times_trip_id.unwrap(),
+        vec![Some(TripIdx::new(0)), Some(TripIdx::new(0))]
+    );
+    assert_eq!(ids.trips_route_id.unwrap(), vec![Some(RouteIdx::new(0))]);
+    assert_eq!(
+        ids.trips_service_id.unwrap(),
+        vec![Some(ServiceIdx::new(0))]
+    );
+    assert!(ids.stops_parent_station.is_none());
+    assert_eq!(std::mem::size_of::<Option<StopIdx>>(), 4);
+}
+
+#[test]
+fn test_validate_dummy() {
+    let buffers = load_gtfs_dummy_buffers(&GtfsFilter::all());
+    let gtfs = Gtfs::from_buffers(buffers.to_slices()).unwrap();
+    let findings = validate_gtfs(&gtfs);
+    assert!(findings.is_empty(), "{:#?}", findings);
+}
+
+#[test]
+fn test_validate_broken() {
+    let buffers = GtfsBuffers::from_fn(|file| {
+        let content: &[u8] = match file {
+            GtfsFile::Agencies => b"agency_id,agency_name,agency_url,agency_timezone\nA,Agency,https://example.com,Europe/Berlin\nB,Other,https://example.com,Europe/Berlin\n",
+            GtfsFile::Stops => b"stop_id,stop_name,parent_station\n1,First,\n2,Second,9\n2,Duplicate,\n",
+            GtfsFile::Routes => b"route_id,agency_id,route_type\nR1,A,3\nR2,,3\n",
+            GtfsFile::Trips => b"route_id,service_id,trip_id\nR1,S1,T1\nR3,S9,T2\n",
+            GtfsFile::StopTimes => b"trip_id,stop_id,stop_sequence,arrival_time,departure_time\nT1,1,1,08:00:00,08:01:00\nT1,2,2,08:05:00,08:04:00\nT1,1,3,08:03:00,08:03:00\nT1,2,3,08:10:00,08:10:00\nT9,3,1,08:00:00,08:00:00\n",
+            GtfsFile::Calendar => b"service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date\nS1,1,1,1,1,1,0,0,20250101,20251231\n",
+            _ => return None,
+        };
+        Some(content.to_vec())
+    });
+    let gtfs = Gtfs::from_buffers(buffers.to_slices()).unwrap();
+    let findings = validate_gtfs(&gtfs);
+    let summary: Vec<_> = findings
+        .iter()
+        .map(|f| (f.kind, f.file, f.row, f.column))
+        .collect();
+    assert!(findings.iter().all(|f| f.severity == Severity::Error));
+    assert_eq!(
+        summary,
+        vec![
+            (
+                FindingKind::DuplicateKey,
+                GtfsFile::Stops,
+                Some(2),
+                Some("stop_id")
+            ),
+            (
+                FindingKind::ForeignKeyViolation,
+                GtfsFile::StopTimes,
+                Some(4),
+                Some("trip_id")
+            ),
+            (
+                FindingKind::ForeignKeyViolation,
+                GtfsFile::StopTimes,
+                Some(4),
+                Some("stop_id")
+            ),
+            (
+                FindingKind::ForeignKeyViolation,
+                GtfsFile::Trips,
+                Some(1),
+                Some("route_id")
+            ),
+            (
+                FindingKind::ForeignKeyViolation,
+                GtfsFile::Trips,
+                Some(1),
+                Some("service_id")
+            ),
+            (
+                FindingKind::ForeignKeyViolation,
+                GtfsFile::Routes,
+                Some(1),
+                Some("agency_id")
+            ),
+            (
+                FindingKind::ForeignKeyViolation,
+                GtfsFile::Stops,
+                Some(1),
+                Some("parent_station")
+            ),
+            (
+                FindingKind::DuplicateStopSequence,
+                GtfsFile::StopTimes,
+                Some(3),
+                Some("stop_sequence")
+            ),
+            (
+                FindingKind::ArrivalAfterDeparture,
+                GtfsFile::StopTimes,
+                Some(1),
+                Some("arrival_time")
+            ),
+            (
+                FindingKind::DecreasingStopTime,
+                GtfsFile::StopTimes,
+                Some(2),
+                Some("arrival_time")
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_validate_warnings() {
+    let buffers = GtfsBuffers::from_fn(|file| {
+        let content: &[u8] = match file {
+            GtfsFile::Agencies => b"agency_id,agency_name,agency_url,agency_timezone\nA,Agency,https://example.com,Europe/Berlin\nB,Other,https://example.com,Europe/Berlin\n",
+            GtfsFile::Stops => b"stop_id,stop_name,location_type,parent_station\nS,Station,1,\n1,First,0,S\n2,Second,,\n3,Unused,0,S\n",
+            GtfsFile::Routes => b"route_id,route_type\nR1,3\n",
+            GtfsFile::Trips => b"route_id,service_id,trip_id\nR1,S1,T1\n",
+            GtfsFile::StopTimes => b"trip_id,stop_id,stop_sequence,arrival_time,departure_time\nT1,1,1,08:00:00,08:00:00\nT1,2,2,08:05:00,08:05:00\n",
+            GtfsFile::Calendar => b"service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date\nS1,1,1,1,1,1,0,0,20250101,20251231\n",
+            _ => return None,
+        };
+        Some(content.to_vec())
+    });
+    let gtfs = Gtfs::from_buffers(buffers.to_slices()).unwrap();
+    let findings = validate_gtfs(&gtfs);
+    let summary: Vec<_> = findings
+        .iter()
+        .map(|f| (f.severity, f.kind, f.file, f.row, f.column))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (
+                Severity::Warning,
+                FindingKind::MissingConditionallyRequiredColumn,
+                GtfsFile::Routes,
+                None,
+                Some("agency_id")
+            ),
+            (
+                Severity::Warning,
+                FindingKind::UnusedStop,
+                GtfsFile::Stops,
+                Some(3),
+                Some("stop_id")
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_parse_errors_keep_file() {
+    let buffers = GtfsBuffers::from_fn(|file| {
+        match file {
+        GtfsFile::Calendar => Some(b"service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date\nS1,1,1,1,1,1,0,0,20250101,20251231\nS2,1,1,1,1,1,0,0,2025011,20251231\n".to_vec()),
+        _ => None,
+    }
+    });
+    let gtfs = Gtfs::from_buffers(buffers.to_slices()).unwrap();
+    assert_eq!(gtfs.calendars.len, 2);
+    let calendars = gtfs.calendars.data.as_ref().unwrap();
+    assert_eq!(calendars.service_id.as_ref().unwrap(), &vec!["S1", "S2"]);
+    assert_eq!(calendars.start_date.as_ref().unwrap()[1], Date::default());
+
+    let errors = gtfs.calendars.parse_errors.column("start_date").unwrap();
+    assert_eq!(errors.errors_num, 1);
+    assert_eq!(errors.samples[0].error.record_index, Some(1));
+    assert_eq!(
+        errors.samples[0].error.kind,
+        csvelo::ErrorKind::InvalidValue
+    );
+    assert_eq!(errors.samples[0].raw, b"2025011");
+    assert!(gtfs.calendars.parse_errors.column("end_date").is_none());
+
+    let findings = validate_gtfs(&gtfs);
+    assert!(findings.iter().any(|f| f.kind == FindingKind::InvalidValue
+        && f.file == GtfsFile::Calendar
+        && f.row == Some(1)
+        && f.column == Some("start_date")));
+}
+
+#[test]
+fn test_empty_optional_fields() {
+    let buffers = GtfsBuffers::from_fn(|file| {
+        match file {
+        GtfsFile::Routes => Some(
+            b"route_id,agency_id,route_short_name,route_type,route_color,route_text_color,route_sort_order\nR1,A,1,700,,,\nR2,A,2,3,00FF00,FFF,5\n"
+                .to_vec(),
+        ),
+        GtfsFile::Transfers => Some(
+            b"from_stop_id,to_stop_id,transfer_type,min_transfer_time\n1,2,2, \n1,2,2,abc\n2,1,2,60\n"
+                .to_vec(),
+        ),
+        _ => None,
+    }
+    });
+    let gtfs = Gtfs::from_buffers(buffers.to_slices()).unwrap();
+    let routes = gtfs.routes.data.as_ref().unwrap();
+    assert_eq!(
+        routes.route_color.as_ref().unwrap(),
+        &vec![
+            OptionalColor(None),
+            OptionalColor(Some(Color { r: 0, g: 255, b: 0 }))
+        ]
+    );
+    assert_eq!(
+        routes.route_text_color.as_ref().unwrap()[0],
+        OptionalColor(None)
+    );
+    assert_eq!(routes.route_sort_order.as_ref().unwrap()[0].0, None);
+    assert_eq!(routes.route_sort_order.as_ref().unwrap()[1].0, Some(5));
+
+    // Only the malformed color is an error, the empty fields are not.
+    assert_eq!(gtfs.routes.parse_errors.errors_num(), 1);
+    let errors = gtfs.routes.parse_errors.column("route_text_color").unwrap();
+    assert_eq!(errors.samples[0].error.record_index, Some(1));
+
+    let mut output = vec![];
+    routes.write_csv(&mut output).unwrap();
+    assert!(std::str::from_utf8(&output)
+        .unwrap()
+        .contains("\nR1,A,1,700,,,\n"));
+
+    // Malformed numbers are errors, too, instead of silently becoming absent.
+    let transfers = gtfs.transfers.data.as_ref().unwrap();
+    let min_transfer_times: Vec<_> = transfers
+        .min_transfer_time
+        .as_ref()
+        .unwrap()
+        .iter()
+        .map(|time| time.0)
+        .collect();
+    assert_eq!(min_transfer_times, [None, None, Some(60)]);
+    assert_eq!(gtfs.transfers.parse_errors.errors_num(), 1);
+    let errors = gtfs
+        .transfers
+        .parse_errors
+        .column("min_transfer_time")
+        .unwrap();
+    assert_eq!(errors.samples[0].error.record_index, Some(1));
+}
+
+#[test]
+fn test_load_with_dialect() {
+    let buffers = GtfsBuffers::from_fn(|file| match file {
+        GtfsFile::Stops => Some(b"stop_id;stop_name\n1;M\xFCnchen\n2\xFC;B\n".to_vec()),
+        _ => None,
+    });
+    let dialect = csvelo::CsvDialect {
+        delimiter: b';',
+        encoding: csvelo::CsvEncoding::Latin1,
+        ..csvelo::CsvDialect::default()
+    };
+    let gtfs = Gtfs::from_buffers_with_dialect(buffers.to_slices(), &dialect).unwrap();
+    // Ids reference the buffer and can't be transcoded.
+    let errors = gtfs.stops.parse_errors.column("stop_id").unwrap();
+    assert_eq!(errors.samples[0].error.record_index, Some(1));
+    let stops = gtfs.stops.data.unwrap();
+    assert_eq!(stops.stop_id.unwrap(), vec!["1", ""]);
+    assert_eq!(stops.stop_name.unwrap(), vec!["München", "B"]);
+}
+
+#[test]
+fn test_load_with_bom() {
+    let buffers = GtfsBuffers::from_fn(|file| match file {
+        GtfsFile::Stops => Some(b"\xEF\xBB\xBFstop_id,stop_name\n1,A\n".to_vec()),
+        _ => None,
+    });
+    let gtfs = Gtfs::from_buffers(buffers.to_slices()).unwrap();
+    assert_eq!(gtfs.stops.data.unwrap().stop_id.unwrap(), vec!["1"]);
+}
+
+#[test]
+fn test_load_escaped_quotes() {
+    let buffers = GtfsBuffers::from_fn(|file| match file {
+        GtfsFile::Stops => Some(
+            b"stop_id,stop_name\n1,\"The \"\"Big\"\" Station\"\n2,Plain\n\"3\"\"\",Quoted\n"
+                .to_vec(),
+        ),
+        _ => None,
+    });
+    let gtfs = Gtfs::from_buffers(buffers.to_slices()).unwrap();
+
+    // Ids are borrowed from the buffer, so they can't be unescaped.
+    let errors = gtfs.stops.parse_errors.column("stop_id").unwrap();
+    assert_eq!(
+        errors.samples[0].error.kind,
+        csvelo::ErrorKind::EscapedQuote
+    );
+    assert_eq!(errors.samples[0].error.record_index, Some(2));
+
+    let stops = gtfs.stops.data.unwrap();
+    assert_eq!(stops.stop_id.unwrap(), vec!["1", "2", ""]);
+    assert_eq!(
+        stops.stop_name.unwrap(),
+        vec!["The \"Big\" Station", "Plain", "Quoted"]
+    );
+}
+
+#[test]
+fn test_write_quotes_and_route_types() {
+    let buffers = GtfsBuffers::from_fn(|file| match file {
+        GtfsFile::Routes => Some(b"route_id,route_type\nR1,700\nR2,3\nR3,bus\n".to_vec()),
+        _ => None,
+    });
+    let gtfs = Gtfs::from_buffers(buffers.to_slices()).unwrap();
+    let routes = gtfs.routes.data.as_ref().unwrap();
+    assert_eq!(
+        routes.route_type.as_ref().unwrap(),
+        &vec![RouteType::Other(700), RouteType::Bus, RouteType::Unknown]
+    );
+
+    let mut output = vec![];
+    routes.write_csv(&mut output).unwrap();
+    assert_eq!(
+        std::str::from_utf8(&output).unwrap(),
+        "route_id,route_type\nR1,700\nR2,3\nR3,\n"
+    );
+
+    // Ids are written as they are, quotes included.
+    let routes = Routes {
+        route_id: Some(vec!["a\"\"b"]),
+        ..Routes::default()
+    };
+    let mut output = vec![];
+    routes.write_csv(&mut output).unwrap();
+    assert_eq!(
+        std::str::from_utf8(&output).unwrap(),
+        "route_id\n\"a\"\"\"\"b\"\n"
+    );
+}
+
+#[test]
+fn test_write_files() {
+    let buffers = load_gtfs_dummy_buffers(&GtfsFilter::all());
+    let gtfs = Gtfs::from_buffers(buffers.to_slices()).unwrap();
+
+    let mut output = vec![];
+    let records_num = gtfs
+        .routes
+        .data
+        .as_ref()
+        .unwrap()
+        .write_csv(&mut output)
+        .unwrap();
+    assert_eq!(records_num, 1);
+    assert_eq!(
+        std::str::from_utf8(&output).unwrap(),
+        "route_id,agency_id,route_short_name,route_long_name,route_type,route_color,route_text_color\nR1,A,1,Line 1,3,FF0000,FFFFFF\n"
+    );
+
+    let mut output = vec![];
+    gtfs.stop_times
+        .data
+        .as_ref()
+        .unwrap()
+        .write_csv(&mut output)
+        .unwrap();
+    // Columns are written in the order of the struct fields.
+    assert_eq!(
+        std::str::from_utf8(&output).unwrap(),
+        "trip_id,stop_id,stop_sequence,arrival_time,departure_time\nT1,2,2,00:05:00,00:06:00\nT1,1,1,00:00:00,00:00:00\n"
+    );
+
+    let mut output = vec![];
+    gtfs.calendars
+        .data
+        .as_ref()
+        .unwrap()
+        .write_csv(&mut output)
+        .unwrap();
+    assert_eq!(
+        std::str::from_utf8(&output).unwrap(),
+        "service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date\nS1,1,1,1,1,1,0,0,20250101,20251231\n"
+    );
+
+    let stops = Stops {
+        stop_id: Some(vec!["1", "2"]),
+        stop_name: Some(vec!["The \"Big\" One".into(), "A, B".into()]),
+        ..Stops::default()
+    };
+    let mut output = vec![];
+    stops.write_csv(&mut output).unwrap();
+    assert_eq!(
+        std::str::from_utf8(&output).unwrap(),
+        "stop_id,stop_name\n1,\"The \"\"Big\"\" One\"\n2,\"A, B\"\n"
+    );
+    let (parsed, _) = Stops::from_csv_buffer(&output).unwrap();
+    assert_eq!(parsed.stop_name, stops.stop_name);
+
+    let mut output = vec![];
+    assert_eq!(Stops::default().write_csv(&mut output).unwrap(), 0);
+    assert!(output.is_empty());
+}
+
+fn assert_same_written_files(a: &Gtfs, b: &Gtfs) {
+    assert_eq!(a.file_lens(), b.file_lens());
+    for &file in GtfsFile::ALL {
+        let mut output_a = vec![];
+        let mut output_b = vec![];
+        a.write_file_csv(file, &mut output_a).unwrap();
+        b.write_file_csv(file, &mut output_b).unwrap();
+        assert_eq!(output_a, output_b, "{}", file.file_name());
+    }
+}
+
+#[test]
+fn test_write_to_dir_round_trip() {
+    let buffers = load_gtfs_dummy_buffers(&GtfsFilter::all());
+    let gtfs = Gtfs::from_buffers(buffers.to_slices()).unwrap();
+
+    let output_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("gtfs_dummy_round_trip");
+    let _ = std::fs::remove_dir_all(&output_dir);
+    gtfs.write_to_dir(&output_dir).unwrap();
+
+    for &file in GtfsFile::ALL {
+        assert_eq!(
+            output_dir.join(file.file_name()).exists(),
+            buffers.to_slices().get(file).is_some(),
+            "{}",
+            file.file_name()
+        );
+    }
+
+    let written_buffers = GtfsBuffers::from_dir(&output_dir, &GtfsFilter::all());
+    let written_gtfs = Gtfs::from_buffers(written_buffers.to_slices()).unwrap();
+    assert_same_written_files(&gtfs, &written_gtfs);
+    assert_eq!(
+        written_gtfs.stops.data.unwrap().stop_name.unwrap(),
+        vec!["My Station", "Another Station"]
+    );
+}
+
+#[test]
+fn test_write_to_zip_round_trip() {
+    let filter = GtfsFilter {
+        shapes: false,
+        ..GtfsFilter::all()
+    };
+    let buffers = load_gtfs_dummy_buffers(&filter);
+    let gtfs = Gtfs::from_buffers(buffers.to_slices()).unwrap();
+
+    let mut archive = zip::ZipWriter::new(std::io::Cursor::new(vec![]));
+    gtfs.write_to_zip_archive(&mut archive).unwrap();
+    let zip_buffer = archive.finish().unwrap().into_inner();
+
+    let written_buffers =
+        GtfsBuffers::from_zip_file_buffer(&zip_buffer, &GtfsFilter::all()).unwrap();
+    assert!(written_buffers.shapes.is_none());
+    assert!(written_buffers.stop_times.is_some());
+    let written_gtfs = Gtfs::from_buffers(written_buffers.to_slices()).unwrap();
+    assert_same_written_files(&gtfs, &written_gtfs);
+
+    let zip_path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("gtfs_dummy_round_trip.zip");
+    gtfs.write_to_path(&zip_path).unwrap();
+    let written_buffers = GtfsBuffers::from_path(&zip_path, &GtfsFilter::all()).unwrap();
+    let written_gtfs = Gtfs::from_buffers(written_buffers.to_slices()).unwrap();
+    assert_same_written_files(&gtfs, &written_gtfs);
+}
+
+#[test]
+fn test_service_calendar() {
+    let buffers = load_gtfs_dummy_buffers(&GtfsFilter::all());
+    let gtfs = Gtfs::from_buffers(buffers.to_slices()).unwrap();
+    let calendar = gtfs.service_calendar();
+
+    let date = |year, month, day| Date { year, month, day };
+    assert_eq!(calendar.first_date(), Some(date(2025, 1, 1)));
+    assert_eq!(calendar.last_date(), Some(date(2025, 12, 31)));
+    assert_eq!(calendar.days_num(), 365);
+    assert_eq!(calendar.service_ids(), &["S1", "S2"]);
+
+    // 2025-01-01 is a Wednesday.
+    assert!(calendar.is_active("S1", &date(2025, 1, 1)));
+    assert!(!calendar.is_active("S1", &date(2025, 1, 4)));
+    // Added by calendar_dates.txt on a Sunday.
+    assert!(calendar.is_active("S1", &date(2025, 1, 5)));
+    assert!(!calendar.is_active("S1", &date(2025, 1, 12)));
+    assert!(!calendar.is_active("S1", &date(2026, 1, 1)));
+    assert!(!calendar.is_active("S3", &date(2025, 1, 1)));
+
+    assert_eq!(
+        calendar.active_services(&date(2025, 1, 6)),
+        ["S1", "S2"].into_iter().collect()
+    );
+    assert_eq!(
+        calendar.active_services(&date(2025, 1, 7)),
+        ["S1"].into_iter().collect()
+    );
+    assert!(calendar.active_services(&date(2024, 12, 31)).is_empty());
+    assert_eq!(
+        calendar.active_services_between(&date(2024, 1, 1), &date(2025, 1, 6)),
+        ["S1", "S2"].into_iter().collect()
+    );
+    assert!(calendar
+        .active_services_between(&date(2025, 1, 7), &date(2025, 1, 6))
+        .is_empty());
+
+    // 261 weekdays in 2025 and one additional Sunday.
+    assert_eq!(calendar.service_days("S1").unwrap().active_days_num(), 262);
+    assert_eq!(calendar.service_days("S2").unwrap().active_days_num(), 1);
+    assert!(calendar.service_days("S2").unwrap().get(5));
+}
+
+#[test]
+fn test_service_calendar_removed_dates() {
+    let calendars = Calendar {
+        service_id: Some(vec!["S"]),
+        saturday: Some(vec![ServiceAvailable::Yes]),
+        start_date: Some(vec![Date {
+            year: 2024,
+            month: 2,
+            day: 24,
+        }]),
+        end_date: Some(vec![Date {
+            year: 2024,
+            month: 3,
+            day: 9,
+        }]),
+        ..Calendar::default()
+    };
+    let calendar_dates = CalendarDates {
+        service_id: Some(vec!["S"]),
+        date: Some(vec![Date {
+            year: 2024,
+            month: 3,
+            day: 2,
+        }]),
+        exception_type: Some(vec![ExceptionType::Removed]),
+    };
+    let calendar = ServiceCalendar::new(Some(&calendars), Some(&calendar_dates));
+    let days = calendar.service_days("S").unwrap();
+    assert_eq!(days.active_days_num(), 2);
+    // Saturdays around the leap day.
+    assert!(days.get(0));
+    assert!(!days.get(7));
+    assert!(days.get(14));
+}
+
+#[test]
+fn test_parse_date() {
+    use csvelo::ParseCsvField;
+
+    assert_eq!(
+        Date::parse_csv_field(b"20240229").unwrap(),
+        Date::new(2024, 2, 29).unwrap()
+    );
+    assert_eq!(
+        Date::parse_csv_field(b" 20250101 ").unwrap(),
+        Date::new(2025, 1, 1).unwrap()
+    );
+    assert!(Date::parse_csv_field(b"2025a101").is_err());
+    assert!(Date::parse_csv_field(b"20251301").is_err());
+    assert!(Date::parse_csv_field(b"20250229").is_err());
+    assert!(Date::parse_csv_field(b"2025010").is_err());
+    assert!(Date::parse_csv_field(b"").is_err());
+}
+
+#[test]
+fn test_date_arithmetic() {
+    let date = Date::new(2025, 1, 1).unwrap();
+    assert_eq!(date.days_since_epoch(), 20089);
+    assert_eq!(
+        Date::from_days_since_epoch(0),
+        Date::new(1970, 1, 1).unwrap()
+    );
+    assert_eq!(Date::from_days_since_epoch(20089), date);
+    assert_eq!(date.weekday(), Weekday::Wednesday);
+    assert_eq!(date.add_days(-1), Date::new(2024, 12, 31).unwrap());
+    assert_eq!(date.add_days(59), Date::new(2025, 3, 1).unwrap());
+    assert_eq!(
+        Date::new(2024, 2, 28).unwrap().add_days(1),
+        Date::new(2024, 2, 29).unwrap()
+    );
+    assert_eq!(date.days_until(&Date::new(2026, 1, 1).unwrap()), 365);
+    assert!(Date::new(2024, 12, 31).unwrap() < date);
+    assert!(Date::new(2025, 1, 2).unwrap() > date);
+
+    let dates: Vec<Date> = Date::range_inclusive(
+        Date::new(2024, 2, 28).unwrap(),
+        Date::new(2024, 3, 1).unwrap(),
+    )
+    .collect();
+    assert_eq!(
+        dates,
+        vec![
+            Date::new(2024, 2, 28).unwrap(),
+            Date::new(2024, 2, 29).unwrap(),
+            Date::new(2024, 3, 1).unwrap(),
+        ]
+    );
+    assert_eq!(Date::range_inclusive(date, date.add_days(-1)).count(), 0);
+}
@@ -3,18 +3,26 @@ use std::path::Path;
 use anyhow::Result;
 use clap::{Parser, Subcommand};
 
+use crate::cli_gtfs_extract;
 use crate::cli_gtfs_merge;
+use crate::cli_gtfs_plan;
 use crate::cli_gtfs_stats;
+use crate::cli_gtfs_validate;
 use crate::cli_serve;
 use crate::cli_serve_dev;
 use crate::gtfs_sources::get_gtfs_sources;
+use crate::routing::WalkingParams;
+use crate::util::parse_date;
 
 const DEFAULT_FRONTEND_HOST: &str = "localhost";
 const DEFAULT_FRONTEND_PORT: u16 = 7654;
 const DEFAULT_GTFS_DATASETS_PATH: &str = "gtfs_datasets";
+const DEFAULT_MAX_WALKING_DISTANCE: u32 = 400;
+const DEFAULT_WALKING_SPEED: f32 = 4.5;
 
 #[derive(Parser, Debug)]
 #[command(name = "trip-atlas")]
+#[allow(clippy::upper_case_acronyms)]
 struct CLI {
     #[command(subcommand)]
     command: Option<CLICommand>,
@@ -30,6 +38,12 @@ enum CLICommand {
         port: u16,
         #[arg(long, default_value_t = DEFAULT_GTFS_DATASETS_PATH.to_string())]
         gtfs_datasets: String,
+        /// Stops within this distance in meters are connected by footpaths.
+        #[arg(long, default_value_t = DEFAULT_MAX_WALKING_DISTANCE)]
+        max_walking_distance: u32,
+        /// Walking speed in km/h, used for footpaths and walks to and from stops.
+        #[arg(long, default_value_t = DEFAULT_WALKING_SPEED)]
+        walking_speed: f32,
     },
     /// Start a development server with live reloading for the frontend.
     Dev {
@@ -39,6 +53,12 @@ enum CLICommand {
         port: u16,
         #[arg(long, default_value_t = DEFAULT_GTFS_DATASETS_PATH.to_string())]
         gtfs_datasets: String,
+        /// Stops within this distance in meters are connected by footpaths.
+        #[arg(long, default_value_t = DEFAULT_MAX_WALKING_DISTANCE)]
+        max_walking_distance: u32,
+        /// Walking speed in km/h, used for footpaths and walks to and from stops.
+        #[arg(long, default_value_t = DEFAULT_WALKING_SPEED)]
+        walking_speed: f32,
     },
     /// Analyse one or more GTFS datasets.
     GtfsStats {
@@ -46,11 +66,72 @@ enum CLICommand {
         #[arg(long)]
         path: String,
     },
+    /// Validate one or more GTFS datasets. Exits with an error if any dataset is invalid.
+    GtfsValidate {
+        /// Path to GTFS dataset or directory containing GTFS datasets. A dataset can be a .zip file or a directory.
+        #[arg(long)]
+        path: String,
+        /// Print a JSON report for every dataset instead of a human readable summary.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Merge multiple GTFS datasets into one. Ids are prefixed per dataset and stops that are
+    /// close to each other and have similar names are deduplicated.
     GtfsMerge {
+        /// Directory containing the GTFS datasets to merge.
+        #[arg(long)]
+        input: String,
+        /// Output directory or .zip file for the merged GTFS dataset.
+        #[arg(long)]
+        output: String,
+    },
+    /// Write a reduced GTFS dataset that only contains the trips in an area and/or date range.
+    GtfsExtract {
+        /// Path to the GTFS dataset. It can be a .zip file or a directory.
         #[arg(long)]
         input: String,
+        /// Output directory or .zip file for the extracted GTFS dataset.
         #[arg(long)]
         output: String,
+        /// Only keep stops in this bounding box, given as min_lon,min_lat,max_lon,max_lat.
+        #[arg(long)]
+        bbox: Option<String>,
+        /// Only keep stops in the polygons of this GeoJSON file.
+        #[arg(long)]
+        polygon: Option<String>,
+        /// Only keep services that run on or after this date (YYYYMMDD).
+        #[arg(long)]
+        start_date: Option<String>,
+        /// Only keep services that run on or before this date (YYYYMMDD).
+        #[arg(long)]
+        end_date: Option<String>,
+    },
+    /// Find the earliest arriving journeys between two stops.
+    GtfsPlan {
+        /// Path to the GTFS dataset. It can be a .zip file or a directory.
+        #[arg(long)]
+        path: String,
+        /// Id of the stop or station to start at.
+        #[arg(long)]
+        from: String,
+        /// Id of the stop or station to travel to.
+        #[arg(long)]
+        to: String,
+        /// Day of the journey (YYYYMMDD).
+        #[arg(long)]
+        date: String,
+        /// Earliest departure (HH:MM or HH:MM:SS).
+        #[arg(long)]
+        time: String,
+        /// Maximum number of changes between trips.
+        #[arg(long, default_value_t = 4)]
+        max_transfers: usize,
+        /// Stops within this distance in meters are connected by footpaths.
+        #[arg(long, default_value_t = DEFAULT_MAX_WALKING_DISTANCE)]
+        max_walking_distance: u32,
+        /// Walking speed in km/h, used for footpaths and walks to and from stops.
+        #[arg(long, default_value_t = DEFAULT_WALKING_SPEED)]
+        walking_speed: f32,
     },
     /// Download GTFS datasets from the Mobility Database.
     GtfsDownloadMobilityDatabase {
@@ -67,6 +148,19 @@ enum CLICommand {
     },
 }
 
+fn walking_params(max_walking_distance: u32, walking_speed: f32) -> Result<WalkingParams> {
+    if !(walking_speed > 0.0 && walking_speed.is_finite()) {
+        return Err(anyhow::anyhow!(
+            "Expected a positive walking speed, got {}",
+            walking_speed
+        ));
+    }
+    Ok(WalkingParams {
+        max_distance_km: max_walking_distance as f32 / 1000.0,
+        speed_km_per_hour: walking_speed,
+    })
+}
+
 pub async fn handle_command_line_arguments() -> Result<()> {
     let cli = CLI::parse();
     match cli.command {
@@ -77,18 +171,19 @@ pub async fn handle_command_line_arguments() -> Result<()> {
                 host: DEFAULT_FRONTEND_HOST.to_string(),
                 port: DEFAULT_FRONTEND_PORT,
                 on_start: Some(Box::new(move || {
-                    if !webbrowser::open(url.as_str()).is_ok() {
+                    if webbrowser::open(url.as_str()).is_err() {
                         println!("Failed to open browser");
                     }
                 })),
                 on_port_in_use: Some(Box::new(move || {
                     println!("Server is already running on {}", url_clone);
-                    if !webbrowser::open(url_clone.as_str()).is_ok() {
+                    if webbrowser::open(url_clone.as_str()).is_err() {
                         println!("Failed to open browser");
                     }
                 })),
                 allow_shutdown_from_frontend: true,
                 gtfs_datasets: vec![],
+                walking_params: WalkingParams::default(),
             })
             .await?
         }
@@ -96,14 +191,17 @@ pub async fn handle_command_line_arguments() -> Result<()> {
             host,
             port,
             gtfs_datasets,
+            max_walking_distance,
+            walking_speed,
         }) => {
             cli_serve::serve(cli_serve::ServeParams {
-                host: host,
-                port: port,
+                host,
+                port,
                 on_start: None,
                 on_port_in_use: None,
                 allow_shutdown_from_frontend: false,
                 gtfs_datasets: get_gtfs_sources(Path::new(&gtfs_datasets), true),
+                walking_params: walking_params(max_walking_distance, walking_speed)?,
             })
             .await?
         }
@@ -111,6 +209,8 @@ pub async fn handle_command_line_arguments() -> Result<()> {
             host,
             port,
             gtfs_datasets,
+            max_walking_distance,
+            walking_speed,
         }) => {
             cli_serve_dev::serve_dev(&cli_serve_dev::ServeDevParams {
                 frontend_host: host.clone(),
@@ -118,6 +218,7 @@ pub async fn handle_command_line_arguments() -> Result<()> {
                 api_host: host,
                 api_port: None,
                 gtfs_datasets: get_gtfs_sources(Path::new(&gtfs_datasets), true),
+                walking_params: walking_params(max_walking_distance, walking_speed)?,
             })
             .await?
         }
@@ -128,7 +229,7 @@ pub async fn handle_command_line_arguments() -> Result<()> {
         }) => {
             crate::cli_mobility_database::download_mobility_database_gtfs(
                 &access_token,
-                &Path::new(&directory),
+                Path::new(&directory),
                 limit,
             )
             .await?;
@@ -138,9 +239,65 @@ pub async fn handle_command_line_arguments() -> Result<()> {
             cli_gtfs_stats::gtfs_stats(Path::new(&path), true).await?;
             println!("Analysis took {:?}", start.elapsed());
         }
+        Some(CLICommand::GtfsValidate { path, json }) => {
+            if !cli_gtfs_validate::gtfs_validate(Path::new(&path), true, json).await? {
+                return Err(anyhow::anyhow!("Some GTFS datasets are invalid"));
+            }
+        }
         Some(CLICommand::GtfsMerge { input, output }) => {
             cli_gtfs_merge::gtfs_merge(Path::new(&input), Path::new(&output)).await?;
         }
+        Some(CLICommand::GtfsExtract {
+            input,
+            output,
+            bbox,
+            polygon,
+            start_date,
+            end_date,
+        }) => {
+            let mut params = cli_gtfs_extract::ExtractParams::default();
+            if let Some(bbox) = bbox {
+                params
+                    .areas
+                    .push(cli_gtfs_extract::ExtractArea::from_bbox_str(&bbox)?);
+            }
+            if let Some(polygon) = polygon {
+                params
+                    .areas
+                    .push(cli_gtfs_extract::ExtractArea::from_geojson(
+                        &std::fs::read_to_string(polygon)?,
+                    )?);
+            }
+            params.start_date = start_date.as_deref().map(parse_date).transpose()?;
+            params.end_date = end_date.as_deref().map(parse_date).transpose()?;
+            cli_gtfs_extract::gtfs_extract(Path::new(&input), Path::new(&output), &params).await?;
+        }
+        Some(CLICommand::GtfsPlan {
+            path,
+            from,
+            to,
+            date,
+            time,
+            max_transfers,
+            max_walking_distance,
+            walking_speed,
+        }) => {
+            let departure = crate::routing::parse_time(&time).ok_or_else(|| {
+                anyhow::anyhow!("Expected time as HH:MM or HH:MM:SS, got {:?}", time)
+            })?;
+            cli_gtfs_plan::gtfs_plan(
+                Path::new(&path),
+                &cli_gtfs_plan::PlanParams {
+                    from_stop_id: from,
+                    to_stop_id: to,
+                    date: parse_date(&date)?,
+                    departure,
+                    max_transfers,
+                    walking: walking_params(max_walking_distance, walking_speed)?,
+                },
+            )
+            .await?;
+        }
     }
     Ok(())
 }
@@ -0,0 +1,664 @@
+use anyhow::{anyhow, Result};
+use gtfs_io::{Date, Gtfs, GtfsFile, GtfsFilter, LocationType};
+use rstar::AABB;
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+};
+
+use crate::{
+    coordinates::{LatLon, LatLonBounds},
+    gtfs_dataset::GtfsDataset,
+    routing::WalkingParams,
+};
+
+/// Files that are reduced to the extracted stops and trips. Other files are not part of the
+/// output.
+fn extracted_files_filter() -> GtfsFilter {
+    GtfsFilter {
+        agencies: true,
+        stops: true,
+        routes: true,
+        trips: true,
+        stop_times: true,
+        calendars: true,
+        calendar_dates: true,
+        shapes: true,
+        frequencies: true,
+        transfers: true,
+        levels: true,
+        feed_infos: true,
+        ..GtfsFilter::none()
+    }
+}
+
+/// Part of the world that extracted stops have to be in.
+#[derive(Debug)]
+pub enum ExtractArea {
+    Bounds(LatLonBounds),
+    /// Every polygon consists of an outer ring followed by optional holes.
+    Polygons(Vec<Vec<Vec<LatLon>>>),
+}
+
+#[derive(Debug, Default)]
+pub struct ExtractParams {
+    /// Stops have to be in all of these areas.
+    pub areas: Vec<ExtractArea>,
+    /// First day on which extracted services run.
+    pub start_date: Option<Date>,
+    /// Last day on which extracted services run.
+    pub end_date: Option<Date>,
+}
+
+pub async fn gtfs_extract(
+    input_path: &Path,
+    output_path: &Path,
+    params: &ExtractParams,
+) -> Result<()> {
+    println!("Loading GTFS from {:?}", input_path);
+    let filter = extracted_files_filter();
+    let buffers = gtfs_io::GtfsBuffers::from_path(input_path, &filter)?;
+    let gtfs = Gtfs::from_buffers(buffers.to_slices())?;
+    let lens_before = gtfs.file_lens();
+
+    let gtfs = extract_gtfs(gtfs, params);
+
+    for ((file, len_before), (_, len_after)) in lens_before.iter().zip(gtfs.file_lens()) {
+        if filter.contains(*file) && *len_before > 0 {
+            println!(
+                "{: <20} {: >10} of {: >10} records",
+                file.file_name(),
+                len_after,
+                len_before
+            );
+        }
+    }
+    let extracted_file_names = GtfsFile::ALL
+        .iter()
+        .filter(|file| filter.contains(**file))
+        .map(|file| file.file_name())
+        .collect::<Vec<_>>();
+    println!(
+        "Only these files are extracted: {}",
+        extracted_file_names.join(", ")
+    );
+
+    gtfs.write_to_path(output_path)?;
+    println!("Wrote extracted GTFS to {:?}", output_path);
+    Ok(())
+}
+
+/// Reduces the feed to trips that run in the date range and stop at least twice in the areas.
+/// Only the stops of these trips and the records they reference are kept. Trips are cut to the
+/// stops in the areas.
+fn extract_gtfs<'a>(gtfs: Gtfs<'a>, params: &ExtractParams) -> Gtfs<'a> {
+    let dataset = GtfsDataset::new(gtfs, WalkingParams::default());
+    let stops_inside = find_stops_inside(&dataset, &params.areas);
+    let mut gtfs = dataset.raw;
+
+    let active_services = if params.start_date.is_some() || params.end_date.is_some() {
+        Some(find_active_services(&gtfs, params))
+    } else {
+        None
+    };
+
+    let (trip_ids, trip_route_ids, trip_service_ids, trip_shape_ids) = match &gtfs.trips.data {
+        Some(trips) => (
+            id_column(&trips.trip_id, gtfs.trips.len),
+            id_column(&trips.route_id, gtfs.trips.len),
+            id_column(&trips.service_id, gtfs.trips.len),
+            id_column(&trips.shape_id, gtfs.trips.len),
+        ),
+        None => Default::default(),
+    };
+    let trip_indices = index_by_id(&trip_ids);
+    let trip_is_active = trip_service_ids
+        .iter()
+        .map(|service_id| {
+            active_services
+                .as_ref()
+                .is_none_or(|services| services.contains(service_id))
+        })
+        .collect::<Vec<_>>();
+
+    let stop_ids = match &gtfs.stops.data {
+        Some(stops) => id_column(&stops.stop_id, gtfs.stops.len),
+        None => vec![],
+    };
+    let stop_indices = index_by_id(&stop_ids);
+
+    // Keep the stop times of active trips at stops inside the areas.
+    let (stop_time_trip_indices, stop_time_stop_indices): (Vec<_>, Vec<_>) =
+        match &gtfs.stop_times.data {
+            Some(stop_times) => id_column(&stop_times.trip_id, gtfs.stop_times.len)
+                .iter()
+                .zip(id_column(&stop_times.stop_id, gtfs.stop_times.len))
+                .map(|(trip_id, stop_id)| (trip_indices.get(trip_id), stop_indices.get(stop_id)))
+                .unzip(),
+            None => Default::default(),
+        };
+    let mut keep_stop_times = stop_time_trip_indices
+        .iter()
+        .zip(&stop_time_stop_indices)
+        .map(|(trip_i, stop_i)| match (trip_i, stop_i) {
+            (Some(trip_i), Some(stop_i)) => trip_is_active[**trip_i] && stops_inside[**stop_i],
+            _ => false,
+        })
+        .collect::<Vec<_>>();
+
+    // A trip has to have at least two stop times to be useful.
+    let mut stop_times_num_per_trip = vec![0; trip_ids.len()];
+    for (trip_i, keep) in stop_time_trip_indices.iter().zip(&keep_stop_times) {
+        if let (Some(trip_i), true) = (trip_i, keep) {
+            stop_times_num_per_trip[**trip_i] += 1;
+        }
+    }
+    let keep_trips = stop_times_num_per_trip
+        .iter()
+        .map(|num| *num >= 2)
+        .collect::<Vec<_>>();
+    let mut keep_stops = vec![false; stop_ids.len()];
+    for ((trip_i, stop_i), keep) in stop_time_trip_indices
+        .iter()
+        .zip(&stop_time_stop_indices)
+        .zip(keep_stop_times.iter_mut())
+    {
+        if let (Some(trip_i), Some(stop_i), true) = (trip_i, stop_i, *keep) {
+            *keep = keep_trips[**trip_i];
+            keep_stops[**stop_i] |= *keep;
+        }
+    }
+    add_related_stops(&gtfs, &stop_indices, &mut keep_stops);
+
+    let kept_trip_ids = kept_ids(&trip_ids, &keep_trips);
+    let kept_route_ids = kept_ids(&trip_route_ids, &keep_trips);
+    let kept_service_ids = kept_ids(&trip_service_ids, &keep_trips);
+    let kept_shape_ids = kept_ids(&trip_shape_ids, &keep_trips);
+    let kept_stop_ids = kept_ids(&stop_ids, &keep_stops);
+
+    let (keep_routes, kept_agency_ids) = match &gtfs.routes.data {
+        Some(routes) => {
+            let route_ids = id_column(&routes.route_id, gtfs.routes.len);
+            let keep_routes = keep_by_id(&route_ids, &kept_route_ids);
+            let agency_ids = id_column(&routes.agency_id, gtfs.routes.len);
+            let kept_agency_ids = kept_ids(&agency_ids, &keep_routes);
+            (keep_routes, kept_agency_ids)
+        }
+        None => Default::default(),
+    };
+    let keep_agencies = match &gtfs.agencies.data {
+        // An empty agency id is used when there is only one agency.
+        Some(agencies) => id_column(&agencies.agency_id, gtfs.agencies.len)
+            .iter()
+            .map(|id| kept_agency_ids.contains(id) || (id.is_empty() && !kept_route_ids.is_empty()))
+            .collect(),
+        None => vec![],
+    };
+
+    let keep_calendars = match gtfs.calendars.data.as_mut() {
+        Some(calendars) => {
+            // Services don't have to run outside of the extracted date range anymore.
+            for date in calendars.start_date.iter_mut().flatten() {
+                if let Some(start_date) = params.start_date {
+                    *date = (*date).max(start_date);
+                }
+            }
+            for date in calendars.end_date.iter_mut().flatten() {
+                if let Some(end_date) = params.end_date {
+                    *date = (*date).min(end_date);
+                }
+            }
+            let mut keep = keep_by_id(
+                &id_column(&calendars.service_id, gtfs.calendars.len),
+                &kept_service_ids,
+            );
+            // Services that are only kept because of added dates in calendar_dates.txt don't
+            // run regularly within the date range anymore.
+            if let (Some(start_dates), Some(end_dates)) =
+                (&calendars.start_date, &calendars.end_date)
+            {
+                for ((keep, start_date), end_date) in
+                    keep.iter_mut().zip(start_dates).zip(end_dates)
+                {
+                    *keep &= start_date <= end_date;
+                }
+            }
+            keep
+        }
+        None => vec![],
+    };
+    let keep_calendar_dates = match &gtfs.calendar_dates.data {
+        Some(calendar_dates) => {
+            let mut keep = keep_by_id(
+                &id_column(&calendar_dates.service_id, gtfs.calendar_dates.len),
+                &kept_service_ids,
+            );
+            if let Some(dates) = &calendar_dates.date {
+                for (keep, date) in keep.iter_mut().zip(dates) {
+                    *keep &= date_in_range(date, params);
+                }
+            }
+            keep
+        }
+        None => vec![],
+    };
+    let keep_shapes = match &gtfs.shapes.data {
+        Some(shapes) => keep_by_id(
+            &id_column(&shapes.shape_id, gtfs.shapes.len),
+            &kept_shape_ids,
+        ),
+        None => vec![],
+    };
+    let keep_frequencies = match &gtfs.frequencies.data {
+        Some(frequencies) => keep_by_id(
+            &id_column(&frequencies.trip_id, gtfs.frequencies.len),
+            &kept_trip_ids,
+        ),
+        None => vec![],
+    };
+    let keep_transfers = match &gtfs.transfers.data {
+        Some(transfers) => {
+            let len = gtfs.transfers.len;
+            // Empty references are allowed, because they are optional.
+            let references = [
+                (&transfers.from_stop_id, &kept_stop_ids),
+                (&transfers.to_stop_id, &kept_stop_ids),
+                (&transfers.from_route_id, &kept_route_ids),
+                (&transfers.to_route_id, &kept_route_ids),
+                (&transfers.from_trip_id, &kept_trip_ids),
+                (&transfers.to_trip_id, &kept_trip_ids),
+            ];
+            let mut keep = vec![true; len];
+            for (column, kept) in references {
+                for (keep, id) in keep.iter_mut().zip(id_column(column, len)) {
+                    *keep &= id.is_empty() || kept.contains(id);
+                }
+            }
+            keep
+        }
+        None => vec![],
+    };
+    let kept_level_ids = match &gtfs.stops.data {
+        Some(stops) => kept_ids(&id_column(&stops.level_id, gtfs.stops.len), &keep_stops),
+        None => HashSet::new(),
+    };
+    let keep_levels = match &gtfs.levels.data {
+        Some(levels) => keep_by_id(
+            &id_column(&levels.level_id, gtfs.levels.len),
+            &kept_level_ids,
+        ),
+        None => vec![],
+    };
+
+    gtfs.agencies.retain_records(&keep_agencies);
+    gtfs.stops.retain_records(&keep_stops);
+    gtfs.routes.retain_records(&keep_routes);
+    gtfs.trips.retain_records(&keep_trips);
+    gtfs.stop_times.retain_records(&keep_stop_times);
+    gtfs.calendars.retain_records(&keep_calendars);
+    gtfs.calendar_dates.retain_records(&keep_calendar_dates);
+    gtfs.shapes.retain_records(&keep_shapes);
+    gtfs.frequencies.retain_records(&keep_frequencies);
+    gtfs.transfers.retain_records(&keep_transfers);
+    gtfs.levels.retain_records(&keep_levels);
+    gtfs
+}
+
+/// Finds the stops that are in all areas. All stops are inside if there are no areas.
+fn find_stops_inside(dataset: &GtfsDataset, areas: &[ExtractArea]) -> Vec<bool> {
+    let stops_len = dataset.raw.stops.len;
+    let Some(first_area) = areas.first() else {
+        return vec![true; stops_len];
+    };
+    let mut stops_inside = vec![false; stops_len];
+    let bounds = first_area.bounds();
+    let candidates = dataset
+        .get_stops_tree()
+        .locate_in_envelope(&AABB::from_corners(
+            [bounds.left, bounds.bottom],
+            [bounds.right, bounds.top],
+        ));
+    for stop in candidates {
+        if areas.iter().all(|area| area.contains(stop.position)) {
+            stops_inside[stop.stop_i as usize] = true;
+        }
+    }
+    stops_inside
+}
+
+/// Finds the services that run on at least one day in the date range.
+fn find_active_services<'a>(gtfs: &Gtfs<'a>, params: &ExtractParams) -> HashSet<&'a str> {
+    let calendar = gtfs.service_calendar();
+    let (Some(first_date), Some(last_date)) = (calendar.first_date(), calendar.last_date()) else {
+        return HashSet::new();
+    };
+    calendar.active_services_between(
+        params.start_date.as_ref().unwrap_or(&first_date),
+        params.end_date.as_ref().unwrap_or(&last_date),
+    )
+}
+
+/// Keeps the parent stations of kept stops and the entrances, generic nodes and boarding areas
+/// of kept stations and platforms.
+fn add_related_stops(gtfs: &Gtfs, stop_indices: &HashMap<&str, usize>, keep_stops: &mut [bool]) {
+    let Some(stops) = &gtfs.stops.data else {
+        return;
+    };
+    let Some(parent_stations) = &stops.parent_station else {
+        return;
+    };
+    let parent_indices = parent_stations
+        .iter()
+        .map(|id| stop_indices.get(id).copied())
+        .collect::<Vec<_>>();
+    let location_types = stops.location_type.as_deref().unwrap_or_default();
+    // Repeat until nothing changes, because the hierarchy can be multiple levels deep.
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (stop_i, parent_i) in parent_indices.iter().enumerate() {
+            let Some(parent_i) = *parent_i else {
+                continue;
+            };
+            if keep_stops[stop_i] && !keep_stops[parent_i] {
+                keep_stops[parent_i] = true;
+                changed = true;
+            }
+            let is_station_part = matches!(
+                location_types.get(stop_i),
+                Some(LocationType::Entrance | LocationType::Generic | LocationType::BoardingArea)
+            );
+            if is_station_part && keep_stops[parent_i] && !keep_stops[stop_i] {
+                keep_stops[stop_i] = true;
+                changed = true;
+            }
+        }
+    }
+}
+
+/// Get a copy of the ids so that the file can be modified afterwards. A missing column is
+/// treated as if all ids are empty.
+fn id_column<'a>(column: &Option<Vec<&'a str>>, len: usize) -> Vec<&'a str> {
+    match column {
+        Some(column) => column.clone(),
+        None => vec![""; len],
+    }
+}
+
+fn index_by_id<'a>(ids: &[&'a str]) -> HashMap<&'a str, usize> {
+    ids.iter().enumerate().map(|(i, id)| (*id, i)).collect()
+}
+
+fn kept_ids<'a>(ids: &[&'a str], keep: &[bool]) -> HashSet<&'a str> {
+    ids.iter()
+        .zip(keep)
+        .filter(|(id, keep)| **keep && !id.is_empty())
+        .map(|(id, _)| *id)
+        .collect()
+}
+
+fn keep_by_id(ids: &[&str], kept_ids: &HashSet<&str>) -> Vec<bool> {
+    ids.iter().map(|id| kept_ids.contains(id)).collect()
+}
+
+fn date_in_range(date: &Date, params: &ExtractParams) -> bool {
+    params
+        .start_date
+        .is_none_or(|start_date| *date >= start_date)
+        && params.end_date.is_none_or(|end_date| *date <= end_date)
+}
+
+impl ExtractArea {
+    /// Parses a bounding box in the GeoJSON order `min_lon,min_lat,max_lon,max_lat`.
+    pub fn from_bbox_str(bbox: &str) -> Result<Self> {
+        let values = bbox
+            .split(',')
+            .map(|value| value.trim().parse::<f32>())
+            .collect::<Result<Vec<_>, _>>()?;
+        let [min_lon, min_lat, max_lon, max_lat] = values[..] else {
+            return Err(anyhow!(
+                "Expected bounding box as min_lon,min_lat,max_lon,max_lat, got {:?}",
+                bbox
+            ));
+        };
+        Ok(Self::Bounds(LatLonBounds::from_corners(
+            LatLon::new(min_lat, min_lon),
+            LatLon::new(max_lat, max_lon),
+        )))
+    }
+
+    /// Uses all polygons and multi-polygons in a GeoJSON geometry, feature or feature collection.
+    pub fn from_geojson(geojson: &str) -> Result<Self> {
+        let value: serde_json::Value = serde_json::from_str(geojson)?;
+        let mut polygons = vec![];
+        collect_geojson_polygons(&value, &mut polygons)?;
+        if polygons.is_empty() {
+            return Err(anyhow!("GeoJSON does not contain any polygons"));
+        }
+        Ok(Self::Polygons(polygons))
+    }
+
+    fn bounds(&self) -> LatLonBounds {
+        match self {
+            ExtractArea::Bounds(bounds) => *bounds,
+            ExtractArea::Polygons(polygons) => {
+                let mut bounds = LatLonBounds {
+                    left: f32::MAX,
+                    right: f32::MIN,
+                    top: f32::MIN,
+                    bottom: f32::MAX,
+                };
+                // Only the outer rings are relevant for the bounds.
+                for point in polygons.iter().filter_map(|rings| rings.first()).flatten() {
+                    bounds.left = bounds.left.min(point.longitude);
+                    bounds.right = bounds.right.max(point.longitude);
+                    bounds.top = bounds.top.max(point.latitude);
+                    bounds.bottom = bounds.bottom.min(point.latitude);
+                }
+                bounds
+            }
+        }
+    }
+
+    fn contains(&self, position: LatLon) -> bool {
+        match self {
+            ExtractArea::Bounds(bounds) => bounds.contains(position),
+            // The point is in a polygon if it is in an odd number of its rings, so that holes
+            // are excluded.
+            ExtractArea::Polygons(polygons) => polygons.iter().any(|rings| {
+                rings
+                    .iter()
+                    .filter(|ring| ring_contains(ring, position))
+                    .count()
+                    % 2
+                    == 1
+            }),
+        }
+    }
+}
+
+/// Even-odd ray casting. Longitudes are treated as planar coordinates.
+fn ring_contains(ring: &[LatLon], position: LatLon) -> bool {
+    let mut inside = false;
+    for (a, b) in ring.iter().zip(ring.iter().cycle().skip(1)) {
+        if (a.latitude > position.latitude) != (b.latitude > position.latitude) {
+            let crossing_longitude = a.longitude
+                + (position.latitude - a.latitude) / (b.latitude - a.latitude)
+                    * (b.longitude - a.longitude);
+            if position.longitude < crossing_longitude {
+                inside = !inside;
+            }
+        }
+    }
+    inside
+}
+
+fn collect_geojson_polygons(
+    value: &serde_json::Value,
+    polygons: &mut Vec<Vec<Vec<LatLon>>>,
+) -> Result<()> {
+    match value["type"].as_str() {
+        Some("FeatureCollection") => {
+            for feature in value["features"].as_array().into_iter().flatten() {
+                collect_geojson_polygons(feature, polygons)?;
+            }
+        }
+        Some("Feature") => collect_geojson_polygons(&value["geometry"], polygons)?,
+        Some("GeometryCollection") => {
+            for geometry in value["geometries"].as_array().into_iter().flatten() {
+                collect_geojson_polygons(geometry, polygons)?;
+            }
+        }
+        Some("Polygon") => polygons.push(parse_geojson_rings(&value["coordinates"])?),
+        Some("MultiPolygon") => {
+            for polygon in value["coordinates"].as_array().into_iter().flatten() {
+                polygons.push(parse_geojson_rings(polygon)?);
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn parse_geojson_rings(value: &serde_json::Value) -> Result<Vec<Vec<LatLon>>> {
+    let invalid = || anyhow!("Invalid polygon coordinates in GeoJSON");
+    value
+        .as_array()
+        .ok_or_else(invalid)?
+        .iter()
+        .map(|ring| {
+            ring.as_array()
+                .ok_or_else(invalid)?
+                .iter()
+                .map(|point| match (point[0].as_f64(), point[1].as_f64()) {
+                    (Some(longitude), Some(latitude)) => {
+                        Ok(LatLon::new(latitude as f32, longitude as f32))
+                    }
+                    _ => Err(invalid()),
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use csvelo::ParseCsvField;
+
+    use super::*;
+    use crate::tests::load_gtfs_dummy_buffers;
+
+    fn date(text: &str) -> Date {
+        Date::parse_csv_field(text.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn test_extract_area() {
+        let buffers = load_gtfs_dummy_buffers(&extracted_files_filter());
+        let gtfs = Gtfs::from_buffers(buffers.to_slices()).unwrap();
+        let params = ExtractParams {
+            areas: vec![ExtractArea::from_bbox_str("0,0,30,50").unwrap()],
+            ..ExtractParams::default()
+        };
+        let gtfs = extract_gtfs(gtfs, &params);
+        assert_eq!(gtfs.stops.len, 2);
+        assert_eq!(gtfs.trips.len, 1);
+        assert_eq!(gtfs.stop_times.len, 2);
+        assert_eq!(gtfs.routes.len, 1);
+        assert_eq!(gtfs.agencies.len, 1);
+        assert_eq!(gtfs.shapes.len, 3);
+        assert_eq!(gtfs.calendar_dates.len, 1);
+        assert_eq!(gtfs.transfers.len, 2);
+
+        // Only the first stop is inside, so the trip is not useful anymore.
+        let buffers = load_gtfs_dummy_buffers(&extracted_files_filter());
+        let gtfs = Gtfs::from_buffers(buffers.to_slices()).unwrap();
+        let params = ExtractParams {
+            areas: vec![ExtractArea::from_bbox_str("20,40,30,50").unwrap()],
+            ..ExtractParams::default()
+        };
+        let gtfs = extract_gtfs(gtfs, &params);
+        assert_eq!(gtfs.stops.len, 0);
+        assert_eq!(gtfs.trips.len, 0);
+        assert_eq!(gtfs.stop_times.len, 0);
+        assert_eq!(gtfs.routes.len, 0);
+        assert_eq!(gtfs.agencies.len, 0);
+        assert_eq!(gtfs.transfers.len, 0);
+    }
+
+    #[test]
+    fn test_extract_date_range() {
+        let buffers = load_gtfs_dummy_buffers(&extracted_files_filter());
+        let gtfs = Gtfs::from_buffers(buffers.to_slices()).unwrap();
+        let params = ExtractParams {
+            start_date: Some(date("20250601")),
+            end_date: Some(date("20250630")),
+            ..ExtractParams::default()
+        };
+        let gtfs = extract_gtfs(gtfs, &params);
+        assert_eq!(gtfs.trips.len, 1);
+        assert_eq!(gtfs.calendar_dates.len, 0);
+        let calendars = gtfs.calendars.data.unwrap();
+        assert_eq!(calendars.start_date.unwrap(), vec![date("20250601")]);
+        assert_eq!(calendars.end_date.unwrap(), vec![date("20250630")]);
+
+        let buffers = load_gtfs_dummy_buffers(&extracted_files_filter());
+        let gtfs = Gtfs::from_buffers(buffers.to_slices()).unwrap();
+        let params = ExtractParams {
+            start_date: Some(date("20260101")),
+            ..ExtractParams::default()
+        };
+        let gtfs = extract_gtfs(gtfs, &params);
+        assert_eq!(gtfs.trips.len, 0);
+        assert_eq!(gtfs.stops.len, 0);
+        assert_eq!(gtfs.calendars.len, 0);
+
+        // The service only runs on an added date after the regular date range.
+        let buffers = load_gtfs_dummy_buffers(&extracted_files_filter());
+        let mut gtfs = Gtfs::from_buffers(buffers.to_slices()).unwrap();
+        gtfs.calendar_dates
+            .data
+            .as_mut()
+            .unwrap()
+            .date
+            .as_mut()
+            .unwrap()[0] = date("20260105");
+        let params = ExtractParams {
+            start_date: Some(date("20260101")),
+            ..ExtractParams::default()
+        };
+        let gtfs = extract_gtfs(gtfs, &params);
+        assert_eq!(gtfs.trips.len, 1);
+        assert_eq!(gtfs.calendar_dates.len, 1);
+        assert_eq!(gtfs.calendars.len, 0);
+    }
+
+    #[test]
+    fn test_geojson_polygon() {
+        let area = ExtractArea::from_geojson(
+            r#"{
+                "type": "FeatureCollection",
+                "features": [{
+                    "type": "Feature",
+                    "properties": {},
+                    "geometry": {
+                        "type": "Polygon",
+                        "coordinates": [
+                            [[0, 0], [10, 0], [10, 10], [0, 10], [0, 0]],
+                            [[4, 4], [6, 4], [6, 6], [4, 6], [4, 4]]
+                        ]
+                    }
+                }]
+            }"#,
+        )
+        .unwrap();
+        assert!(area.contains(LatLon::new(2.0, 2.0)));
+        assert!(!area.contains(LatLon::new(5.0, 5.0)));
+        assert!(!area.contains(LatLon::new(11.0, 2.0)));
+        let bounds = area.bounds();
+        assert_eq!((bounds.left, bounds.right), (0.0, 10.0));
+        assert_eq!((bounds.bottom, bounds.top), (0.0, 10.0));
+
+        assert!(ExtractArea::from_geojson(r#"{"type": "Point", "coordinates": [1, 2]}"#).is_err());
+    }
+}
@@ -1,14 +1,38 @@
-use anyhow::Result;
-use gtfs_io::{Gtfs, GtfsFilter};
+use anyhow::{anyhow, Result};
+use bumpalo::Bump;
+use gtfs_io::{Gtfs, GtfsFile, GtfsFilter, LocationType};
 use rstar::{RTree, RTreeObject, AABB};
-use std::path::Path;
+use std::{borrow::Cow, collections::HashMap, path::Path};
 
 use crate::{
     coordinates::{LatLon, XYZ},
     gtfs_sources,
 };
 
-#[derive(Debug)]
+/// Stops from different sources are only merged if they are at most this far apart.
+const MAX_STOP_MERGE_DISTANCE_KM: f32 = 0.1;
+/// Normalized Levenshtein similarity that lowercase stop names need to have to be merged.
+const MIN_STOP_NAME_SIMILARITY: f64 = 0.8;
+
+/// Files whose ids are namespaced during the merge. Other files are not part of the output.
+fn merged_files_filter() -> GtfsFilter {
+    GtfsFilter {
+        agencies: true,
+        stops: true,
+        routes: true,
+        trips: true,
+        stop_times: true,
+        calendars: true,
+        calendar_dates: true,
+        shapes: true,
+        frequencies: true,
+        transfers: true,
+        levels: true,
+        ..GtfsFilter::none()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
 struct OriginalStop {
     source_i: u32,
     stop_i: u32,
@@ -24,31 +48,307 @@ impl RTreeObject for OriginalStop {
     }
 }
 
-pub async fn gtfs_merge(input_path: &Path, _output_path: &Path) -> Result<()> {
+/// A stop that has been replaced by a similar stop of another source.
+#[derive(Debug)]
+struct StopMergeDecision {
+    source_i: usize,
+    stop_id: String,
+    stop_name: String,
+    into_source_i: usize,
+    into_stop_id: String,
+    into_stop_name: String,
+    distance_m: f32,
+}
+
+struct MergeResult<'a> {
+    gtfs: Gtfs<'a>,
+    stop_decisions: Vec<StopMergeDecision>,
+}
+
+pub async fn gtfs_merge(input_path: &Path, output_path: &Path) -> Result<()> {
     let gtfs_sources = gtfs_sources::get_gtfs_sources(input_path, true);
+    // The largest sources come first, so that their stops are kept when stops are merged.
     let gtfs_sources = gtfs_sources::sort_gtfs_sources_by_size(gtfs_sources);
+    if gtfs_sources.is_empty() {
+        return Err(anyhow!("No GTFS sources found in {:?}", input_path));
+    }
     println!("Found {} GTFS sources to merge.", gtfs_sources.len());
 
-    let mut all_original_stops = Vec::new();
-
+    let filter = merged_files_filter();
+    let mut all_buffers = vec![];
     for (source_i, gtfs_source) in gtfs_sources.iter().enumerate() {
-        println!("{: >3} Loading GTFS from {:?}", source_i + 1, gtfs_source);
-        let Ok(buffers) = gtfs_io::GtfsBuffers::from_path(
-            &gtfs_source,
-            &GtfsFilter {
-                stops: true,
-                ..GtfsFilter::none()
-            },
-        ) else {
-            continue;
+        println!(
+            "{: >3} Loading GTFS from {:?} with id prefix \"{}\"",
+            source_i + 1,
+            gtfs_source,
+            source_prefix(source_i)
+        );
+        all_buffers.push(gtfs_io::GtfsBuffers::from_path(gtfs_source, &filter)?);
+    }
+    let sources = all_buffers
+        .iter()
+        .map(|buffers| Gtfs::from_buffers(buffers.to_slices()))
+        .collect::<Result<Vec<_>>>()?;
+
+    let arena = Bump::new();
+    let result = merge_feeds(sources, &arena);
+
+    println!();
+    for decision in &result.stop_decisions {
+        println!(
+            "Merged stop {}{} \"{}\" into {}{} \"{}\" ({:.0} m apart)",
+            source_prefix(decision.source_i),
+            decision.stop_id,
+            decision.stop_name,
+            source_prefix(decision.into_source_i),
+            decision.into_stop_id,
+            decision.into_stop_name,
+            decision.distance_m,
+        );
+    }
+    println!(
+        "Merged {} stops into stops of other sources.",
+        result.stop_decisions.len()
+    );
+    let merged_file_names = GtfsFile::ALL
+        .iter()
+        .filter(|file| filter.contains(**file))
+        .map(|file| file.file_name())
+        .collect::<Vec<_>>();
+    println!(
+        "Only these files are merged: {}",
+        merged_file_names.join(", ")
+    );
+
+    result.gtfs.write_to_path(output_path)?;
+    println!("Wrote merged GTFS to {:?}", output_path);
+    Ok(())
+}
+
+/// Ids of every source are prefixed with this to avoid collisions.
+fn source_prefix(source_i: usize) -> String {
+    format!("{}:", source_i + 1)
+}
+
+/// Merges all sources into one feed. Ids are prefixed per source and similar stops of different
+/// sources are replaced by the stop of the earliest source.
+fn merge_feeds<'a>(sources: Vec<Gtfs<'a>>, arena: &'a Bump) -> MergeResult<'a> {
+    let (merged_into, stop_decisions) = find_stops_to_merge(&sources);
+
+    // Compute the new ids of all stops up front, because stops may be replaced by stops of
+    // sources that are merged later.
+    let prefixed_stop_ids = sources
+        .iter()
+        .enumerate()
+        .map(|(source_i, gtfs)| {
+            let prefix = source_prefix(source_i);
+            stop_ids(gtfs)
+                .iter()
+                .map(|id| &*arena.alloc_str(&format!("{}{}", prefix, id)))
+                .collect::<Vec<&'a str>>()
+        })
+        .collect::<Vec<_>>();
+
+    let mut merged = Gtfs::default();
+    for (source_i, mut gtfs) in sources.into_iter().enumerate() {
+        let prefix = source_prefix(source_i);
+        let new_stop_ids: HashMap<&'a str, &'a str> = stop_ids(&gtfs)
+            .iter()
+            .enumerate()
+            .map(|(stop_i, id)| {
+                let new_id = match merged_into[source_i][stop_i] {
+                    Some((into_source_i, into_stop_i)) => {
+                        prefixed_stop_ids[into_source_i][into_stop_i]
+                    }
+                    None => prefixed_stop_ids[source_i][stop_i],
+                };
+                (*id, new_id)
+            })
+            .collect();
+        let prefix_ids = |column: &mut Option<Vec<&'a str>>| {
+            prefix_id_column(column, &prefix, arena);
+        };
+        let replace_stop_ids = |column: &mut Option<Vec<&'a str>>| {
+            replace_stop_id_column(column, &new_stop_ids, &prefix, arena);
         };
-        let Ok(gtfs) = gtfs_io::Gtfs::from_buffers(buffers.to_slices()) else {
+
+        // The agency id is optional when there is only one agency. Routes without an agency
+        // id belong to that agency.
+        let agencies_len = gtfs.agencies.len;
+        let mut only_agency_id = MISSING_ID;
+        if let Some(agencies) = gtfs.agencies.data.as_mut() {
+            fill_missing_ids(&mut agencies.agency_id, agencies_len, MISSING_ID);
+            if let [agency_id] = agencies.agency_id.as_deref().unwrap_or_default() {
+                only_agency_id = agency_id;
+            }
+            prefix_ids(&mut agencies.agency_id);
+        }
+        let routes_len = gtfs.routes.len;
+        if let Some(routes) = gtfs.routes.data.as_mut() {
+            prefix_ids(&mut routes.route_id);
+            fill_missing_ids(&mut routes.agency_id, routes_len, only_agency_id);
+            prefix_ids(&mut routes.agency_id);
+            prefix_ids(&mut routes.network_id);
+        }
+        if let Some(stops) = gtfs.stops.data.as_mut() {
+            replace_stop_ids(&mut stops.stop_id);
+            replace_stop_ids(&mut stops.parent_station);
+            prefix_ids(&mut stops.zone_id);
+            prefix_ids(&mut stops.level_id);
+        }
+        let keep_stops = merged_into[source_i]
+            .iter()
+            .map(|into| into.is_none())
+            .collect::<Vec<_>>();
+        gtfs.stops.retain_records(&keep_stops);
+        if let Some(trips) = gtfs.trips.data.as_mut() {
+            prefix_ids(&mut trips.route_id);
+            prefix_ids(&mut trips.service_id);
+            prefix_ids(&mut trips.trip_id);
+            prefix_ids(&mut trips.block_id);
+            prefix_ids(&mut trips.shape_id);
+        }
+        if let Some(stop_times) = gtfs.stop_times.data.as_mut() {
+            prefix_ids(&mut stop_times.trip_id);
+            replace_stop_ids(&mut stop_times.stop_id);
+        }
+        if let Some(calendars) = gtfs.calendars.data.as_mut() {
+            prefix_ids(&mut calendars.service_id);
+        }
+        if let Some(calendar_dates) = gtfs.calendar_dates.data.as_mut() {
+            prefix_ids(&mut calendar_dates.service_id);
+        }
+        if let Some(shapes) = gtfs.shapes.data.as_mut() {
+            prefix_ids(&mut shapes.shape_id);
+        }
+        if let Some(frequencies) = gtfs.frequencies.data.as_mut() {
+            prefix_ids(&mut frequencies.trip_id);
+        }
+        if let Some(transfers) = gtfs.transfers.data.as_mut() {
+            replace_stop_ids(&mut transfers.from_stop_id);
+            replace_stop_ids(&mut transfers.to_stop_id);
+            prefix_ids(&mut transfers.from_route_id);
+            prefix_ids(&mut transfers.to_route_id);
+            prefix_ids(&mut transfers.from_trip_id);
+            prefix_ids(&mut transfers.to_trip_id);
+        }
+        if let Some(levels) = gtfs.levels.data.as_mut() {
+            prefix_ids(&mut levels.level_id);
+        }
+
+        merged.agencies.append_records(gtfs.agencies);
+        merged.stops.append_records(gtfs.stops);
+        merged.routes.append_records(gtfs.routes);
+        merged.trips.append_records(gtfs.trips);
+        merged.stop_times.append_records(gtfs.stop_times);
+        merged.calendars.append_records(gtfs.calendars);
+        merged.calendar_dates.append_records(gtfs.calendar_dates);
+        merged.shapes.append_records(gtfs.shapes);
+        merged.frequencies.append_records(gtfs.frequencies);
+        merged.transfers.append_records(gtfs.transfers);
+        merged.levels.append_records(gtfs.levels);
+    }
+
+    MergeResult {
+        gtfs: merged,
+        stop_decisions,
+    }
+}
+
+fn stop_ids<'a, 'b>(gtfs: &'b Gtfs<'a>) -> &'b [&'a str] {
+    gtfs.stops
+        .data
+        .as_ref()
+        .and_then(|stops| stops.stop_id.as_deref())
+        .unwrap_or_default()
+}
+
+fn prefix_id_column<'a>(column: &mut Option<Vec<&'a str>>, prefix: &str, arena: &'a Bump) {
+    let Some(column) = column.as_mut() else {
+        return;
+    };
+    for id in column.iter_mut() {
+        // Empty ids are kept, because they mean that there is no reference.
+        if !id.is_empty() {
+            *id = arena.alloc_str(&format!("{}{}", prefix, id));
+        }
+    }
+}
+
+/// Like [`prefix_id_column`] but uses the ids of merged stops. Unknown ids are only prefixed.
+fn replace_stop_id_column<'a>(
+    column: &mut Option<Vec<&'a str>>,
+    new_stop_ids: &HashMap<&'a str, &'a str>,
+    prefix: &str,
+    arena: &'a Bump,
+) {
+    let Some(column) = column.as_mut() else {
+        return;
+    };
+    for id in column.iter_mut() {
+        if id.is_empty() {
             continue;
+        }
+        *id = match new_stop_ids.get(id) {
+            Some(new_id) => new_id,
+            None => arena.alloc_str(&format!("{}{}", prefix, id)),
         };
-        let Some(stops) = gtfs.stops.data else {
+    }
+}
+
+/// Gives empty ids a value, so that they are still unique after prefixing them.
+/// Used for agencies without an id.
+const MISSING_ID: &str = "default";
+
+fn fill_missing_ids<'a>(column: &mut Option<Vec<&'a str>>, len: usize, missing_id: &'a str) {
+    let column = column.get_or_insert_with(|| vec![""; len]);
+    for id in column.iter_mut() {
+        if id.is_empty() {
+            *id = missing_id;
+        }
+    }
+}
+
+/// Finds stops and stations that are likely the same in different sources. For every stop of
+/// every source, the result contains the stop that it should be replaced with. Stops of earlier
+/// sources are preferred.
+#[allow(clippy::type_complexity)]
+fn find_stops_to_merge(
+    sources: &[Gtfs],
+) -> (Vec<Vec<Option<(usize, usize)>>>, Vec<StopMergeDecision>) {
+    let mut merged_into = sources
+        .iter()
+        .map(|gtfs| vec![None; gtfs.stops.len])
+        .collect::<Vec<_>>();
+    let mut is_kept_for_merge = sources
+        .iter()
+        .map(|gtfs| vec![false; gtfs.stops.len])
+        .collect::<Vec<_>>();
+    let mut decisions = vec![];
+
+    let mut all_original_stops = Vec::new();
+    let mut stop_names = Vec::new();
+    let mut location_types = Vec::new();
+    for (source_i, gtfs) in sources.iter().enumerate() {
+        let Some(stops) = gtfs.stops.data.as_ref() else {
+            stop_names.push(vec![]);
+            location_types.push(vec![]);
             continue;
         };
-        let (Some(longitudes), Some(latitudes)) = (stops.stop_lon, stops.stop_lat) else {
+        stop_names.push(
+            stops
+                .stop_name
+                .as_ref()
+                .map(|names| names.iter().map(|name| name.to_lowercase()).collect())
+                .unwrap_or_default(),
+        );
+        location_types.push(
+            stops
+                .location_type
+                .clone()
+                .unwrap_or_else(|| vec![LocationType::Stop; gtfs.stops.len]),
+        );
+        let (Some(longitudes), Some(latitudes)) = (&stops.stop_lon, &stops.stop_lat) else {
             continue;
         };
         for (stop_i, (longitude, latitude)) in longitudes.iter().zip(latitudes.iter()).enumerate() {
@@ -63,46 +363,172 @@ pub async fn gtfs_merge(input_path: &Path, _output_path: &Path) -> Result<()> {
             });
         }
     }
+    let tree = RTree::bulk_load(all_original_stops.clone());
 
-    let start = std::time::Instant::now();
-    println!("Size: {}", all_original_stops.len());
-    let tree = RTree::bulk_load(all_original_stops);
-    println!("Time to build tree: {:?}", start.elapsed());
-
-    let a = LatLon::new(52.6374196, 13.2054151).to_xyz_km();
-    let size_km = 1.0f32;
-    let b = AABB::from_corners(
-        [a.x - size_km, a.y - size_km, a.z - size_km],
-        [a.x + size_km, a.y + size_km, a.z + size_km],
-    );
-
-    for stop in tree.locate_in_envelope(&b) {
-        let source = &gtfs_sources[stop.source_i as usize];
-        let Ok(buffers) = gtfs_io::GtfsBuffers::from_path(
-            &source,
-            &GtfsFilter {
-                stops: true,
-                attributions: true,
-                ..GtfsFilter::none()
-            },
-        ) else {
+    for stop in &all_original_stops {
+        let (source_i, stop_i) = (stop.source_i as usize, stop.stop_i as usize);
+        if merged_into[source_i][stop_i].is_some() {
+            continue;
+        }
+        let location_type = &location_types[source_i][stop_i];
+        if !matches!(location_type, LocationType::Stop | LocationType::Station) {
+            continue;
+        }
+        let Some(name) = stop_names[source_i].get(stop_i).filter(|n| !n.is_empty()) else {
             continue;
         };
-        let gtfs = Gtfs::from_buffers(buffers.to_slices()).unwrap();
-        let stop = gtfs
-            .stops
-            .data
-            .as_ref()
-            .unwrap()
-            .stop_name
-            .as_ref()
-            .unwrap()
-            .get(stop.stop_i as usize)
-            .unwrap();
-        println!("{:?}", stop);
+
+        let a = stop.position;
+        let d = MAX_STOP_MERGE_DISTANCE_KM;
+        let envelope = AABB::from_corners([a.x - d, a.y - d, a.z - d], [a.x + d, a.y + d, a.z + d]);
+        let mut candidates = tree
+            .locate_in_envelope(&envelope)
+            .filter(|other| {
+                let (other_source_i, other_stop_i) =
+                    (other.source_i as usize, other.stop_i as usize);
+                other_source_i != source_i
+                    && merged_into[other_source_i][other_stop_i].is_none()
+                    && !is_kept_for_merge[other_source_i][other_stop_i]
+                    && location_types[other_source_i][other_stop_i] == *location_type
+                    && stop_names[other_source_i]
+                        .get(other_stop_i)
+                        .is_some_and(|other_name| {
+                            strsim::normalized_levenshtein(name, other_name)
+                                >= MIN_STOP_NAME_SIMILARITY
+                        })
+            })
+            .map(|other| (a.dist_to(&other.position), *other))
+            .filter(|(distance, _)| *distance <= MAX_STOP_MERGE_DISTANCE_KM)
+            .collect::<Vec<_>>();
+        candidates.sort_by(|(distance_a, a), (distance_b, b)| {
+            distance_a
+                .total_cmp(distance_b)
+                .then((a.source_i, a.stop_i).cmp(&(b.source_i, b.stop_i)))
+        });
+
+        // Only one stop per source is merged, because stops within a source are distinct.
+        let mut cluster_sources = vec![source_i];
+        for (distance, other) in candidates {
+            let (other_source_i, other_stop_i) = (other.source_i as usize, other.stop_i as usize);
+            if cluster_sources.contains(&other_source_i) {
+                continue;
+            }
+            cluster_sources.push(other_source_i);
+            merged_into[other_source_i][other_stop_i] = Some((source_i, stop_i));
+            is_kept_for_merge[source_i][stop_i] = true;
+            decisions.push(StopMergeDecision {
+                source_i: other_source_i,
+                stop_id: stop_ids(&sources[other_source_i])[other_stop_i].to_string(),
+                stop_name: stop_name(&sources[other_source_i], other_stop_i),
+                into_source_i: source_i,
+                into_stop_id: stop_ids(&sources[source_i])[stop_i].to_string(),
+                into_stop_name: stop_name(&sources[source_i], stop_i),
+                distance_m: distance * 1000.0,
+            });
+        }
     }
+    (merged_into, decisions)
+}
 
-    // println!("{:#?}", tree);
+fn stop_name(gtfs: &Gtfs, stop_i: usize) -> String {
+    gtfs.stops
+        .data
+        .as_ref()
+        .and_then(|stops| stops.stop_name.as_ref())
+        .and_then(|names| names.get(stop_i))
+        .map(Cow::to_string)
+        .unwrap_or_default()
+}
 
-    Ok(())
+#[cfg(test)]
+mod tests {
+    use gtfs_io::OptionalF32;
+
+    use super::*;
+    use crate::tests::load_gtfs_dummy_buffers;
+
+    #[test]
+    fn test_merge_identical_feeds() {
+        let buffers_a = load_gtfs_dummy_buffers(&merged_files_filter());
+        let buffers_b = load_gtfs_dummy_buffers(&merged_files_filter());
+        let sources = vec![
+            Gtfs::from_buffers(buffers_a.to_slices()).unwrap(),
+            Gtfs::from_buffers(buffers_b.to_slices()).unwrap(),
+        ];
+        let arena = Bump::new();
+        let result = merge_feeds(sources, &arena);
+
+        assert_eq!(result.stop_decisions.len(), 2);
+        assert!(result
+            .stop_decisions
+            .iter()
+            .all(|d| d.source_i == 1 && d.into_source_i == 0 && d.distance_m < 1.0));
+
+        let gtfs = result.gtfs;
+        assert_eq!(gtfs.stops.len, 2);
+        assert_eq!(
+            gtfs.stops.data.unwrap().stop_id.unwrap(),
+            vec!["1:1", "1:2"]
+        );
+        assert_eq!(
+            gtfs.agencies.data.unwrap().agency_id.unwrap(),
+            vec!["1:A", "2:A"]
+        );
+        assert_eq!(
+            gtfs.trips.data.unwrap().trip_id.unwrap(),
+            vec!["1:T1", "2:T1"]
+        );
+        let stop_times = gtfs.stop_times.data.unwrap();
+        assert_eq!(
+            stop_times.trip_id.unwrap(),
+            vec!["1:T1", "1:T1", "2:T1", "2:T1"]
+        );
+        assert_eq!(
+            stop_times.stop_id.unwrap(),
+            vec!["1:2", "1:1", "1:2", "1:1"]
+        );
+        let transfers = gtfs.transfers.data.unwrap();
+        assert_eq!(
+            transfers.from_stop_id.unwrap(),
+            vec!["1:1", "1:2", "1:1", "1:2"]
+        );
+        assert_eq!(gtfs.shapes.len, 10);
+    }
+
+    #[test]
+    fn test_distant_stops_are_not_merged() {
+        let buffers_a = load_gtfs_dummy_buffers(&merged_files_filter());
+        let buffers_b = load_gtfs_dummy_buffers(&merged_files_filter());
+        let mut gtfs_b = Gtfs::from_buffers(buffers_b.to_slices()).unwrap();
+        let stops_b = gtfs_b.stops.data.as_mut().unwrap();
+        // Move the first stop about 1 km north and rename the second one.
+        stops_b.stop_lat.as_mut().unwrap()[0] = OptionalF32(Some(42.01));
+        stops_b.stop_name.as_mut().unwrap()[1] = "Central Station".into();
+
+        let sources = vec![Gtfs::from_buffers(buffers_a.to_slices()).unwrap(), gtfs_b];
+        let arena = Bump::new();
+        let result = merge_feeds(sources, &arena);
+        assert!(result.stop_decisions.is_empty());
+        assert_eq!(
+            result.gtfs.stops.data.unwrap().stop_id.unwrap(),
+            vec!["1:1", "1:2", "2:1", "2:2"]
+        );
+    }
+
+    #[test]
+    fn test_routes_without_agency_id() {
+        let buffers_a = load_gtfs_dummy_buffers(&merged_files_filter());
+        let buffers_b = load_gtfs_dummy_buffers(&merged_files_filter());
+        let mut gtfs_b = Gtfs::from_buffers(buffers_b.to_slices()).unwrap();
+        // The agency id may be left out when the feed has only one agency.
+        gtfs_b.routes.data.as_mut().unwrap().agency_id = None;
+
+        let sources = vec![Gtfs::from_buffers(buffers_a.to_slices()).unwrap(), gtfs_b];
+        let arena = Bump::new();
+        let result = merge_feeds(sources, &arena);
+        assert_eq!(
+            result.gtfs.routes.data.unwrap().agency_id.unwrap(),
+            vec!["1:A", "2:A"]
+        );
+    }
 }
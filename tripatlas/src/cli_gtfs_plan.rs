@@ -0,0 +1,120 @@
+use std::{collections::HashMap, path::Path};
+
+use anyhow::Result;
+use gtfs_io::{Date, Gtfs, GtfsFilter, Stops};
+
+use crate::{
+    gtfs_dataset::GtfsDataset,
+    routing::{self, format_time, JourneyLeg, JourneyQuery, Time, WalkingParams},
+};
+
+pub struct PlanParams {
+    pub from_stop_id: String,
+    pub to_stop_id: String,
+    pub date: Date,
+    pub departure: Time,
+    pub max_transfers: usize,
+    pub walking: WalkingParams,
+}
+
+pub fn routing_files_filter() -> GtfsFilter {
+    GtfsFilter {
+        stops: true,
+        routes: true,
+        trips: true,
+        stop_times: true,
+        calendars: true,
+        calendar_dates: true,
+        frequencies: true,
+        transfers: true,
+        ..GtfsFilter::none()
+    }
+}
+
+pub async fn gtfs_plan(path: &Path, params: &PlanParams) -> Result<()> {
+    println!("Loading GTFS from {:?}", path);
+    let buffers = gtfs_io::GtfsBuffers::from_path(path, &routing_files_filter())?;
+    let dataset = GtfsDataset::new(Gtfs::from_buffers(buffers.to_slices())?, params.walking);
+
+    let start = std::time::Instant::now();
+    let timetable = dataset.get_timetable(params.date);
+    println!(
+        "Built timetable for {} with {} patterns and {} transfers in {:?}",
+        timetable.date(),
+        timetable.patterns().len(),
+        timetable.transfers_num(),
+        start.elapsed()
+    );
+
+    let start = std::time::Instant::now();
+    let journeys = routing::plan_journeys(
+        &timetable,
+        &JourneyQuery {
+            from_stop_id: &params.from_stop_id,
+            to_stop_id: &params.to_stop_id,
+            departure: params.departure,
+            max_transfers: params.max_transfers,
+        },
+    )?;
+    println!("Found {} journeys in {:?}", journeys.len(), start.elapsed());
+
+    let stop_names: HashMap<&str, &str> = match &dataset.raw.stops.data {
+        Some(Stops {
+            stop_id: Some(stop_ids),
+            stop_name: Some(stop_names),
+            ..
+        }) => stop_ids
+            .iter()
+            .zip(stop_names)
+            .map(|(stop_id, stop_name)| (*stop_id, stop_name.as_ref()))
+            .collect(),
+        _ => HashMap::new(),
+    };
+    let stop_name = |stop| {
+        let stop_id = timetable.stops().id(stop);
+        stop_names.get(stop_id).copied().unwrap_or(stop_id)
+    };
+    for journey in &journeys {
+        println!();
+        println!(
+            "{} -> {} with {} transfers",
+            format_time(journey.departure),
+            format_time(journey.arrival),
+            journey.transfers_num()
+        );
+        for leg in &journey.legs {
+            match leg {
+                JourneyLeg::Transit {
+                    route_id,
+                    trip_id,
+                    from_stop,
+                    to_stop,
+                    departure,
+                    arrival,
+                    ..
+                } => println!(
+                    "  {} {} -> {} {} (route {}, trip {})",
+                    format_time(*departure),
+                    stop_name(*from_stop),
+                    format_time(*arrival),
+                    stop_name(*to_stop),
+                    route_id,
+                    trip_id
+                ),
+                JourneyLeg::Walk {
+                    from_stop,
+                    to_stop,
+                    departure,
+                    arrival,
+                } => println!(
+                    "  {} {} -> {} {} (walk)",
+                    format_time(*departure),
+                    stop_name(*from_stop),
+                    format_time(*arrival),
+                    stop_name(*to_stop)
+                ),
+            }
+        }
+    }
+    Ok(())
+}
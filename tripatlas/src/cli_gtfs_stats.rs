@@ -1,25 +1,27 @@
 use anyhow::Result;
 use colored::Colorize;
-use gtfs_io::{Gtfs, GtfsBuffers, GtfsBuffersMmap, GtfsFilter};
+use gtfs_io::{Gtfs, GtfsBuffers, GtfsBuffersMmap, GtfsFile, GtfsFilter};
 use num_format::ToFormattedString;
 use rayon::prelude::*;
+use std::collections::HashMap;
 
 use crate::{
     gtfs_sources::{get_estimated_dataset_size, get_gtfs_sources, sort_gtfs_sources_by_size},
     util,
 };
 
-#[derive(Debug, Default)]
+#[derive(Debug)]
 struct GtfsStats {
-    stop_times_num: usize,
-    stops_num: usize,
-    trips_num: usize,
-    routes_num: usize,
-    calendars_num: usize,
-    calendar_dates_num: usize,
-    agencies_num: usize,
-    feed_infos_num: usize,
-    attributions_num: usize,
+    /// Number of records in each file in the order of [`GtfsFile::ALL`].
+    records_nums: Vec<usize>,
+}
+
+impl Default for GtfsStats {
+    fn default() -> Self {
+        Self {
+            records_nums: vec![0; GtfsFile::ALL.len()],
+        }
+    }
 }
 
 pub async fn gtfs_stats(input_path: &std::path::Path, deduplicate_archives: bool) -> Result<()> {
@@ -42,7 +44,7 @@ pub async fn gtfs_stats(input_path: &std::path::Path, deduplicate_archives: bool
                 "{: >3}/{} {: >10} {}",
                 current,
                 gtfs_sources.len(),
-                util::bytes_to_human_string(get_estimated_dataset_size(&p).unwrap_or_default())
+                util::bytes_to_human_string(get_estimated_dataset_size(p).unwrap_or_default())
                     .green(),
                 p.strip_prefix(input_path)
                     .unwrap_or(p)
@@ -63,74 +65,51 @@ pub async fn gtfs_stats(input_path: &std::path::Path, deduplicate_archives: bool
         .collect();
 
     let merged_stats = all_gtfs_stats.into_iter().filter_map(|r| r.ok()).fold(
-        GtfsStats {
-            ..Default::default()
-        },
+        GtfsStats::default(),
         |acc, stats| GtfsStats {
-            stop_times_num: acc.stop_times_num + stats.stop_times_num,
-            stops_num: acc.stops_num + stats.stops_num,
-            trips_num: acc.trips_num + stats.trips_num,
-            routes_num: acc.routes_num + stats.routes_num,
-            calendars_num: acc.calendars_num + stats.calendars_num,
-            calendar_dates_num: acc.calendar_dates_num + stats.calendar_dates_num,
-            agencies_num: acc.agencies_num + stats.agencies_num,
-            feed_infos_num: acc.feed_infos_num + stats.feed_infos_num,
-            attributions_num: acc.attributions_num + stats.attributions_num,
+            records_nums: acc
+                .records_nums
+                .iter()
+                .zip(stats.records_nums.iter())
+                .map(|(a, b)| a + b)
+                .collect(),
         },
     );
 
     println!("Total GTFS stats:");
     let locale = num_format::Locale::en;
-    println!(
-        "  Stop times: {}",
-        merged_stats.stop_times_num.to_formatted_string(&locale)
-    );
-    println!(
-        "  Stops: {}",
-        merged_stats.stops_num.to_formatted_string(&locale)
-    );
-    println!(
-        "  Trips: {}",
-        merged_stats.trips_num.to_formatted_string(&locale)
-    );
-    println!(
-        "  Routes: {}",
-        merged_stats.routes_num.to_formatted_string(&locale)
-    );
-    println!(
-        "  Calendars: {}",
-        merged_stats.calendars_num.to_formatted_string(&locale)
-    );
-    println!(
-        "  Calendar dates: {}",
-        merged_stats.calendar_dates_num.to_formatted_string(&locale)
-    );
-    println!(
-        "  Agencies: {}",
-        merged_stats.agencies_num.to_formatted_string(&locale)
-    );
-    println!(
-        "  Feed infos: {}",
-        merged_stats.feed_infos_num.to_formatted_string(&locale)
-    );
-    println!(
-        "  Attributions: {}",
-        merged_stats.attributions_num.to_formatted_string(&locale)
-    );
+    for (file, records_num) in GtfsFile::ALL.iter().zip(merged_stats.records_nums.iter()) {
+        println!(
+            "  {}: {}",
+            file.file_name(),
+            records_num.to_formatted_string(&locale)
+        );
+    }
 
     Ok(())
 }
 
 fn analyse_gtfs(gtfs: &Gtfs) -> GtfsStats {
+    // Headway-based template trips are counted once for every trip instance they describe.
+    let expanded_trips = gtfs.expand_frequencies();
+    let mut template_stop_times_num: HashMap<&str, usize> = HashMap::new();
+    for trip in &expanded_trips {
+        template_stop_times_num.insert(trip.trip_id, trip.stop_times.len());
+    }
+    let expanded_stop_times_num: usize = expanded_trips.iter().map(|t| t.stop_times.len()).sum();
+
     GtfsStats {
-        stop_times_num: gtfs.stop_times.len,
-        stops_num: gtfs.stops.len,
-        trips_num: gtfs.trips.len,
-        routes_num: gtfs.routes.len,
-        calendars_num: gtfs.calendars.len,
-        calendar_dates_num: gtfs.calendar_dates.len,
-        agencies_num: gtfs.agencies.len,
-        feed_infos_num: gtfs.feed_infos.len,
-        attributions_num: gtfs.attributions.len,
+        records_nums: gtfs
+            .file_lens()
+            .into_iter()
+            .map(|(file, len)| match file {
+                GtfsFile::StopTimes => (len + expanded_stop_times_num)
+                    .saturating_sub(template_stop_times_num.values().sum::<usize>()),
+                GtfsFile::Trips => {
+                    (len + expanded_trips.len()).saturating_sub(template_stop_times_num.len())
+                }
+                _ => len,
+            })
+            .collect(),
     }
 }
@@ -0,0 +1,197 @@
+use anyhow::Result;
+use colored::Colorize;
+use csvelo::CsvDialect;
+use gtfs_io::{
+    validate::{validate_gtfs, Finding, Severity},
+    Gtfs, GtfsBufferSlices, GtfsBuffers, GtfsBuffersMmap, GtfsFile, GtfsFilter,
+};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::{collections::HashMap, path::Path};
+
+use crate::gtfs_sources::{get_gtfs_sources, sort_gtfs_sources_by_size};
+
+#[derive(Debug, Serialize)]
+struct FeedReport {
+    path: String,
+    /// True if the feed could be loaded and has no findings with error severity.
+    valid: bool,
+    load_error: Option<String>,
+    errors_num: usize,
+    warnings_num: usize,
+    findings: Vec<FindingReport>,
+}
+
+#[derive(Debug, Serialize)]
+struct FindingReport {
+    severity: String,
+    kind: String,
+    file: &'static str,
+    /// Index of the record in the file, not counting the header.
+    row: Option<usize>,
+    /// Line of the record in the file, starting at 1 for the header.
+    line: Option<usize>,
+    column: Option<&'static str>,
+    message: String,
+}
+
+/// Validates all GTFS datasets found at the given path. Returns false if any of them is invalid.
+pub async fn gtfs_validate(
+    input_path: &Path,
+    deduplicate_archives: bool,
+    json: bool,
+) -> Result<bool> {
+    let mut gtfs_sources = get_gtfs_sources(input_path, deduplicate_archives);
+    if gtfs_sources.is_empty() {
+        if !json {
+            println!("No GTFS sources found.");
+        }
+        return Ok(true);
+    }
+    gtfs_sources = sort_gtfs_sources_by_size(gtfs_sources);
+
+    let counter = std::sync::atomic::AtomicUsize::new(0);
+
+    let mut reports: Vec<FeedReport> = (0..gtfs_sources.len())
+        .into_par_iter()
+        .map(|_| {
+            // Manually retrieve the work item, so that we can take the sorting done previously into account.
+            let current = counter.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            let p = &gtfs_sources[current];
+            let display_path = match p.strip_prefix(input_path) {
+                // The input path itself is the dataset.
+                Ok(relative) if relative.as_os_str().is_empty() => p.as_path(),
+                Ok(relative) => relative,
+                Err(_) => p.as_path(),
+            }
+            .to_string_lossy()
+            .to_string();
+            if !json {
+                println!("{: >3}/{} {}", current, gtfs_sources.len(), display_path);
+            }
+            match validate_source(p) {
+                Ok(findings) => FeedReport::new(display_path, findings),
+                Err(err) => FeedReport {
+                    path: display_path,
+                    valid: false,
+                    load_error: Some(err.to_string()),
+                    errors_num: 0,
+                    warnings_num: 0,
+                    findings: vec![],
+                },
+            }
+        })
+        .collect();
+    reports.sort_by(|a, b| a.path.cmp(&b.path));
+
+    if json {
+        // One report per line, so that the reports of the feeds can be processed separately.
+        for report in &reports {
+            println!("{}", serde_json::to_string(report)?);
+        }
+    } else {
+        print_summary(&reports);
+    }
+    Ok(reports.iter().all(|r| r.valid))
+}
+
+/// Validates the feed and returns its findings together with the line of their record.
+fn validate_source(p: &Path) -> Result<Vec<(Finding, Option<usize>)>> {
+    if p.is_dir() {
+        let buffers = unsafe { GtfsBuffersMmap::from_dir(p, &GtfsFilter::all()) };
+        validate_buffers(buffers.to_slices())
+    } else {
+        let buffers = unsafe { GtfsBuffers::from_zip_file_path_mmap(p, &GtfsFilter::all()) }?;
+        validate_buffers(buffers.to_slices())
+    }
+}
+
+fn validate_buffers(buffers: GtfsBufferSlices) -> Result<Vec<(Finding, Option<usize>)>> {
+    let gtfs = Gtfs::from_buffers(buffers)?;
+    let findings = validate_gtfs(&gtfs);
+    // Only the files with findings about records are scanned for line numbers.
+    let mut line_numbers: HashMap<GtfsFile, Vec<usize>> = HashMap::new();
+    Ok(findings
+        .into_iter()
+        .map(|finding| {
+            let line = finding.row.and_then(|row| {
+                let lines = line_numbers.entry(finding.file).or_insert_with(|| {
+                    buffers.get(finding.file).map_or(vec![], |buffer| {
+                        csvelo::record_line_numbers(buffer, &CsvDialect::default())
+                    })
+                });
+                lines.get(row).copied()
+            });
+            (finding, line)
+        })
+        .collect())
+}
+
+impl FeedReport {
+    fn new(path: String, findings: Vec<(Finding, Option<usize>)>) -> Self {
+        let errors_num = findings
+            .iter()
+            .filter(|(f, _)| f.severity == Severity::Error)
+            .count();
+        Self {
+            path,
+            valid: errors_num == 0,
+            load_error: None,
+            errors_num,
+            warnings_num: findings.len() - errors_num,
+            findings: findings
+                .into_iter()
+                .map(|(f, line)| FindingReport {
+                    severity: format!("{:?}", f.severity).to_lowercase(),
+                    kind: format!("{:?}", f.kind),
+                    file: f.file.file_name(),
+                    row: f.row,
+                    line,
+                    column: f.column,
+                    message: f.message,
+                })
+                .collect(),
+        }
+    }
+}
+
+fn print_summary(reports: &[FeedReport]) {
+    println!();
+    for report in reports {
+        if let Some(load_error) = &report.load_error {
+            println!("{} {}: {}", "FAILED".red().bold(), report.path, load_error);
+            continue;
+        }
+        let status = if !report.valid {
+            "INVALID".red().bold()
+        } else if report.warnings_num > 0 {
+            "WARN".yellow().bold()
+        } else {
+            "OK".green().bold()
+        };
+        println!(
+            "{} {} ({} errors, {} warnings)",
+            status, report.path, report.errors_num, report.warnings_num
+        );
+        for finding in &report.findings {
+            let severity = match finding.severity.as_str() {
+                "error" => finding.severity.red(),
+                _ => finding.severity.yellow(),
+            };
+            let location = match (finding.line, finding.row) {
+                (Some(line), _) => format!("{}:{}", finding.file, line),
+                (None, Some(row)) => format!("{} record {}", finding.file, row + 1),
+                (None, None) => finding.file.to_string(),
+            };
+            println!("  {: <7} {}: {}", severity, location, finding.message);
+        }
+    }
+
+    let invalid_num = reports.iter().filter(|r| !r.valid).count();
+    println!();
+    println!(
+        "{} of {} GTFS datasets are valid.",
+        reports.len() - invalid_num,
+        reports.len()
+    );
+}
@@ -2,7 +2,7 @@ use std::{path::PathBuf, time::Duration};
 
 use anyhow::Result;
 
-use crate::start_server;
+use crate::{routing::WalkingParams, start_server};
 
 pub struct ServeParams {
     pub host: String,
@@ -11,6 +11,7 @@ pub struct ServeParams {
     pub on_port_in_use: Option<Box<dyn FnOnce() + Send>>,
     pub allow_shutdown_from_frontend: bool,
     pub gtfs_datasets: Vec<PathBuf>,
+    pub walking_params: WalkingParams,
 }
 
 pub async fn serve(params: ServeParams) -> Result<()> {
@@ -31,6 +32,7 @@ pub async fn serve(params: ServeParams) -> Result<()> {
         params.on_start,
         params.allow_shutdown_from_frontend,
         params.gtfs_datasets.clone(),
+        params.walking_params,
     )
     .await?;
     Ok(())
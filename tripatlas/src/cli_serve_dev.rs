@@ -3,7 +3,7 @@ use std::path::{Path, PathBuf};
 use anyhow::Result;
 use tokio::process::Command;
 
-use crate::start_server;
+use crate::{routing::WalkingParams, start_server};
 
 pub struct ServeDevParams {
     pub frontend_host: String,
@@ -11,6 +11,7 @@ pub struct ServeDevParams {
     pub api_host: String,
     pub api_port: Option<u16>,
     pub gtfs_datasets: Vec<PathBuf>,
+    pub walking_params: WalkingParams,
 }
 
 pub async fn serve_dev(params: &ServeDevParams) -> Result<()> {
@@ -50,6 +51,13 @@ pub async fn serve_dev(params: &ServeDevParams) -> Result<()> {
         ));
     }
 
-    start_server::start_server(api_listener, None, false, params.gtfs_datasets.clone()).await?;
+    start_server::start_server(
+        api_listener,
+        None,
+        false,
+        params.gtfs_datasets.clone(),
+        params.walking_params,
+    )
+    .await?;
     Ok(())
 }
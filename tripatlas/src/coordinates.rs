@@ -17,6 +17,7 @@ pub struct LatLonBounds {
 }
 
 #[derive(Debug, Clone, Copy)]
+#[allow(clippy::upper_case_acronyms)]
 pub struct XYZ {
     pub x: f32,
     pub y: f32,
@@ -31,7 +32,7 @@ impl LatLon {
         }
     }
 
-    pub fn to_xyz_km(&self) -> XYZ {
+    pub fn to_xyz_km(self) -> XYZ {
         let lat = to_radians(self.latitude);
         let lon = to_radians(self.longitude);
 
@@ -45,6 +46,16 @@ impl LatLon {
             z * APPROXIMATE_EARTH_RADIUS_IN_KM,
         )
     }
+    /// Distance along the surface of the earth using the haversine formula.
+    pub fn great_circle_distance_km(self, other: LatLon) -> f32 {
+        let lat1 = to_radians(self.latitude);
+        let lat2 = to_radians(other.latitude);
+        let half_delta_lat = (lat2 - lat1) / 2.0;
+        let half_delta_lon = to_radians(other.longitude - self.longitude) / 2.0;
+        let a =
+            half_delta_lat.sin().powi(2) + lat1.cos() * lat2.cos() * half_delta_lon.sin().powi(2);
+        2.0 * APPROXIMATE_EARTH_RADIUS_IN_KM * a.sqrt().min(1.0).asin()
+    }
 }
 
 impl XYZ {
@@ -52,7 +63,7 @@ impl XYZ {
         Self { x, y, z }
     }
     #[cfg(test)]
-    pub fn to_lat_lon(&self) -> LatLon {
+    pub fn to_lat_lon(self) -> LatLon {
         let x = self.x / APPROXIMATE_EARTH_RADIUS_IN_KM;
         let y = self.y / APPROXIMATE_EARTH_RADIUS_IN_KM;
         let z = self.z / APPROXIMATE_EARTH_RADIUS_IN_KM;
@@ -62,7 +73,6 @@ impl XYZ {
 
         LatLon::new(lat, lon)
     }
-    #[cfg(test)]
     pub fn dist_to(&self, other: &Self) -> f32 {
         let dx = self.x - other.x;
         let dy = self.y - other.y;
@@ -104,6 +114,7 @@ fn to_degrees(radians: f32) -> f32 {
 }
 
 #[cfg(test)]
+#[allow(clippy::excessive_precision)]
 mod tests {
     use rand::{Rng, SeedableRng};
 
@@ -119,6 +130,15 @@ mod tests {
         assert!(distance_m < 50.0);
     }
 
+    #[test]
+    fn test_great_circle_distance() {
+        let berlin = LatLon::new(52.5200, 13.4050);
+        let paris = LatLon::new(48.8566, 2.3522);
+        let distance_km = berlin.great_circle_distance_km(paris);
+        assert!((875.0..885.0).contains(&distance_km), "{}", distance_km);
+        assert_eq!(berlin.great_circle_distance_km(berlin), 0.0);
+    }
+
     #[test]
     fn test_bidirectional_conversion() {
         let a = LatLon::new(52.6374196, 13.2054151);
@@ -1,13 +1,55 @@
-use std::sync::OnceLock;
+use std::{
+    collections::{HashMap, HashSet},
+    hash::Hash,
+    sync::{Arc, OnceLock},
+};
 
-use gtfs_io::Gtfs;
+use gtfs_io::{Date, ExpandedStopTime, ExpandedTrip, Gtfs, InternedIds, ServiceCalendar, StopIdx};
+use parking_lot::Mutex;
 use rstar::{RTree, RTreeObject, AABB};
 
-use crate::coordinates::LatLon;
+use crate::{
+    coordinates::LatLon,
+    routing::{
+        compute_reachability, walking_transfers, Reachability, ReachabilityQuery, Time, Timetable,
+        TimetableSources, Transfer, WalkingParams,
+    },
+};
 
-pub struct GtfsDataset {
-    pub raw: Gtfs<'static>,
+/// Building a timetable is relatively expensive, so the ones of recently queried days are kept.
+const MAX_CACHED_TIMETABLES: usize = 8;
+
+/// Reachability tiles of the same start are requested together, so the result is kept for the
+/// other tiles.
+const MAX_CACHED_REACHABILITIES: usize = 32;
+
+/// A cached value that is built by the first request that needs it. Other requests for the same
+/// value wait for it instead of building it again.
+type CacheSlot<T> = Arc<OnceLock<Arc<T>>>;
+
+/// Day, start position (as bits of latitude and longitude), departure and maximum number of
+/// transfers of a reachability query.
+type ReachabilityKey = (Date, u32, u32, Time, usize);
+
+pub struct GtfsDataset<'a> {
+    pub raw: Gtfs<'a>,
     pub stops_tree: OnceLock<RTree<RTreeStop>>,
+    pub timetables: Mutex<HashMap<Date, CacheSlot<Timetable<'a>>>>,
+    pub reachabilities: Mutex<HashMap<ReachabilityKey, CacheSlot<Reachability>>>,
+    /// Used to connect nearby stops in timetables. It can't change afterwards, because the
+    /// footpaths are only computed once.
+    walking_params: WalkingParams,
+    pub footpaths: OnceLock<Vec<(StopIdx, Transfer)>>,
+    pub ids: OnceLock<InternedIds<'a>>,
+    pub expanded_trips: OnceLock<Vec<ExpandedTrip<'a>>>,
+    pub stop_rows: OnceLock<HashMap<&'a str, u32>>,
+    pub trip_rows: OnceLock<HashMap<&'a str, u32>>,
+    pub route_rows: OnceLock<HashMap<&'a str, u32>>,
+    pub child_stop_rows: OnceLock<HashMap<&'a str, Vec<u32>>>,
+    pub stop_time_rows: OnceLock<HashMap<&'a str, Vec<u32>>>,
+    pub last_stop_time_rows: OnceLock<HashSet<u32>>,
+    pub frequency_stop_times: OnceLock<HashMap<u32, Vec<ExpandedStopTime>>>,
+    pub service_calendar: OnceLock<ServiceCalendar<'a>>,
 }
 
 pub struct RTreeStop {
@@ -23,13 +65,171 @@ impl RTreeObject for RTreeStop {
     }
 }
 
-impl GtfsDataset {
+impl<'a> GtfsDataset<'a> {
+    pub fn new(raw: Gtfs<'a>, walking_params: WalkingParams) -> Self {
+        Self {
+            raw,
+            stops_tree: OnceLock::new(),
+            timetables: Mutex::new(HashMap::new()),
+            reachabilities: Mutex::new(HashMap::new()),
+            walking_params,
+            footpaths: OnceLock::new(),
+            ids: OnceLock::new(),
+            expanded_trips: OnceLock::new(),
+            stop_rows: OnceLock::new(),
+            trip_rows: OnceLock::new(),
+            route_rows: OnceLock::new(),
+            child_stop_rows: OnceLock::new(),
+            stop_time_rows: OnceLock::new(),
+            last_stop_time_rows: OnceLock::new(),
+            frequency_stop_times: OnceLock::new(),
+            service_calendar: OnceLock::new(),
+        }
+    }
+
+    pub fn walking_params(&self) -> &WalkingParams {
+        &self.walking_params
+    }
+
+    /// Row of the stop in stops.txt.
+    pub fn get_stop_row(&self, stop_id: &str) -> Option<usize> {
+        let rows = self.stop_rows.get_or_init(|| {
+            row_indices(
+                self.raw
+                    .stops
+                    .data
+                    .as_ref()
+                    .and_then(|s| s.stop_id.as_ref()),
+            )
+        });
+        rows.get(stop_id).map(|row| *row as usize)
+    }
+
+    /// Row of the trip in trips.txt.
+    pub fn get_trip_row(&self, trip_id: &str) -> Option<usize> {
+        let rows = self.trip_rows.get_or_init(|| {
+            row_indices(
+                self.raw
+                    .trips
+                    .data
+                    .as_ref()
+                    .and_then(|t| t.trip_id.as_ref()),
+            )
+        });
+        rows.get(trip_id).map(|row| *row as usize)
+    }
+
+    /// Row of the route in routes.txt.
+    pub fn get_route_row(&self, route_id: &str) -> Option<usize> {
+        let rows = self.route_rows.get_or_init(|| {
+            row_indices(
+                self.raw
+                    .routes
+                    .data
+                    .as_ref()
+                    .and_then(|r| r.route_id.as_ref()),
+            )
+        });
+        rows.get(route_id).map(|row| *row as usize)
+    }
+
+    /// Rows in stops.txt of the stops whose parent station is the given stop.
+    pub fn get_child_stop_rows(&self, stop_id: &str) -> &[u32] {
+        let rows = self.child_stop_rows.get_or_init(|| {
+            rows_by_id(
+                self.raw
+                    .stops
+                    .data
+                    .as_ref()
+                    .and_then(|s| s.parent_station.as_ref()),
+            )
+        });
+        rows.get(stop_id).map_or(&[], Vec::as_slice)
+    }
+
+    /// Rows in stop_times.txt of the stop times at the given stop.
+    pub fn get_stop_time_rows(&self, stop_id: &str) -> &[u32] {
+        let rows = self.stop_time_rows.get_or_init(|| {
+            rows_by_id(
+                self.raw
+                    .stop_times
+                    .data
+                    .as_ref()
+                    .and_then(|s| s.stop_id.as_ref()),
+            )
+        });
+        rows.get(stop_id).map_or(&[], Vec::as_slice)
+    }
+
+    /// Whether the row in stop_times.txt is the last stop of its trip.
+    pub fn is_last_stop_time(&self, stop_time_row: usize) -> bool {
+        let rows = self.last_stop_time_rows.get_or_init(|| {
+            let Some(stop_times) = self.raw.stop_times.data.as_ref() else {
+                return HashSet::new();
+            };
+            // Stop sequence and row of the last stop time of every trip.
+            let mut last_stop_times: HashMap<&str, (u32, u32)> = HashMap::new();
+            for (row, trip_id) in stop_times.trip_id.iter().flatten().enumerate() {
+                let stop_sequence = stop_times
+                    .stop_sequence
+                    .as_ref()
+                    .map_or(row as u32, |stop_sequences| stop_sequences[row]);
+                let last = last_stop_times
+                    .entry(trip_id)
+                    .or_insert((stop_sequence, row as u32));
+                if stop_sequence >= last.0 {
+                    *last = (stop_sequence, row as u32);
+                }
+            }
+            last_stop_times.into_values().map(|(_, row)| row).collect()
+        });
+        rows.contains(&(stop_time_row as u32))
+    }
+
+    /// Times of all instances of a frequency-based trip at the given row of its template trip
+    /// in stop_times.txt. Returns `None` if the trip is not frequency-based.
+    pub fn get_frequency_stop_times(&self, stop_time_row: usize) -> Option<&[ExpandedStopTime]> {
+        let stop_times = self.frequency_stop_times.get_or_init(|| {
+            let mut stop_times: HashMap<u32, Vec<ExpandedStopTime>> = HashMap::new();
+            for trip in self.get_expanded_trips() {
+                for stop_time in &trip.stop_times {
+                    stop_times
+                        .entry(stop_time.stop_time_i as u32)
+                        .or_default()
+                        .push(*stop_time);
+                }
+            }
+            stop_times
+        });
+        stop_times.get(&(stop_time_row as u32)).map(Vec::as_slice)
+    }
+
+    pub fn get_service_calendar(&self) -> &ServiceCalendar<'a> {
+        self.service_calendar
+            .get_or_init(|| self.raw.service_calendar())
+    }
+
+    /// The interned ids of the feed. Their indices are the same in the timetables of all days.
+    pub fn get_ids(&self) -> &InternedIds<'a> {
+        self.ids.get_or_init(|| self.raw.intern_ids())
+    }
+
+    /// The instances of the trips from frequencies.txt.
+    pub fn get_expanded_trips(&self) -> &[ExpandedTrip<'a>] {
+        self.expanded_trips
+            .get_or_init(|| self.raw.expand_frequencies())
+    }
+
+    /// Contains all stops that have a position. The tree is empty if there are no stops.
     pub fn get_stops_tree(&self) -> &RTree<RTreeStop> {
         self.stops_tree.get_or_init(|| {
-            let stops = self.raw.stops.data.as_ref().unwrap();
-            let lats = stops.stop_lat.as_ref().unwrap();
-            let lons = stops.stop_lon.as_ref().unwrap();
             let mut elements = vec![];
+            let Some(stops) = self.raw.stops.data.as_ref() else {
+                return RTree::new();
+            };
+            let (Some(lats), Some(lons)) = (&stops.stop_lat, &stops.stop_lon) else {
+                return RTree::new();
+            };
             for (i, (lat, lon)) in lats.iter().zip(lons.iter()).enumerate() {
                 let (Some(lat), Some(lon)) = (lat.0, lon.0) else {
                     continue;
@@ -43,4 +243,82 @@ impl GtfsDataset {
             RTree::bulk_load(elements)
         })
     }
+
+    /// The timetable of the given day, which is built on first use. Besides the transfers from
+    /// transfers.txt, it contains footpaths between all stops within walking distance.
+    pub fn get_timetable(&self, date: Date) -> Arc<Timetable<'a>> {
+        get_or_build(&self.timetables, date, MAX_CACHED_TIMETABLES, || {
+            let sources = TimetableSources {
+                gtfs: &self.raw,
+                ids: self.get_ids(),
+                calendar: self.get_service_calendar(),
+                expanded_trips: self.get_expanded_trips(),
+            };
+            let mut timetable = Timetable::new(&sources, date);
+            let footpaths = self.get_footpaths();
+            timetable.add_transfers(footpaths.iter().copied());
+            timetable
+        })
+    }
+
+    /// Footpaths between nearby stops, which are computed on first use. The timetables of all
+    /// days use the stop indices of [`GtfsDataset::get_ids`], so the footpaths apply to all.
+    pub fn get_footpaths(&self) -> &[(StopIdx, Transfer)] {
+        self.footpaths
+            .get_or_init(|| walking_transfers(self, &self.get_ids().stops, &self.walking_params))
+    }
+
+    /// The stops that can be reached with the timetable of the given day, which is computed on
+    /// first use.
+    pub fn get_reachability(&self, date: Date, query: &ReachabilityQuery) -> Arc<Reachability> {
+        let key = (
+            date,
+            query.start.latitude.to_bits(),
+            query.start.longitude.to_bits(),
+            query.departure,
+            query.max_transfers,
+        );
+        get_or_build(&self.reachabilities, key, MAX_CACHED_REACHABILITIES, || {
+            compute_reachability(self, &self.get_timetable(date), query)
+        })
+    }
+}
+
+/// Gets the cached value of the key or builds it. The lock is only held to find the slot of the
+/// key, so that the value is built without blocking requests for other keys. The cache is
+/// cleared when it is full.
+fn get_or_build<K: Eq + Hash, T>(
+    cache: &Mutex<HashMap<K, CacheSlot<T>>>,
+    key: K,
+    max_len: usize,
+    build: impl FnOnce() -> T,
+) -> Arc<T> {
+    let slot = {
+        let mut cache = cache.lock();
+        if !cache.contains_key(&key) && cache.len() >= max_len {
+            cache.clear();
+        }
+        cache.entry(key).or_default().clone()
+    };
+    slot.get_or_init(|| Arc::new(build())).clone()
+}
+
+/// Maps every id to the first row that it appears in.
+fn row_indices<'a>(ids: Option<&Vec<&'a str>>) -> HashMap<&'a str, u32> {
+    let mut rows = HashMap::new();
+    for (row, id) in ids.into_iter().flatten().enumerate() {
+        rows.entry(*id).or_insert(row as u32);
+    }
+    rows
+}
+
+/// Maps every id to all rows that it appears in. Empty ids are left out.
+fn rows_by_id<'a>(ids: Option<&Vec<&'a str>>) -> HashMap<&'a str, Vec<u32>> {
+    let mut rows: HashMap<&'a str, Vec<u32>> = HashMap::new();
+    for (row, id) in ids.into_iter().flatten().enumerate() {
+        if !id.is_empty() {
+            rows.entry(*id).or_default().push(row as u32);
+        }
+    }
+    rows
 }
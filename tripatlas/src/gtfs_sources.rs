@@ -24,15 +24,13 @@ pub fn get_gtfs_sources(input_path: &Path, deduplicate_archives: bool) -> Vec<Pa
                 let path = entry.path();
                 let file_type = entry.file_type();
                 if file_type.is_dir() {
-                    if maybe_gtfs_dir(&path) {
+                    if maybe_gtfs_dir(path) {
                         gtfs_folders.push(path.to_owned());
                         continue;
                     }
-                } else if file_type.is_file() {
-                    if maybe_gtfs_zip_file(path) {
-                        gtfs_zip_files.push(path.to_owned());
-                        continue;
-                    }
+                } else if file_type.is_file() && maybe_gtfs_zip_file(path) {
+                    gtfs_zip_files.push(path.to_owned());
+                    continue;
                 }
             }
         }
@@ -43,25 +41,22 @@ pub fn get_gtfs_sources(input_path: &Path, deduplicate_archives: bool) -> Vec<Pa
     if deduplicate_archives {
         let gtfs_folder_names: HashSet<&OsStr> =
             gtfs_folders.iter().filter_map(|p| p.file_name()).collect();
-        gtfs_zip_files = gtfs_zip_files
-            .into_iter()
-            .filter(|p| match p.with_extension("").file_name() {
-                Some(name) => {
-                    if gtfs_folder_names.contains(name) {
-                        println!("Ignored potential duplicate: {:?}", p);
-                        false
-                    } else {
-                        true
-                    }
+        gtfs_zip_files.retain(|p| match p.with_extension("").file_name() {
+            Some(name) => {
+                if gtfs_folder_names.contains(name) {
+                    println!("Ignored potential duplicate: {:?}", p);
+                    false
+                } else {
+                    true
                 }
-                None => false,
-            })
-            .collect();
+            }
+            None => false,
+        });
     }
 
     gtfs_folders
         .into_iter()
-        .chain(gtfs_zip_files.into_iter())
+        .chain(gtfs_zip_files)
         .map(|p| p.to_owned())
         .collect()
 }
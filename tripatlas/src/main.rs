@@ -1,8 +1,11 @@
 use anyhow::Result;
 
 mod cli;
+mod cli_gtfs_extract;
 mod cli_gtfs_merge;
+mod cli_gtfs_plan;
 mod cli_gtfs_stats;
+mod cli_gtfs_validate;
 mod cli_mobility_database;
 mod cli_serve;
 mod cli_serve_dev;
@@ -11,6 +14,7 @@ mod gtfs_dataset;
 mod gtfs_sources;
 mod projection;
 mod routes;
+mod routing;
 mod start_server;
 mod util;
 
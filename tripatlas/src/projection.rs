@@ -38,7 +38,7 @@ impl WebMercatorTile {
         LatLon::new(self.bottom_latitude(), self.right_longitude())
     }
 
-    pub fn to_bounds(&self) -> LatLonBounds {
+    pub fn to_bounds(self) -> LatLonBounds {
         LatLonBounds::from_corners(self.top_left_lat_lon(), self.bottom_right_lat_lon())
     }
 }
@@ -47,17 +47,18 @@ fn tile_x_to_longitude(x: u32, zoom: u8) -> f32 {
     let x = x as f32;
     let tile_num = 2_u64.pow(zoom as u32);
     let tile_num_f = tile_num as f32;
-    return x / tile_num_f * 360.0 - 180.0;
+    x / tile_num_f * 360.0 - 180.0
 }
 
 fn tile_y_to_latitude(y: u32, zoom: u8) -> f32 {
     let y = y as f32;
     let tile_num = 2_u64.pow(zoom as u32);
     let tile_num_f = tile_num as f32;
-    return ((1.0 - 2.0 * y / tile_num_f) * PI).sinh().atan() * 180.0 / PI;
+    ((1.0 - 2.0 * y / tile_num_f) * PI).sinh().atan() * 180.0 / PI
 }
 
 #[cfg(test)]
+#[allow(clippy::excessive_precision)]
 mod tests {
     use super::*;
 
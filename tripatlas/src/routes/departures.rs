@@ -0,0 +1,219 @@
+use std::collections::HashSet;
+
+use actix_web::{web, HttpResponse, Responder};
+use gtfs_io::{PickupType, ServiceDayTime};
+
+use crate::{
+    gtfs_dataset::GtfsDataset,
+    routing::{format_time, parse_time, Time, SECONDS_PER_DAY},
+    start_server::State,
+    util::{cell, format_color, parse_date},
+};
+
+/// Upper bound for the `limit` parameter.
+const MAX_DEPARTURES: usize = 100;
+
+fn default_limit() -> usize {
+    10
+}
+
+#[derive(serde::Deserialize)]
+struct DeparturesParams {
+    /// Day of the departures (YYYYMMDD).
+    date: String,
+    /// Earliest departure (HH:MM or HH:MM:SS).
+    time: String,
+    #[serde(default = "default_limit")]
+    limit: usize,
+}
+
+#[derive(serde::Serialize)]
+struct DepartureBoard<'a> {
+    stop_id: &'a str,
+    name: Option<&'a str>,
+    departures: Vec<Departure<'a>>,
+}
+
+#[derive(serde::Serialize)]
+struct Departure<'a> {
+    /// Relative to the start of the requested day, so it can exceed 24:00:00.
+    time: String,
+    /// The stop itself or one of its child platforms.
+    stop_id: &'a str,
+    platform_code: Option<&'a str>,
+    trip_id: &'a str,
+    route_name: Option<&'a str>,
+    /// As `#RRGGBB`.
+    route_color: Option<String>,
+    headsign: Option<&'a str>,
+    pickup_type: &'static str,
+    #[serde(skip)]
+    seconds: Time,
+}
+
+/// The next departures from a stop and its child platforms. The dataset is the index of the
+/// dataset in the order they were loaded. Frequency-based trips are expanded into their
+/// instances. Stop times without pickup and the last stop times of trips are left out.
+#[actix_web::get("/api/stops/{dataset}/{stop_id}/departures")]
+async fn route_api_departures(
+    state: web::Data<State>,
+    path: web::Path<(usize, String)>,
+    params: web::Query<DeparturesParams>,
+) -> impl Responder {
+    state.metrics.departure_requests_total.inc();
+    let (dataset_i, stop_id) = path.into_inner();
+
+    let Ok(date) = parse_date(&params.date) else {
+        return HttpResponse::BadRequest().body("Expected date as YYYYMMDD.");
+    };
+    let Some(earliest_departure) = parse_time(&params.time) else {
+        return HttpResponse::BadRequest().body("Expected time as HH:MM or HH:MM:SS.");
+    };
+    let Some(dataset) = state.datasets.get(dataset_i) else {
+        return HttpResponse::NotFound().body("Unknown dataset.");
+    };
+    let Some(stop_row) = dataset.get_stop_row(&stop_id) else {
+        return HttpResponse::NotFound().body("Unknown stop.");
+    };
+
+    let stops = dataset.raw.stops.data.as_ref().unwrap();
+    let mut stop_rows = vec![stop_row];
+    stop_rows.extend(
+        dataset
+            .get_child_stop_rows(&stop_id)
+            .iter()
+            .map(|row| *row as usize),
+    );
+
+    let calendar = dataset.get_service_calendar();
+    // Trips of the previous day that run past midnight are included.
+    let days = [(date, 0), (date.add_days(-1), -SECONDS_PER_DAY)]
+        .map(|(day, shift)| (calendar.active_services(&day), shift));
+    let mut departures = vec![];
+    for stop_row in stop_rows {
+        let platform_id = stops.stop_id.as_ref().unwrap()[stop_row];
+        for stop_time_row in dataset.get_stop_time_rows(platform_id) {
+            departures.extend(departures_at(
+                dataset,
+                *stop_time_row as usize,
+                &days,
+                earliest_departure,
+                stop_row,
+            ));
+        }
+    }
+    departures.sort_by_key(|departure| departure.seconds);
+    departures.truncate(params.limit.min(MAX_DEPARTURES));
+
+    HttpResponse::Ok()
+        .content_type("application/json")
+        .json(DepartureBoard {
+            stop_id: stops.stop_id.as_ref().unwrap()[stop_row],
+            name: cell(stops.stop_name.as_ref(), Some(stop_row)).map(|name| name.as_ref()),
+            departures,
+        })
+}
+
+/// The departures of a stop time on the days on which its trip runs. Every day comes with the
+/// services that are active on it and the shift of its times relative to the requested day.
+fn departures_at<'a>(
+    dataset: &'a GtfsDataset,
+    stop_time_row: usize,
+    days: &[(HashSet<&str>, Time)],
+    earliest_departure: Time,
+    stop_row: usize,
+) -> Vec<Departure<'a>> {
+    let stops = dataset.raw.stops.data.as_ref();
+    let stop_times = dataset.raw.stop_times.data.as_ref();
+    let trips = dataset.raw.trips.data.as_ref();
+    let routes = dataset.raw.routes.data.as_ref();
+    let stop_time_row = Some(stop_time_row);
+
+    let pickup_type = cell(
+        stop_times.and_then(|s| s.pickup_type.as_ref()),
+        stop_time_row,
+    );
+    if matches!(pickup_type, Some(PickupType::NotAvailable))
+        || stop_time_row.is_some_and(|row| dataset.is_last_stop_time(row))
+    {
+        return vec![];
+    }
+    let times: Vec<ServiceDayTime> =
+        match stop_time_row.and_then(|row| dataset.get_frequency_stop_times(row)) {
+            Some(instances) => instances
+                .iter()
+                .filter_map(|instance| instance.departure_time.or(instance.arrival_time))
+                .collect(),
+            None => cell(
+                stop_times.and_then(|s| s.departure_time.as_ref()),
+                stop_time_row,
+            )
+            .and_then(|time| time.0)
+            .or_else(|| {
+                cell(
+                    stop_times.and_then(|s| s.arrival_time.as_ref()),
+                    stop_time_row,
+                )
+                .and_then(|time| time.0)
+            })
+            .into_iter()
+            .collect(),
+        };
+    let Some(trip_id) = cell(stop_times.and_then(|s| s.trip_id.as_ref()), stop_time_row) else {
+        return vec![];
+    };
+    let trip_row = dataset.get_trip_row(trip_id);
+    let Some(service_id) = cell(trips.and_then(|t| t.service_id.as_ref()), trip_row) else {
+        return vec![];
+    };
+
+    let mut departures = vec![];
+    for ((active_services, shift), time) in days
+        .iter()
+        .flat_map(|day| times.iter().map(move |time| (day, time)))
+    {
+        let seconds = time.seconds() as Time + shift;
+        if seconds < earliest_departure || !active_services.contains(service_id) {
+            continue;
+        }
+        let route_row = cell(trips.and_then(|t| t.route_id.as_ref()), trip_row)
+            .and_then(|route_id| dataset.get_route_row(route_id));
+        let short_name = cell(routes.and_then(|r| r.route_short_name.as_ref()), route_row)
+            .filter(|name| !name.is_empty());
+        let long_name = cell(routes.and_then(|r| r.route_long_name.as_ref()), route_row);
+        let stop_headsign = cell(
+            stop_times.and_then(|s| s.stop_headsign.as_ref()),
+            stop_time_row,
+        )
+        .filter(|headsign| !headsign.is_empty());
+        let trip_headsign = cell(trips.and_then(|t| t.trip_headsign.as_ref()), trip_row);
+        departures.push(Departure {
+            time: format_time(seconds),
+            stop_id: stops.unwrap().stop_id.as_ref().unwrap()[stop_row],
+            platform_code: cell(stops.and_then(|s| s.platform_code.as_ref()), Some(stop_row))
+                .copied()
+                .filter(|code| !code.is_empty()),
+            trip_id,
+            route_name: short_name.or(long_name).map(|name| name.as_ref()),
+            route_color: cell(routes.and_then(|r| r.route_color.as_ref()), route_row)
+                .and_then(|color| color.0.as_ref())
+                .map(format_color),
+            headsign: stop_headsign
+                .or(trip_headsign)
+                .map(|headsign| headsign.as_ref()),
+            pickup_type: pickup_type_name(pickup_type),
+            seconds,
+        });
+    }
+    departures
+}
+
+fn pickup_type_name(pickup_type: Option<&PickupType>) -> &'static str {
+    match pickup_type {
+        None | Some(PickupType::Regular) => "regular",
+        Some(PickupType::NotAvailable) => "not_available",
+        Some(PickupType::MustPhone) => "must_phone",
+        Some(PickupType::MustCoordinateWithDriver) => "must_coordinate_with_driver",
+        Some(PickupType::Unknown) => "unknown",
+    }
+}
@@ -0,0 +1,283 @@
+use actix_web::{web, HttpResponse, Responder};
+use gtfs_io::StopIdx;
+
+use crate::{
+    coordinates::LatLon,
+    gtfs_dataset::GtfsDataset,
+    routing::{
+        format_time, parse_time, resolve_place, run_raptor, Journey, JourneyLeg, Place, Time,
+        Timetable,
+    },
+    start_server::{get_timetables, State},
+    util::{cell, format_color, parse_date},
+};
+
+fn default_max_transfers() -> usize {
+    4
+}
+
+#[derive(serde::Deserialize)]
+struct JourneyParams {
+    /// Stop id or position (`lat,lon`) of the start.
+    from: String,
+    /// Stop id or position (`lat,lon`) of the destination.
+    to: String,
+    /// Day of the departure (YYYYMMDD).
+    date: String,
+    /// Earliest departure (HH:MM or HH:MM:SS).
+    time: String,
+    #[serde(default = "default_max_transfers")]
+    max_transfers: usize,
+}
+
+#[derive(serde::Serialize)]
+struct JourneyJson<'a> {
+    departure: String,
+    arrival: String,
+    transfers: usize,
+    legs: Vec<LegJson<'a>>,
+}
+
+/// A stop, or a position that is not a stop. The fields that are not known are `null`.
+#[derive(serde::Serialize)]
+struct PlaceJson<'a> {
+    stop_id: Option<&'a str>,
+    name: Option<&'a str>,
+    lat: Option<f32>,
+    lon: Option<f32>,
+}
+
+#[derive(serde::Serialize)]
+struct IntermediateStopJson<'a> {
+    #[serde(flatten)]
+    stop: PlaceJson<'a>,
+    arrival: String,
+    departure: String,
+}
+
+#[derive(serde::Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum LegJson<'a> {
+    Transit {
+        trip_id: &'a str,
+        route_short_name: Option<&'a str>,
+        /// As `#RRGGBB`.
+        route_color: Option<String>,
+        headsign: Option<&'a str>,
+        from: PlaceJson<'a>,
+        to: PlaceJson<'a>,
+        departure: String,
+        arrival: String,
+        intermediate_stops: Vec<IntermediateStopJson<'a>>,
+    },
+    Walk {
+        from: PlaceJson<'a>,
+        to: PlaceJson<'a>,
+        departure: String,
+        arrival: String,
+    },
+}
+
+/// Journeys between two stops or positions that depart at or after the given time. All loaded
+/// datasets are searched separately, and every dataset contributes its Pareto-optimal journeys
+/// with respect to arrival time and number of transfers. Journeys don't change between datasets,
+/// so feeds that should be connected have to be merged with `gtfs-merge` first.
+#[actix_web::get("/api/journeys")]
+async fn route_api_journeys(
+    state: web::Data<State>,
+    params: web::Query<JourneyParams>,
+) -> impl Responder {
+    state.metrics.journey_requests_total.inc();
+    let _timer = state.metrics.journey_request_duration_seconds.start_timer();
+    let response = find_journeys(&state, &params).await;
+    if !response.status().is_success() {
+        state.metrics.journey_request_errors_total.inc();
+    }
+    response
+}
+
+async fn find_journeys(state: &web::Data<State>, params: &JourneyParams) -> HttpResponse {
+    let Ok(date) = parse_date(&params.date) else {
+        return HttpResponse::BadRequest().body("Expected date as YYYYMMDD.");
+    };
+    let Some(departure) = parse_time(&params.time) else {
+        return HttpResponse::BadRequest().body("Expected time as HH:MM or HH:MM:SS.");
+    };
+    let from = Place::parse(&params.from);
+    let to = Place::parse(&params.to);
+
+    let Ok(timetables) = get_timetables(state, date).await else {
+        return HttpResponse::InternalServerError().finish();
+    };
+
+    let mut found_places = false;
+    let mut journeys = vec![];
+    for (dataset, timetable) in state.datasets.iter().zip(timetables) {
+        let access = resolve_place(dataset, &timetable, &from);
+        let egress = resolve_place(dataset, &timetable, &to);
+        if access.is_empty() || egress.is_empty() {
+            continue;
+        }
+        found_places = true;
+        let sources: Vec<_> = access
+            .iter()
+            .map(|(stop, walk_duration)| (*stop, departure + walk_duration))
+            .collect();
+        let result = run_raptor(&timetable, &sources, params.max_transfers);
+        for journey in result.journeys_to(&timetable, &egress) {
+            let mut legs = journey_legs(dataset, &timetable, &journey);
+            if let Place::Position(position) = &from {
+                let walk_duration = walk_duration(&access, journey.from_stop);
+                legs.insert(
+                    0,
+                    LegJson::Walk {
+                        from: position_json(*position),
+                        to: stop_json(dataset, timetable.stops().id(journey.from_stop)),
+                        departure: format_time(journey.departure - walk_duration),
+                        arrival: format_time(journey.departure),
+                    },
+                );
+            }
+            if let Place::Position(position) = &to {
+                let walk_duration = walk_duration(&egress, journey.to_stop);
+                legs.push(LegJson::Walk {
+                    from: stop_json(dataset, timetable.stops().id(journey.to_stop)),
+                    to: position_json(*position),
+                    departure: format_time(journey.arrival),
+                    arrival: format_time(journey.arrival + walk_duration),
+                });
+            }
+            let (first_departure, last_arrival) = match (legs.first(), legs.last()) {
+                (Some(first), Some(last)) => (first.departure(), last.arrival()),
+                _ => (format_time(journey.departure), format_time(journey.arrival)),
+            };
+            journeys.push(JourneyJson {
+                departure: first_departure,
+                arrival: last_arrival,
+                transfers: journey.transfers_num(),
+                legs,
+            });
+        }
+    }
+    if !found_places {
+        return HttpResponse::NotFound().body("No stops found at the start or destination.");
+    }
+
+    HttpResponse::Ok()
+        .content_type("application/json")
+        .json(journeys)
+}
+
+fn walk_duration(stops: &[(StopIdx, Time)], stop: StopIdx) -> Time {
+    stops
+        .iter()
+        .find(|(other_stop, _)| *other_stop == stop)
+        .map_or(0, |(_, walk_duration)| *walk_duration)
+}
+
+fn journey_legs<'a>(
+    dataset: &'a GtfsDataset,
+    timetable: &Timetable<'a>,
+    journey: &Journey<'a>,
+) -> Vec<LegJson<'a>> {
+    let stop = |stop| stop_json(dataset, timetable.stops().id(stop));
+    journey
+        .legs
+        .iter()
+        .map(|leg| match leg {
+            JourneyLeg::Transit {
+                route_id,
+                trip_id,
+                from_stop,
+                to_stop,
+                departure,
+                arrival,
+                intermediate_stops,
+            } => {
+                let routes = dataset.raw.routes.data.as_ref();
+                let route_row = dataset.get_route_row(route_id);
+                let trip_row = dataset.get_trip_row(trip_id);
+                LegJson::Transit {
+                    trip_id,
+                    route_short_name: cell(
+                        routes.and_then(|r| r.route_short_name.as_ref()),
+                        route_row,
+                    )
+                    .map(|name| name.as_ref()),
+                    route_color: cell(routes.and_then(|r| r.route_color.as_ref()), route_row)
+                        .and_then(|color| color.0.as_ref())
+                        .map(format_color),
+                    headsign: cell(
+                        dataset
+                            .raw
+                            .trips
+                            .data
+                            .as_ref()
+                            .and_then(|t| t.trip_headsign.as_ref()),
+                        trip_row,
+                    )
+                    .map(|headsign| headsign.as_ref()),
+                    from: stop(*from_stop),
+                    to: stop(*to_stop),
+                    departure: format_time(*departure),
+                    arrival: format_time(*arrival),
+                    intermediate_stops: intermediate_stops
+                        .iter()
+                        .map(|(intermediate_stop, stop_time)| IntermediateStopJson {
+                            stop: stop(*intermediate_stop),
+                            arrival: format_time(stop_time.arrival),
+                            departure: format_time(stop_time.departure),
+                        })
+                        .collect(),
+                }
+            }
+            JourneyLeg::Walk {
+                from_stop,
+                to_stop,
+                departure,
+                arrival,
+            } => LegJson::Walk {
+                from: stop(*from_stop),
+                to: stop(*to_stop),
+                departure: format_time(*departure),
+                arrival: format_time(*arrival),
+            },
+        })
+        .collect()
+}
+
+fn stop_json<'a>(dataset: &'a GtfsDataset, stop_id: &'a str) -> PlaceJson<'a> {
+    let stops = dataset.raw.stops.data.as_ref();
+    let row = dataset.get_stop_row(stop_id);
+    PlaceJson {
+        stop_id: Some(stop_id),
+        name: cell(stops.and_then(|s| s.stop_name.as_ref()), row).map(|name| name.as_ref()),
+        lat: cell(stops.and_then(|s| s.stop_lat.as_ref()), row).and_then(|lat| lat.0),
+        lon: cell(stops.and_then(|s| s.stop_lon.as_ref()), row).and_then(|lon| lon.0),
+    }
+}
+
+fn position_json<'a>(position: LatLon) -> PlaceJson<'a> {
+    PlaceJson {
+        stop_id: None,
+        name: None,
+        lat: Some(position.latitude),
+        lon: Some(position.longitude),
+    }
+}
+
+impl LegJson<'_> {
+    fn departure(&self) -> String {
+        match self {
+            LegJson::Transit { departure, .. } | LegJson::Walk { departure, .. } => {
+                departure.clone()
+            }
+        }
+    }
+
+    fn arrival(&self) -> String {
+        match self {
+            LegJson::Transit { arrival, .. } | LegJson::Walk { arrival, .. } => arrival.clone(),
+        }
+    }
+}
@@ -1,3 +1,6 @@
 pub mod api_basics;
+pub mod departures;
 pub mod frontend;
+pub mod journeys;
+pub mod reachability;
 pub mod stations;
@@ -0,0 +1,141 @@
+use actix_web::{web, HttpResponse, Responder};
+use rstar::AABB;
+
+use crate::{
+    coordinates::LatLon,
+    projection::WebMercatorTile,
+    routing::{parse_time, ReachabilityQuery},
+    start_server::State,
+    util::parse_date,
+};
+
+/// Colors of the travel time buckets, from short to long travel times.
+const BUCKET_COLORS: [&str; 6] = [
+    "#1a9850", "#91cf60", "#d9ef8b", "#fee08b", "#fc8d59", "#d73027",
+];
+
+fn default_bucket_minutes() -> u32 {
+    10
+}
+
+fn default_max_transfers() -> usize {
+    4
+}
+
+#[derive(serde::Deserialize)]
+struct ReachabilityParams {
+    lat: f32,
+    lon: f32,
+    /// Day of the departure (YYYYMMDD).
+    date: String,
+    /// Departure time (HH:MM or HH:MM:SS).
+    time: String,
+    #[serde(default = "default_bucket_minutes")]
+    bucket_minutes: u32,
+    #[serde(default = "default_max_transfers")]
+    max_transfers: usize,
+}
+
+#[derive(serde::Serialize)]
+struct ReachableStop {
+    lat: f32,
+    lon: f32,
+    minutes: u32,
+    bucket: u32,
+    color: &'static str,
+}
+
+/// Stops in the tile that can be reached from the start position, colored by travel time.
+/// Stops that take longer than the last bucket are left out. Every loaded dataset is searched
+/// separately and contributes its stops, like for journeys.
+#[actix_web::get("/api/reachability/{zoom}_{tile_x}_{tile_y}.json")]
+async fn route_api_reachability(
+    state: web::Data<State>,
+    path: web::Path<(u8, u32, u32)>,
+    params: web::Query<ReachabilityParams>,
+) -> impl Responder {
+    state.metrics.reachability_requests_total.inc();
+    let (zoom, tile_x, tile_y) = path.into_inner();
+    let tile_bounds = WebMercatorTile::new(zoom, tile_x, tile_y).to_bounds();
+
+    let Ok(date) = parse_date(&params.date) else {
+        return HttpResponse::BadRequest().body("Expected date as YYYYMMDD.");
+    };
+    let Some(departure) = parse_time(&params.time) else {
+        return HttpResponse::BadRequest().body("Expected time as HH:MM or HH:MM:SS.");
+    };
+    if params.bucket_minutes == 0 {
+        return HttpResponse::BadRequest().body("The bucket size has to be positive.");
+    }
+    if state.datasets.is_empty() {
+        return HttpResponse::NotFound().finish();
+    }
+
+    let query = ReachabilityQuery {
+        start: LatLon::new(params.lat, params.lon),
+        departure,
+        max_transfers: params.max_transfers,
+    };
+    // Building the timetables and searching them takes a while on first use.
+    let blocking_state = state.clone();
+    let Ok(results) = web::block(move || {
+        blocking_state
+            .datasets
+            .iter()
+            .map(|dataset| {
+                (
+                    dataset.get_timetable(date),
+                    dataset.get_reachability(date, &query),
+                )
+            })
+            .collect::<Vec<_>>()
+    })
+    .await
+    else {
+        return HttpResponse::InternalServerError().finish();
+    };
+
+    let mut reachable_stops = vec![];
+    for (dataset, (timetable, reachability)) in state.datasets.iter().zip(results) {
+        let Some(stop_ids) = dataset
+            .raw
+            .stops
+            .data
+            .as_ref()
+            .and_then(|stops| stops.stop_id.as_ref())
+        else {
+            continue;
+        };
+        for rtree_stop in dataset
+            .get_stops_tree()
+            .locate_in_envelope(&AABB::from_corners(
+                [tile_bounds.left, tile_bounds.top],
+                [tile_bounds.right, tile_bounds.bottom],
+            ))
+        {
+            let Some(travel_time) = timetable
+                .stops()
+                .get(stop_ids[rtree_stop.stop_i as usize])
+                .and_then(|stop| reachability.travel_time(stop))
+            else {
+                continue;
+            };
+            let minutes = travel_time as u32 / 60;
+            let bucket = minutes / params.bucket_minutes;
+            let Some(color) = BUCKET_COLORS.get(bucket as usize) else {
+                continue;
+            };
+            reachable_stops.push(ReachableStop {
+                lat: rtree_stop.position.latitude,
+                lon: rtree_stop.position.longitude,
+                minutes,
+                bucket,
+                color,
+            });
+        }
+    }
+
+    HttpResponse::Ok()
+        .content_type("application/json")
+        .json(reachable_stops)
+}
@@ -0,0 +1,14 @@
+//! Journey planning on the timetable of a GTFS dataset with the RAPTOR algorithm. See
+//! "Round-Based Public Transit Routing" by Delling, Pajor and Werneck.
+
+mod place;
+mod raptor;
+mod reachability;
+mod timetable;
+mod walking;
+
+pub use place::*;
+pub use raptor::*;
+pub use reachability::*;
+pub use timetable::*;
+pub use walking::*;
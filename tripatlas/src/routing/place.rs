@@ -0,0 +1,62 @@
+use gtfs_io::StopIdx;
+
+use super::{stops_within, Time, Timetable};
+use crate::{coordinates::LatLon, gtfs_dataset::GtfsDataset};
+
+/// Maximum distance that is walked between a position and the first or last stop of a journey.
+pub const MAX_ACCESS_DISTANCE_KM: f32 = 1.0;
+
+/// Where a journey starts or ends.
+#[derive(Debug, Clone)]
+pub enum Place {
+    /// A stop or station id. Stations include their child stops.
+    Stop(String),
+    Position(LatLon),
+}
+
+impl Place {
+    /// Parses `lat,lon` as a position and everything else as a stop id.
+    pub fn parse(text: &str) -> Self {
+        if let Some((lat, lon)) = text.split_once(',') {
+            if let (Ok(lat), Ok(lon)) = (lat.trim().parse(), lon.trim().parse()) {
+                return Place::Position(LatLon::new(lat, lon));
+            }
+        }
+        Place::Stop(text.to_string())
+    }
+}
+
+/// Stops at which a journey from or to the place can start or end, together with the time it
+/// takes to walk between the place and the stop. Positions are connected to all stops within
+/// [`MAX_ACCESS_DISTANCE_KM`] using the walking speed of the dataset.
+pub fn resolve_place(
+    dataset: &GtfsDataset,
+    timetable: &Timetable,
+    place: &Place,
+) -> Vec<(StopIdx, Time)> {
+    match place {
+        Place::Stop(stop_id) => timetable
+            .stop_with_children(stop_id)
+            .into_iter()
+            .map(|stop| (stop, 0))
+            .collect(),
+        Place::Position(position) => {
+            let Some(stop_ids) = dataset
+                .raw
+                .stops
+                .data
+                .as_ref()
+                .and_then(|stops| stops.stop_id.as_ref())
+            else {
+                return vec![];
+            };
+            stops_within(dataset, *position, MAX_ACCESS_DISTANCE_KM)
+                .into_iter()
+                .filter_map(|(stop_i, distance_km)| {
+                    let stop = timetable.stops().get(stop_ids[stop_i as usize])?;
+                    Some((stop, dataset.walking_params().duration(distance_km)))
+                })
+                .collect()
+        }
+    }
+}
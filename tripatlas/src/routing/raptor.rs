@@ -0,0 +1,635 @@
+use anyhow::{bail, Result};
+use gtfs_io::StopIdx;
+
+use super::{StopTime, Time, Timetable};
+
+/// Arrival time at stops that have not been reached.
+pub const UNREACHED: Time = Time::MAX;
+
+/// Finds journeys from one stop to another.
+#[derive(Debug, Clone)]
+pub struct JourneyQuery<'q> {
+    pub from_stop_id: &'q str,
+    pub to_stop_id: &'q str,
+    /// Earliest departure at the origin stop.
+    pub departure: Time,
+    /// Changes between trips. Walking between stops does not count as a change.
+    pub max_transfers: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Journey<'a> {
+    /// Stop at which the journey starts.
+    pub from_stop: StopIdx,
+    /// Stop at which the journey ends.
+    pub to_stop: StopIdx,
+    pub departure: Time,
+    pub arrival: Time,
+    pub legs: Vec<JourneyLeg<'a>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JourneyLeg<'a> {
+    Transit {
+        route_id: &'a str,
+        trip_id: &'a str,
+        from_stop: StopIdx,
+        to_stop: StopIdx,
+        departure: Time,
+        arrival: Time,
+        /// Stops between boarding and alighting.
+        intermediate_stops: Vec<(StopIdx, StopTime)>,
+    },
+    Walk {
+        from_stop: StopIdx,
+        to_stop: StopIdx,
+        departure: Time,
+        arrival: Time,
+    },
+}
+
+/// Earliest arrival times at all stops for a growing number of trips. Round `k` contains the
+/// earliest arrivals when using at most `k` trips.
+pub struct RaptorResult {
+    rounds: Vec<Round>,
+}
+
+#[derive(Clone)]
+struct Round {
+    arrivals: Vec<Time>,
+    /// How the stop was reached if its arrival time improved in this round.
+    parents: Vec<Option<Parent>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Parent {
+    Source,
+    Trip {
+        pattern_i: u32,
+        trip_i: u32,
+        board_position: u32,
+        alight_position: u32,
+    },
+    Walk {
+        from_stop: StopIdx,
+        duration: Time,
+    },
+}
+
+/// Answers an earliest-arrival query between two stops. If a stop is a station, all of its
+/// child stops are used as well.
+///
+/// The result contains the Pareto-optimal journeys regarding arrival time and number of
+/// transfers, ordered by the number of transfers. The last one arrives earliest.
+pub fn plan_journeys<'a>(
+    timetable: &Timetable<'a>,
+    query: &JourneyQuery,
+) -> Result<Vec<Journey<'a>>> {
+    let from_stops = timetable.stop_with_children(query.from_stop_id);
+    if from_stops.is_empty() {
+        bail!("Unknown stop: {}", query.from_stop_id);
+    }
+    let to_stops = timetable.stop_with_children(query.to_stop_id);
+    if to_stops.is_empty() {
+        bail!("Unknown stop: {}", query.to_stop_id);
+    }
+    let sources: Vec<_> = from_stops
+        .iter()
+        .map(|stop| (*stop, query.departure))
+        .collect();
+    let targets: Vec<_> = to_stops.iter().map(|stop| (*stop, 0)).collect();
+    let result = run_raptor(timetable, &sources, query.max_transfers);
+    Ok(result.journeys_to(timetable, &targets))
+}
+
+/// Computes the earliest arrival at every stop when starting at the sources at the given times.
+pub fn run_raptor(
+    timetable: &Timetable,
+    sources: &[(StopIdx, Time)],
+    max_transfers: usize,
+) -> RaptorResult {
+    let stops_num = timetable.stops().len();
+    let mut best_arrivals = vec![UNREACHED; stops_num];
+    let mut round = Round {
+        arrivals: vec![UNREACHED; stops_num],
+        parents: vec![None; stops_num],
+    };
+    let mut marked = vec![false; stops_num];
+    for (stop, time) in sources {
+        let stop_i = usize::from(*stop);
+        if *time < round.arrivals[stop_i] {
+            round.arrivals[stop_i] = *time;
+            round.parents[stop_i] = Some(Parent::Source);
+            best_arrivals[stop_i] = *time;
+            marked[stop_i] = true;
+        }
+    }
+    relax_transfers(timetable, &mut round, &mut best_arrivals, &mut marked);
+    let mut rounds = vec![round];
+
+    for _ in 0..=max_transfers {
+        let previous = rounds.last().unwrap();
+        let mut round = Round {
+            arrivals: previous.arrivals.clone(),
+            parents: vec![None; stops_num],
+        };
+
+        // Every pattern only has to be scanned from the first marked stop on.
+        let mut pattern_starts = vec![u32::MAX; timetable.patterns().len()];
+        for (stop_i, is_marked) in marked.iter_mut().enumerate() {
+            if !std::mem::take(is_marked) {
+                continue;
+            }
+            for (pattern_i, position) in timetable.stop_patterns(StopIdx::from(stop_i)) {
+                let start = &mut pattern_starts[*pattern_i as usize];
+                *start = (*start).min(*position);
+            }
+        }
+
+        for (pattern_i, start) in pattern_starts.iter().enumerate() {
+            if *start == u32::MAX {
+                continue;
+            }
+            let stops = timetable.pattern_stops(pattern_i);
+            let trips_num = timetable.trips_num(pattern_i);
+            // The trip that is currently ridden and where it was boarded.
+            let mut current: Option<(usize, usize)> = None;
+            for (position, stop) in stops.iter().enumerate().skip(*start as usize) {
+                let stop_i = usize::from(stop.stop);
+                if let Some((trip_i, board_position)) = current {
+                    let arrival = timetable.trip_stop_times(pattern_i, trip_i)[position].arrival;
+                    if stop.can_alight && arrival < best_arrivals[stop_i] {
+                        round.arrivals[stop_i] = arrival;
+                        round.parents[stop_i] = Some(Parent::Trip {
+                            pattern_i: pattern_i as u32,
+                            trip_i: trip_i as u32,
+                            board_position: board_position as u32,
+                            alight_position: position as u32,
+                        });
+                        best_arrivals[stop_i] = arrival;
+                        marked[stop_i] = true;
+                    }
+                }
+                let ready_time = previous.arrivals[stop_i];
+                if !stop.can_board || ready_time == UNREACHED {
+                    continue;
+                }
+                // Trips don't overtake each other, so only earlier trips can be better.
+                let search_end = current.map_or(trips_num, |(trip_i, _)| trip_i);
+                let first_possible =
+                    timetable.first_trip_departing(pattern_i, position, ready_time, search_end);
+                if let Some(trip_i) = first_possible {
+                    current = Some((trip_i, position));
+                }
+            }
+        }
+
+        relax_transfers(timetable, &mut round, &mut best_arrivals, &mut marked);
+        rounds.push(round);
+        if !marked.contains(&true) {
+            break;
+        }
+    }
+    RaptorResult { rounds }
+}
+
+/// Walks from all marked stops to their neighbors. The walked to stops are marked as well.
+fn relax_transfers(
+    timetable: &Timetable,
+    round: &mut Round,
+    best_arrivals: &mut [Time],
+    marked: &mut [bool],
+) {
+    let marked_stops: Vec<usize> = (0..marked.len()).filter(|stop_i| marked[*stop_i]).collect();
+    for from_stop_i in marked_stops {
+        let from_arrival = round.arrivals[from_stop_i];
+        for transfer in timetable.transfers(StopIdx::from(from_stop_i)) {
+            let to_stop_i = usize::from(transfer.to_stop);
+            let arrival = from_arrival.saturating_add(transfer.duration);
+            if arrival < best_arrivals[to_stop_i] {
+                round.arrivals[to_stop_i] = arrival;
+                round.parents[to_stop_i] = Some(Parent::Walk {
+                    from_stop: StopIdx::from(from_stop_i),
+                    duration: transfer.duration,
+                });
+                best_arrivals[to_stop_i] = arrival;
+                marked[to_stop_i] = true;
+            }
+        }
+    }
+}
+
+impl RaptorResult {
+    /// Earliest arrival at every stop with up to the maximum number of transfers. Unreached
+    /// stops have [`UNREACHED`] as arrival.
+    pub fn earliest_arrivals(&self) -> &[Time] {
+        &self.rounds.last().unwrap().arrivals
+    }
+
+    /// The Pareto-optimal journeys to any of the target stops. Every target comes with the
+    /// time it takes to get from the stop to the actual destination, which is taken into account
+    /// when comparing journeys but not included in their arrival time. See [`plan_journeys`].
+    pub fn journeys_to<'a>(
+        &self,
+        timetable: &Timetable<'a>,
+        targets: &[(StopIdx, Time)],
+    ) -> Vec<Journey<'a>> {
+        let mut journeys = vec![];
+        let mut best_arrival = UNREACHED;
+        for round_i in 0..self.rounds.len() {
+            let Some((arrival, target)) = targets
+                .iter()
+                .filter_map(|(stop, egress)| {
+                    let arrival = self.rounds[round_i].arrivals[usize::from(*stop)];
+                    (arrival != UNREACHED).then(|| (arrival + egress, *stop))
+                })
+                .min()
+            else {
+                continue;
+            };
+            if arrival < best_arrival {
+                best_arrival = arrival;
+                journeys.push(self.reconstruct_journey(timetable, round_i, target));
+            }
+        }
+        journeys
+    }
+
+    fn reconstruct_journey<'a>(
+        &self,
+        timetable: &Timetable<'a>,
+        mut round_i: usize,
+        target: StopIdx,
+    ) -> Journey<'a> {
+        let arrival = self.rounds[round_i].arrivals[usize::from(target)];
+        let mut legs = vec![];
+        let mut stop = target;
+        loop {
+            // The arrival may have been carried over from an earlier round.
+            while self.rounds[round_i].parents[usize::from(stop)].is_none() {
+                round_i -= 1;
+            }
+            match self.rounds[round_i].parents[usize::from(stop)].unwrap() {
+                Parent::Source => break,
+                Parent::Walk {
+                    from_stop,
+                    duration,
+                } => {
+                    let arrival = self.rounds[round_i].arrivals[usize::from(stop)];
+                    legs.push(JourneyLeg::Walk {
+                        from_stop,
+                        to_stop: stop,
+                        departure: arrival - duration,
+                        arrival,
+                    });
+                    stop = from_stop;
+                }
+                Parent::Trip {
+                    pattern_i,
+                    trip_i,
+                    board_position,
+                    alight_position,
+                } => {
+                    let (pattern_i, trip_i) = (pattern_i as usize, trip_i as usize);
+                    let stops = timetable.pattern_stops(pattern_i);
+                    let times = timetable.trip_stop_times(pattern_i, trip_i);
+                    let (board_position, alight_position) =
+                        (board_position as usize, alight_position as usize);
+                    let from_stop = stops[board_position].stop;
+                    legs.push(JourneyLeg::Transit {
+                        route_id: timetable.patterns()[pattern_i].route_id,
+                        trip_id: timetable.trip_id(pattern_i, trip_i),
+                        from_stop,
+                        to_stop: stop,
+                        departure: times[board_position].departure,
+                        arrival: times[alight_position].arrival,
+                        intermediate_stops: (board_position + 1..alight_position)
+                            .map(|position| (stops[position].stop, times[position]))
+                            .collect(),
+                    });
+                    stop = from_stop;
+                    round_i -= 1;
+                }
+            }
+        }
+        legs.reverse();
+        Journey {
+            from_stop: stop,
+            to_stop: target,
+            departure: legs.first().map_or(arrival, JourneyLeg::departure),
+            arrival,
+            legs,
+        }
+    }
+}
+
+impl JourneyLeg<'_> {
+    pub fn departure(&self) -> Time {
+        match self {
+            JourneyLeg::Transit { departure, .. } | JourneyLeg::Walk { departure, .. } => {
+                *departure
+            }
+        }
+    }
+}
+
+impl Journey<'_> {
+    /// Number of changes between trips.
+    pub fn transfers_num(&self) -> usize {
+        self.legs
+            .iter()
+            .filter(|leg| matches!(leg, JourneyLeg::Transit { .. }))
+            .count()
+            .saturating_sub(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gtfs_io::{Gtfs, GtfsBufferSlices};
+    use indoc::indoc;
+
+    use super::*;
+    use crate::routing::{format_time, parse_time, TimetableSources};
+
+    const STOPS: &str = indoc! {"
+        stop_id,stop_name,location_type,parent_station
+        S,Station,1,
+        A,Station Platform,0,S
+        B,B,0,
+        C,C,0,
+        D,D,0,
+    "};
+    const TRIPS: &str = indoc! {"
+        route_id,service_id,trip_id
+        R1,WEEKDAY,T1
+        R1,WEEKDAY,T2
+        R1,WEEKDAY,T_NIGHT
+        R2,WEEKDAY,T3
+        R2,WEEKDAY,T4
+        R3,WEEKDAY,T5
+    "};
+    const STOP_TIMES: &str = indoc! {"
+        trip_id,arrival_time,departure_time,stop_id,stop_sequence
+        T1,08:00:00,08:00:00,A,1
+        T1,08:10:00,08:10:00,B,2
+        T1,08:20:00,08:20:00,C,3
+        T2,09:00:00,09:00:00,A,1
+        T2,09:10:00,09:10:00,B,2
+        T2,09:20:00,09:20:00,C,3
+        T_NIGHT,23:50:00,23:50:00,A,1
+        T_NIGHT,24:10:00,24:10:00,B,2
+        T_NIGHT,24:30:00,24:30:00,C,3
+        T3,08:15:00,08:15:00,B,1
+        T3,08:30:00,08:30:00,D,2
+        T4,08:05:00,08:05:00,B,1
+        T4,08:12:00,08:12:00,D,2
+        T5,08:00:00,08:00:00,A,1
+        T5,09:00:00,09:00:00,D,2
+    "};
+    const CALENDAR: &str = indoc! {"
+        service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date
+        WEEKDAY,1,1,1,1,1,0,0,20250101,20251231
+    "};
+    const TRANSFERS: &str = indoc! {"
+        from_stop_id,to_stop_id,transfer_type,min_transfer_time
+        C,D,2,900
+    "};
+
+    fn load_gtfs() -> Gtfs<'static> {
+        Gtfs::from_buffers(GtfsBufferSlices {
+            stops: Some(STOPS.as_bytes()),
+            trips: Some(TRIPS.as_bytes()),
+            stop_times: Some(STOP_TIMES.as_bytes()),
+            calendars: Some(CALENDAR.as_bytes()),
+            transfers: Some(TRANSFERS.as_bytes()),
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    /// Builds the timetable without walking transfers, only with the ones from transfers.txt.
+    fn build_timetable<'a>(gtfs: &Gtfs<'a>, date: gtfs_io::Date) -> Timetable<'a> {
+        let sources = TimetableSources {
+            gtfs,
+            ids: &gtfs.intern_ids(),
+            calendar: &gtfs.service_calendar(),
+            expanded_trips: &gtfs.expand_frequencies(),
+        };
+        Timetable::new(&sources, date)
+    }
+
+    fn date(year: u16, month: u8, day: u8) -> gtfs_io::Date {
+        gtfs_io::Date::new(year, month, day).unwrap()
+    }
+
+    fn time(text: &str) -> Time {
+        parse_time(text).unwrap()
+    }
+
+    /// Describes every leg as `(trip or "walk", from, to, departure, arrival)`.
+    fn describe<'a>(
+        timetable: &Timetable<'a>,
+        journey: &Journey<'a>,
+    ) -> Vec<(&'a str, &'a str, &'a str, Time, Time)> {
+        let stop_id = |stop| timetable.stops().id(stop);
+        journey
+            .legs
+            .iter()
+            .map(|leg| match leg {
+                JourneyLeg::Transit {
+                    trip_id,
+                    from_stop,
+                    to_stop,
+                    departure,
+                    arrival,
+                    ..
+                } => (
+                    *trip_id,
+                    stop_id(*from_stop),
+                    stop_id(*to_stop),
+                    *departure,
+                    *arrival,
+                ),
+                JourneyLeg::Walk {
+                    from_stop,
+                    to_stop,
+                    departure,
+                    arrival,
+                } => (
+                    "walk",
+                    stop_id(*from_stop),
+                    stop_id(*to_stop),
+                    *departure,
+                    *arrival,
+                ),
+            })
+            .collect()
+    }
+
+    fn query<'q>(from_stop_id: &'q str, to_stop_id: &'q str, departure: &str) -> JourneyQuery<'q> {
+        JourneyQuery {
+            from_stop_id,
+            to_stop_id,
+            departure: time(departure),
+            max_transfers: 4,
+        }
+    }
+
+    #[test]
+    fn test_pareto_journeys() {
+        let gtfs = load_gtfs();
+        let timetable = build_timetable(&gtfs, date(2025, 3, 3));
+        let journeys = plan_journeys(&timetable, &query("A", "D", "07:50")).unwrap();
+        assert_eq!(journeys.len(), 2);
+
+        // Riding to the end of the line and walking is the fastest without changing trips.
+        assert_eq!(journeys[0].transfers_num(), 0);
+        assert_eq!(
+            describe(&timetable, &journeys[0]),
+            vec![
+                ("T1", "A", "C", time("08:00"), time("08:20")),
+                ("walk", "C", "D", time("08:20"), time("08:35")),
+            ]
+        );
+        // T4 leaves B before T1 arrives, so T3 has to be taken.
+        assert_eq!(journeys[1].transfers_num(), 1);
+        assert_eq!(journeys[1].departure, time("08:00"));
+        assert_eq!(journeys[1].arrival, time("08:30"));
+        assert_eq!(
+            describe(&timetable, &journeys[1]),
+            vec![
+                ("T1", "A", "B", time("08:00"), time("08:10")),
+                ("T3", "B", "D", time("08:15"), time("08:30")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_max_transfers() {
+        let gtfs = load_gtfs();
+        let timetable = build_timetable(&gtfs, date(2025, 3, 3));
+        let journeys = plan_journeys(
+            &timetable,
+            &JourneyQuery {
+                max_transfers: 0,
+                ..query("A", "D", "07:50")
+            },
+        )
+        .unwrap();
+        assert_eq!(journeys.len(), 1);
+        assert_eq!(journeys[0].arrival, time("08:35"));
+    }
+
+    #[test]
+    fn test_later_departure() {
+        let gtfs = load_gtfs();
+        let timetable = build_timetable(&gtfs, date(2025, 3, 3));
+        let journeys = plan_journeys(&timetable, &query("A", "C", "08:01")).unwrap();
+        assert_eq!(journeys.len(), 1);
+        assert_eq!(
+            describe(&timetable, &journeys[0]),
+            vec![("T2", "A", "C", time("09:00"), time("09:20"))]
+        );
+    }
+
+    #[test]
+    fn test_station_uses_child_stops() {
+        let gtfs = load_gtfs();
+        let timetable = build_timetable(&gtfs, date(2025, 3, 3));
+        let journeys = plan_journeys(&timetable, &query("S", "C", "07:50")).unwrap();
+        assert_eq!(journeys.len(), 1);
+        assert_eq!(journeys[0].arrival, time("08:20"));
+    }
+
+    #[test]
+    fn test_service_days() {
+        let gtfs = load_gtfs();
+        // Nothing runs on Saturday, except for the trip that started on Friday evening.
+        let timetable = build_timetable(&gtfs, date(2025, 3, 8));
+        let journeys = plan_journeys(&timetable, &query("B", "C", "00:00")).unwrap();
+        assert_eq!(journeys.len(), 1);
+        assert_eq!(
+            describe(&timetable, &journeys[0]),
+            vec![("T_NIGHT", "B", "C", time("00:10"), time("00:30"))]
+        );
+        assert!(plan_journeys(&timetable, &query("A", "D", "07:50"))
+            .unwrap()
+            .is_empty());
+
+        // On Monday, the trip of the previous day does not run.
+        let timetable = build_timetable(&gtfs, date(2025, 3, 3));
+        let journeys = plan_journeys(&timetable, &query("B", "C", "00:00")).unwrap();
+        assert_eq!(journeys[0].departure, time("08:10"));
+    }
+
+    #[test]
+    fn test_first_trip_departing() {
+        let gtfs = load_gtfs();
+        let timetable = build_timetable(&gtfs, date(2025, 3, 3));
+        let stop = timetable.stops().get("A").unwrap();
+        let (pattern_i, position) = timetable
+            .stop_patterns(stop)
+            .iter()
+            .map(|(pattern_i, position)| (*pattern_i as usize, *position as usize))
+            .find(|(pattern_i, _)| timetable.patterns()[*pattern_i].route_id == "R1")
+            .unwrap();
+        assert_eq!(timetable.trips_num(pattern_i), 3);
+        let first = |departure, trips_num| {
+            timetable
+                .first_trip_departing(pattern_i, position, time(departure), trips_num)
+                .map(|trip_i| timetable.trip_id(pattern_i, trip_i))
+        };
+        assert_eq!(first("07:00", 3), Some("T1"));
+        assert_eq!(first("08:00", 3), Some("T1"));
+        assert_eq!(first("08:01", 3), Some("T2"));
+        assert_eq!(first("23:50", 3), Some("T_NIGHT"));
+        assert_eq!(first("23:51", 3), None);
+        // Only earlier trips than the current one are searched.
+        assert_eq!(first("08:01", 1), None);
+    }
+
+    #[test]
+    fn test_long_transfers() {
+        let gtfs = Gtfs::from_buffers(GtfsBufferSlices {
+            stops: Some(STOPS.as_bytes()),
+            trips: Some(TRIPS.as_bytes()),
+            stop_times: Some(STOP_TIMES.as_bytes()),
+            calendars: Some(CALENDAR.as_bytes()),
+            transfers: Some(
+                b"from_stop_id,to_stop_id,transfer_type,min_transfer_time\nC,D,2,4294967295\nD,C,2,2147483647\n",
+            ),
+            ..Default::default()
+        })
+        .unwrap();
+        let timetable = build_timetable(&gtfs, date(2025, 3, 3));
+        let stop = |stop_id| timetable.stops().get(stop_id).unwrap();
+        // Transfer times that don't fit into a time are left out.
+        assert!(timetable.transfers(stop("C")).is_empty());
+        assert_eq!(timetable.transfers(stop("D"))[0].duration, Time::MAX);
+
+        // Walking for the maximum time does not overflow.
+        let journeys = plan_journeys(&timetable, &query("A", "C", "07:50")).unwrap();
+        assert_eq!(journeys[0].arrival, time("08:20"));
+    }
+
+    #[test]
+    fn test_unknown_stop() {
+        let gtfs = load_gtfs();
+        let timetable = build_timetable(&gtfs, date(2025, 3, 3));
+        assert!(plan_journeys(&timetable, &query("A", "Z", "08:00")).is_err());
+    }
+
+    #[test]
+    fn test_parse_and_format_time() {
+        assert_eq!(parse_time("08:05"), Some(8 * 3600 + 5 * 60));
+        assert_eq!(parse_time("25:00:30"), Some(25 * 3600 + 30));
+        assert_eq!(parse_time("08:60"), None);
+        assert_eq!(parse_time("8"), None);
+        assert_eq!(parse_time("47:59:59"), Some(48 * 3600 - 1));
+        assert_eq!(parse_time("48:00"), None);
+        assert_eq!(parse_time("1000000:00"), None);
+        assert_eq!(format_time(25 * 3600 + 30), "25:00:30");
+        assert_eq!(format_time(-60), "-00:01:00");
+    }
+}
@@ -0,0 +1,114 @@
+use gtfs_io::StopIdx;
+
+use super::{resolve_place, run_raptor, Place, Time, Timetable, UNREACHED};
+use crate::{coordinates::LatLon, gtfs_dataset::GtfsDataset};
+
+/// Finds the earliest arrival at all stops from a position.
+#[derive(Debug, Clone, Copy)]
+pub struct ReachabilityQuery {
+    pub start: LatLon,
+    pub departure: Time,
+    pub max_transfers: usize,
+}
+
+/// Earliest arrival times at all stops of a [`Timetable`].
+#[derive(Debug, Clone)]
+pub struct Reachability {
+    pub departure: Time,
+    arrivals: Vec<Time>,
+}
+
+/// One-to-all search from a position. The stops close to the start are reached by walking
+/// (see [`resolve_place`]), all other stops with trips and the transfers between them.
+pub fn compute_reachability(
+    dataset: &GtfsDataset,
+    timetable: &Timetable,
+    query: &ReachabilityQuery,
+) -> Reachability {
+    let sources: Vec<(StopIdx, Time)> =
+        resolve_place(dataset, timetable, &Place::Position(query.start))
+            .into_iter()
+            .map(|(stop, walk_duration)| (stop, query.departure + walk_duration))
+            .collect();
+    let result = run_raptor(timetable, &sources, query.max_transfers);
+    Reachability {
+        departure: query.departure,
+        arrivals: result.earliest_arrivals().to_vec(),
+    }
+}
+
+impl Reachability {
+    pub fn arrival(&self, stop: StopIdx) -> Option<Time> {
+        let arrival = self.arrivals[usize::from(stop)];
+        (arrival != UNREACHED).then_some(arrival)
+    }
+
+    /// Time from the departure until the stop is reached.
+    pub fn travel_time(&self, stop: StopIdx) -> Option<Time> {
+        self.arrival(stop).map(|arrival| arrival - self.departure)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gtfs_io::{Gtfs, GtfsFilter};
+
+    use super::*;
+    use crate::{routing::WalkingParams, tests::load_gtfs_dummy_buffers};
+
+    #[test]
+    fn test_reachability_from_position() {
+        let buffers = load_gtfs_dummy_buffers(&GtfsFilter::all());
+        let dataset = GtfsDataset::new(
+            Gtfs::from_buffers(buffers.to_slices()).unwrap(),
+            WalkingParams::default(),
+        );
+        let timetable = dataset.get_timetable(gtfs_io::Date::new(2025, 1, 6).unwrap());
+        let stop = |stop_id| timetable.stops().get(stop_id).unwrap();
+
+        // Roughly 100m north of stop 1. Stop 2 is far away but connected by a transfer.
+        let reachability = compute_reachability(
+            &dataset,
+            &timetable,
+            &ReachabilityQuery {
+                start: LatLon::new(42.0009, 24.0),
+                departure: 8 * 3600,
+                max_transfers: 2,
+            },
+        );
+        let walk_time = reachability.travel_time(stop("1")).unwrap();
+        assert!((70..=90).contains(&walk_time), "{}", walk_time);
+        assert_eq!(reachability.travel_time(stop("2")), Some(walk_time + 180));
+
+        // Nothing is within walking distance.
+        let reachability = compute_reachability(
+            &dataset,
+            &timetable,
+            &ReachabilityQuery {
+                start: LatLon::new(0.0, 0.0),
+                departure: 8 * 3600,
+                max_transfers: 2,
+            },
+        );
+        assert_eq!(reachability.arrival(stop("1")), None);
+        assert_eq!(reachability.arrival(stop("2")), None);
+
+        // The result is kept for further tiles of the same query.
+        let date = gtfs_io::Date::new(2025, 1, 6).unwrap();
+        let query = ReachabilityQuery {
+            start: LatLon::new(42.0009, 24.0),
+            departure: 8 * 3600,
+            max_transfers: 2,
+        };
+        let cached = dataset.get_reachability(date, &query);
+        assert_eq!(cached.travel_time(stop("1")), Some(walk_time));
+        assert!(std::sync::Arc::ptr_eq(
+            &cached,
+            &dataset.get_reachability(date, &query)
+        ));
+        assert!(std::sync::Arc::ptr_eq(
+            &timetable,
+            &dataset.get_timetable(date)
+        ));
+    }
+}
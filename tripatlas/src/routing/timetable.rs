@@ -0,0 +1,515 @@
+use std::collections::{HashMap, HashSet};
+
+use super::TransferGraph;
+
+use gtfs_io::{
+    Date, DropOffType, ExpandedStopTime, ExpandedTrip, Gtfs, IdMap, InternedIds,
+    OptionalServiceDayTime, PickupType, ServiceCalendar, ServiceDayTime, StopIdx, StopTimes,
+    TransferType,
+};
+
+/// Seconds since midnight of the day that a [`Timetable`] was built for. Trips of the previous
+/// service day that run past midnight have negative times before midnight.
+pub type Time = i32;
+
+pub const SECONDS_PER_DAY: Time = 24 * 60 * 60;
+
+/// Queries can't start later than this, which is one day after the end of the requested day.
+const MAX_HOURS: Time = 48;
+
+/// Parses `HH:MM` or `HH:MM:SS`. Hours may be larger than 23 for times after midnight, but have
+/// to be less than 48.
+pub fn parse_time(text: &str) -> Option<Time> {
+    let mut parts = text.trim().split(':');
+    let hours: Time = parts.next()?.parse().ok()?;
+    let minutes: Time = parts.next()?.parse().ok()?;
+    let seconds: Time = parts.next().map_or(Some(0), |part| part.parse().ok())?;
+    if parts.next().is_some()
+        || !(0..MAX_HOURS).contains(&hours)
+        || !(0..60).contains(&minutes)
+        || !(0..60).contains(&seconds)
+    {
+        return None;
+    }
+    hours
+        .checked_mul(3600)?
+        .checked_add(minutes * 60)?
+        .checked_add(seconds)
+}
+
+/// Formats the time as `HH:MM:SS` without wrapping hours after midnight.
+pub fn format_time(time: Time) -> String {
+    let sign = if time < 0 { "-" } else { "" };
+    let time = time.abs();
+    format!(
+        "{}{:02}:{:02}:{:02}",
+        sign,
+        time / 3600,
+        (time / 60) % 60,
+        time % 60
+    )
+}
+
+/// The trips that run on one day, preprocessed for the RAPTOR algorithm.
+///
+/// Trips of the same route that serve the same sequence of stops are grouped into a
+/// [`Pattern`]. Within a pattern, trips are sorted by departure and never overtake each other,
+/// so the first trip that can be boarded at a stop is also the first to arrive at every later
+/// stop. The stops, trips and stop times of all patterns are stored in flat arrays.
+pub struct Timetable<'a> {
+    date: Date,
+    stops: IdMap<'a, StopIdx>,
+    /// Stops whose parent station is the stop, e.g. the platforms of a station.
+    stop_children: Vec<Vec<StopIdx>>,
+    patterns: Vec<Pattern<'a>>,
+    pattern_stops: Vec<PatternStop>,
+    trip_ids: Vec<&'a str>,
+    /// Stored trip by trip, so that the times of one trip are contiguous.
+    stop_times: Vec<StopTime>,
+    /// Patterns that serve a stop together with the position of the stop in the pattern.
+    stop_patterns: Vec<Vec<(u32, u32)>>,
+    transfers: TransferGraph,
+}
+
+#[derive(Debug, Clone)]
+pub struct Pattern<'a> {
+    pub route_id: &'a str,
+    stops_start: usize,
+    stops_num: usize,
+    trips_start: usize,
+    trips_num: usize,
+    stop_times_start: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PatternStop {
+    pub stop: StopIdx,
+    pub can_board: bool,
+    pub can_alight: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StopTime {
+    pub arrival: Time,
+    pub departure: Time,
+}
+
+/// The parts of a feed that timetables are built from and that don't depend on the day. They
+/// are prepared once and shared by the timetables of all days.
+pub struct TimetableSources<'s, 'a> {
+    pub gtfs: &'s Gtfs<'a>,
+    pub ids: &'s InternedIds<'a>,
+    pub calendar: &'s ServiceCalendar<'a>,
+    /// Instances of the trips from frequencies.txt.
+    pub expanded_trips: &'s [ExpandedTrip<'a>],
+}
+
+/// A footpath to another stop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Transfer {
+    pub to_stop: StopIdx,
+    pub duration: Time,
+}
+
+impl<'a> Timetable<'a> {
+    /// Contains all trips whose service runs on the given date and the trips of the previous
+    /// day that are still running after midnight. Trips from frequencies.txt are expanded into
+    /// individual trips. Stop times without arrival and departure time are ignored.
+    pub fn new(sources: &TimetableSources<'_, 'a>, date: Date) -> Self {
+        let ids = sources.ids;
+        let mut builder = TimetableBuilder::default();
+        builder.add_trips(sources, date);
+
+        let mut stop_children = vec![vec![]; ids.stops.len()];
+        if let (Some(stop_ids), Some(parent_stations)) =
+            (&ids.stops_stop_id, &ids.stops_parent_station)
+        {
+            for (stop, parent_station) in stop_ids.iter().zip(parent_stations) {
+                if let (Some(stop), Some(parent_station)) = (stop, parent_station) {
+                    stop_children[usize::from(*parent_station)].push(*stop);
+                }
+            }
+        }
+
+        let mut timetable = Self {
+            date,
+            stop_children,
+            patterns: vec![],
+            pattern_stops: vec![],
+            trip_ids: vec![],
+            stop_times: vec![],
+            stop_patterns: vec![vec![]; ids.stops.len()],
+            transfers: TransferGraph::default(),
+            stops: ids.stops.clone(),
+        };
+        for group in builder.groups {
+            timetable.add_pattern_group(group);
+        }
+        timetable.add_transfers(gtfs_transfers(sources.gtfs, &timetable.stops));
+        timetable
+    }
+
+    fn add_pattern_group(&mut self, group: PatternGroup<'a>) {
+        let PatternGroup {
+            route_id,
+            stops,
+            mut trips,
+        } = group;
+        trips.sort_by(|a, b| {
+            a.times
+                .iter()
+                .map(|t| t.departure)
+                .cmp(b.times.iter().map(|t| t.departure))
+        });
+
+        // Split the trips so that no trip overtakes another one in the same pattern.
+        let mut fifo_groups: Vec<Vec<TripTimes<'a>>> = vec![];
+        for trip in trips {
+            let fifo_group = fifo_groups.iter_mut().find(|fifo_group| {
+                let last = fifo_group.last().unwrap();
+                last.times
+                    .iter()
+                    .zip(&trip.times)
+                    .all(|(a, b)| a.arrival <= b.arrival && a.departure <= b.departure)
+            });
+            match fifo_group {
+                Some(fifo_group) => fifo_group.push(trip),
+                None => fifo_groups.push(vec![trip]),
+            }
+        }
+
+        for trips in fifo_groups {
+            let pattern_i = self.patterns.len() as u32;
+            for (position, stop) in stops.iter().enumerate() {
+                self.stop_patterns[usize::from(stop.stop)].push((pattern_i, position as u32));
+            }
+            self.patterns.push(Pattern {
+                route_id,
+                stops_start: self.pattern_stops.len(),
+                stops_num: stops.len(),
+                trips_start: self.trip_ids.len(),
+                trips_num: trips.len(),
+                stop_times_start: self.stop_times.len(),
+            });
+            self.pattern_stops.extend_from_slice(&stops);
+            for trip in trips {
+                self.trip_ids.push(trip.trip_id);
+                self.stop_times.extend(trip.times);
+            }
+        }
+    }
+
+    /// Merges the transfers into the existing ones. If there are multiple transfers between
+    /// the same stops, only the fastest is kept.
+    pub fn add_transfers(&mut self, transfers: impl IntoIterator<Item = (StopIdx, Transfer)>) {
+        let mut edges: Vec<_> = self.transfers.edges().collect();
+        edges.extend(transfers);
+        self.transfers = TransferGraph::new(self.stops.len(), edges);
+    }
+
+    pub fn date(&self) -> Date {
+        self.date
+    }
+
+    pub fn stops(&self) -> &IdMap<'a, StopIdx> {
+        &self.stops
+    }
+
+    /// The stop itself followed by its child stops, if it is a station. Returns an empty
+    /// vector if the stop does not exist.
+    pub fn stop_with_children(&self, stop_id: &str) -> Vec<StopIdx> {
+        let Some(stop) = self.stops.get(stop_id) else {
+            return vec![];
+        };
+        let mut stops = vec![stop];
+        stops.extend_from_slice(&self.stop_children[usize::from(stop)]);
+        stops
+    }
+
+    pub fn patterns(&self) -> &[Pattern<'a>] {
+        &self.patterns
+    }
+
+    pub fn pattern_stops(&self, pattern_i: usize) -> &[PatternStop] {
+        let pattern = &self.patterns[pattern_i];
+        &self.pattern_stops[pattern.stops_start..pattern.stops_start + pattern.stops_num]
+    }
+
+    pub fn trips_num(&self, pattern_i: usize) -> usize {
+        self.patterns[pattern_i].trips_num
+    }
+
+    pub fn trip_id(&self, pattern_i: usize, trip_i: usize) -> &'a str {
+        self.trip_ids[self.patterns[pattern_i].trips_start + trip_i]
+    }
+
+    /// Times of the trip at all stops of the pattern.
+    pub fn trip_stop_times(&self, pattern_i: usize, trip_i: usize) -> &[StopTime] {
+        let pattern = &self.patterns[pattern_i];
+        let start = pattern.stop_times_start + trip_i * pattern.stops_num;
+        &self.stop_times[start..start + pattern.stops_num]
+    }
+
+    /// The earliest of the first `trips_num` trips of the pattern that departs at the position
+    /// no earlier than the time. Trips of a pattern don't overtake each other, so their
+    /// departures are sorted at every position and can be binary searched.
+    pub fn first_trip_departing(
+        &self,
+        pattern_i: usize,
+        position: usize,
+        time: Time,
+        trips_num: usize,
+    ) -> Option<usize> {
+        // Same as `partition_point`, but the departures at a position are not contiguous.
+        let (mut start, mut end) = (0, trips_num);
+        while start < end {
+            let middle = start + (end - start) / 2;
+            if self.trip_stop_times(pattern_i, middle)[position].departure < time {
+                start = middle + 1;
+            } else {
+                end = middle;
+            }
+        }
+        (start < trips_num).then_some(start)
+    }
+
+    /// Patterns that serve the stop together with the position of the stop in the pattern.
+    pub fn stop_patterns(&self, stop: StopIdx) -> &[(u32, u32)] {
+        &self.stop_patterns[usize::from(stop)]
+    }
+
+    pub fn transfers(&self, stop: StopIdx) -> &[Transfer] {
+        self.transfers.transfers(stop)
+    }
+
+    pub fn transfers_num(&self) -> usize {
+        self.transfers.transfers_num()
+    }
+}
+
+/// Transfers from transfers.txt. Only transfers between two different stops that don't depend
+/// on a route or trip are used. Transfers whose minimum time is out of range are left out.
+fn gtfs_transfers(gtfs: &Gtfs, stops: &IdMap<StopIdx>) -> Vec<(StopIdx, Transfer)> {
+    let Some(transfers) = gtfs.transfers.data.as_ref() else {
+        return vec![];
+    };
+    let (Some(from_stop_ids), Some(to_stop_ids)) = (&transfers.from_stop_id, &transfers.to_stop_id)
+    else {
+        return vec![];
+    };
+    let is_empty = |column: &Option<Vec<&str>>, i: usize| {
+        column.as_ref().is_none_or(|column| column[i].is_empty())
+    };
+    let mut result = vec![];
+    for (i, (from_stop_id, to_stop_id)) in from_stop_ids.iter().zip(to_stop_ids).enumerate() {
+        let (Some(from_stop), Some(to_stop)) = (stops.get(from_stop_id), stops.get(to_stop_id))
+        else {
+            continue;
+        };
+        if from_stop == to_stop
+            || !is_empty(&transfers.from_route_id, i)
+            || !is_empty(&transfers.to_route_id, i)
+            || !is_empty(&transfers.from_trip_id, i)
+            || !is_empty(&transfers.to_trip_id, i)
+        {
+            continue;
+        }
+        let is_possible = transfers.transfer_type.as_ref().is_none_or(|column| {
+            matches!(
+                column[i],
+                TransferType::Recommended | TransferType::Timed | TransferType::MinimumTime
+            )
+        });
+        if !is_possible {
+            continue;
+        }
+        let duration = transfers
+            .min_transfer_time
+            .as_ref()
+            .and_then(|column| column[i].0)
+            .unwrap_or(0);
+        let Ok(duration) = Time::try_from(duration) else {
+            continue;
+        };
+        result.push((from_stop, Transfer { to_stop, duration }));
+    }
+    result
+}
+
+#[derive(Default)]
+struct TimetableBuilder<'a> {
+    groups: Vec<PatternGroup<'a>>,
+    group_indices: HashMap<(&'a str, Vec<PatternStop>), usize>,
+}
+
+struct PatternGroup<'a> {
+    route_id: &'a str,
+    stops: Vec<PatternStop>,
+    trips: Vec<TripTimes<'a>>,
+}
+
+struct TripTimes<'a> {
+    trip_id: &'a str,
+    times: Vec<StopTime>,
+}
+
+impl<'a> TimetableBuilder<'a> {
+    fn add_trips(&mut self, sources: &TimetableSources<'_, 'a>, date: Date) {
+        let TimetableSources {
+            gtfs,
+            ids,
+            calendar,
+            expanded_trips,
+        } = *sources;
+        let (Some(trips), Some(stop_times)) =
+            (gtfs.trips.data.as_ref(), gtfs.stop_times.data.as_ref())
+        else {
+            return;
+        };
+        let (Some(route_ids), Some(service_ids), Some(trips_trip_id), Some(stop_times_trip_id)) = (
+            &trips.route_id,
+            &trips.service_id,
+            &ids.trips_trip_id,
+            &ids.stop_times_trip_id,
+        ) else {
+            return;
+        };
+
+        // Stop times of every trip, sorted by stop sequence.
+        let mut trip_rows = vec![vec![]; ids.trips.len()];
+        for (stop_time_i, trip) in stop_times_trip_id.iter().enumerate() {
+            if let Some(trip) = trip {
+                trip_rows[usize::from(*trip)].push(stop_time_i);
+            }
+        }
+        if let Some(stop_sequences) = &stop_times.stop_sequence {
+            for rows in &mut trip_rows {
+                rows.sort_by_key(|stop_time_i| stop_sequences[*stop_time_i]);
+            }
+        }
+        let get_time = |times: &Option<Vec<OptionalServiceDayTime>>, stop_time_i: usize| {
+            times.as_ref().and_then(|times| times[stop_time_i].0)
+        };
+
+        let frequency_trip_ids: HashSet<&str> =
+            expanded_trips.iter().map(|trip| trip.trip_id).collect();
+        // Row in trips.txt of every trip id.
+        let mut trip_row_by_trip = vec![None; ids.trips.len()];
+        for (trip_row, trip) in trips_trip_id.iter().enumerate() {
+            if let Some(trip) = trip {
+                trip_row_by_trip[usize::from(*trip)].get_or_insert(trip_row);
+            }
+        }
+
+        for day_offset in [-1, 0] {
+            let active_services = calendar.active_services(&date.add_days(day_offset));
+            let shift = day_offset * SECONDS_PER_DAY;
+            for (trip_row, trip) in trips_trip_id.iter().enumerate() {
+                let Some(trip) = trip else {
+                    continue;
+                };
+                let trip_id = ids.trips.id(*trip);
+                if !active_services.contains(service_ids[trip_row])
+                    || frequency_trip_ids.contains(trip_id)
+                {
+                    continue;
+                }
+                let expanded_stop_times =
+                    trip_rows[usize::from(*trip)]
+                        .iter()
+                        .map(|stop_time_i| ExpandedStopTime {
+                            stop_time_i: *stop_time_i,
+                            arrival_time: get_time(&stop_times.arrival_time, *stop_time_i),
+                            departure_time: get_time(&stop_times.departure_time, *stop_time_i),
+                        });
+                self.add_trip(
+                    stop_times,
+                    ids,
+                    route_ids[trip_row],
+                    trip_id,
+                    expanded_stop_times,
+                    shift,
+                );
+            }
+            for expanded_trip in expanded_trips {
+                let Some(trip) = ids.trips.get(expanded_trip.trip_id) else {
+                    continue;
+                };
+                let Some(trip_row) = trip_row_by_trip[usize::from(trip)] else {
+                    continue;
+                };
+                if !active_services.contains(service_ids[trip_row]) {
+                    continue;
+                }
+                self.add_trip(
+                    stop_times,
+                    ids,
+                    route_ids[trip_row],
+                    ids.trips.id(trip),
+                    expanded_trip.stop_times.iter().copied(),
+                    shift,
+                );
+            }
+        }
+    }
+
+    fn add_trip(
+        &mut self,
+        stop_times: &StopTimes<'a>,
+        ids: &InternedIds<'a>,
+        route_id: &'a str,
+        trip_id: &'a str,
+        trip_stop_times: impl Iterator<Item = ExpandedStopTime>,
+        shift: Time,
+    ) {
+        let Some(stop_times_stop_id) = &ids.stop_times_stop_id else {
+            return;
+        };
+        let to_time = |time: ServiceDayTime| time.seconds() as Time + shift;
+        let mut stops = vec![];
+        let mut times = vec![];
+        for stop_time in trip_stop_times {
+            let Some(stop) = stop_times_stop_id[stop_time.stop_time_i] else {
+                continue;
+            };
+            let (arrival, departure) = match (stop_time.arrival_time, stop_time.departure_time) {
+                (Some(arrival), Some(departure)) => (to_time(arrival), to_time(departure)),
+                (Some(time), None) | (None, Some(time)) => (to_time(time), to_time(time)),
+                (None, None) => continue,
+            };
+            let can_board = stop_times.pickup_type.as_ref().is_none_or(|column| {
+                !matches!(column[stop_time.stop_time_i], PickupType::NotAvailable)
+            });
+            let can_alight = stop_times.drop_off_type.as_ref().is_none_or(|column| {
+                !matches!(column[stop_time.stop_time_i], DropOffType::NotAvailable)
+            });
+            stops.push(PatternStop {
+                stop,
+                can_board,
+                can_alight,
+            });
+            times.push(StopTime { arrival, departure });
+        }
+        // Trips of the previous day only matter if they are still running after midnight.
+        if times.len() < 2 || times.last().unwrap().arrival < 0 {
+            return;
+        }
+
+        let key = (route_id, stops);
+        let group_i = match self.group_indices.get(&key) {
+            Some(group_i) => *group_i,
+            None => {
+                self.groups.push(PatternGroup {
+                    route_id,
+                    stops: key.1.clone(),
+                    trips: vec![],
+                });
+                self.group_indices.insert(key, self.groups.len() - 1);
+                self.groups.len() - 1
+            }
+        };
+        self.groups[group_i]
+            .trips
+            .push(TripTimes { trip_id, times });
+    }
+}
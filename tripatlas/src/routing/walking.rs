@@ -0,0 +1,287 @@
+use gtfs_io::{IdMap, StopIdx};
+use rayon::prelude::*;
+use rstar::AABB;
+
+use super::{Time, Transfer};
+use crate::{coordinates::LatLon, gtfs_dataset::GtfsDataset};
+
+/// Controls which stops are connected by footpaths and how long walking takes.
+#[derive(Debug, Clone, Copy)]
+pub struct WalkingParams {
+    /// Stops that are further apart are not connected.
+    pub max_distance_km: f32,
+    pub speed_km_per_hour: f32,
+}
+
+impl Default for WalkingParams {
+    fn default() -> Self {
+        Self {
+            max_distance_km: 0.4,
+            speed_km_per_hour: 4.5,
+        }
+    }
+}
+
+impl WalkingParams {
+    /// Time it takes to walk the distance.
+    pub fn duration(&self, distance_km: f32) -> Time {
+        (distance_km / self.speed_km_per_hour * 3600.0).ceil() as Time
+    }
+}
+
+/// Transfers between stops in compressed sparse row format. The transfers starting at stop `i`
+/// are stored in `transfers[offsets[i]..offsets[i + 1]]`, sorted by the stop they lead to.
+#[derive(Debug, Clone, Default)]
+pub struct TransferGraph {
+    offsets: Vec<u32>,
+    transfers: Vec<Transfer>,
+}
+
+impl TransferGraph {
+    /// If there are multiple transfers between the same stops, only the fastest is kept.
+    pub fn new(stops_num: usize, mut edges: Vec<(StopIdx, Transfer)>) -> Self {
+        edges.sort_unstable_by_key(|(from_stop, transfer)| {
+            (*from_stop, transfer.to_stop, transfer.duration)
+        });
+        edges.dedup_by_key(|(from_stop, transfer)| (*from_stop, transfer.to_stop));
+
+        let mut offsets = vec![0; stops_num + 1];
+        for (from_stop, _) in &edges {
+            offsets[usize::from(*from_stop) + 1] += 1;
+        }
+        for i in 0..stops_num {
+            offsets[i + 1] += offsets[i];
+        }
+        Self {
+            offsets,
+            transfers: edges.into_iter().map(|(_, transfer)| transfer).collect(),
+        }
+    }
+
+    pub fn transfers(&self, stop: StopIdx) -> &[Transfer] {
+        let stop_i = usize::from(stop);
+        match self.offsets.get(stop_i..stop_i + 2) {
+            Some(range) => &self.transfers[range[0] as usize..range[1] as usize],
+            None => &[],
+        }
+    }
+
+    /// All transfers together with the stop they start at.
+    pub fn edges(&self) -> impl Iterator<Item = (StopIdx, Transfer)> + '_ {
+        self.offsets
+            .windows(2)
+            .enumerate()
+            .flat_map(|(stop_i, range)| {
+                self.transfers[range[0] as usize..range[1] as usize]
+                    .iter()
+                    .map(move |transfer| (StopIdx::from(stop_i), *transfer))
+            })
+    }
+
+    pub fn transfers_num(&self) -> usize {
+        self.transfers.len()
+    }
+}
+
+/// Rows in stops.txt of the stops within the distance of the position, together with their
+/// great-circle distance.
+pub fn stops_within(
+    dataset: &GtfsDataset,
+    position: LatLon,
+    max_distance_km: f32,
+) -> Vec<(u32, f32)> {
+    // Roughly 111 km per degree of latitude. Degrees of longitude get shorter towards the poles.
+    let lat_delta = max_distance_km / 111.0;
+    let lon_delta = lat_delta / position.latitude.to_radians().cos().max(0.01);
+    let envelope = AABB::from_corners(
+        [
+            position.longitude - lon_delta,
+            position.latitude - lat_delta,
+        ],
+        [
+            position.longitude + lon_delta,
+            position.latitude + lat_delta,
+        ],
+    );
+    dataset
+        .get_stops_tree()
+        .locate_in_envelope(&envelope)
+        .filter_map(|rtree_stop| {
+            let distance_km = position.great_circle_distance_km(rtree_stop.position);
+            (distance_km <= max_distance_km).then_some((rtree_stop.stop_i, distance_km))
+        })
+        .collect()
+}
+
+/// Footpaths between all stops of the dataset that are within walking distance of each other.
+/// This connects stops that don't have transfers in transfers.txt. Stops of different datasets
+/// are not connected, so feeds have to be merged with `gtfs-merge` first to transfer between
+/// them.
+pub fn walking_transfers(
+    dataset: &GtfsDataset,
+    stops: &IdMap<StopIdx>,
+    params: &WalkingParams,
+) -> Vec<(StopIdx, Transfer)> {
+    let Some(stop_ids) = dataset
+        .raw
+        .stops
+        .data
+        .as_ref()
+        .and_then(|stops| stops.stop_id.as_ref())
+    else {
+        return vec![];
+    };
+    let rtree_stops: Vec<_> = dataset.get_stops_tree().iter().collect();
+    rtree_stops
+        .par_iter()
+        .flat_map_iter(|from_rtree_stop| {
+            let from_stop = stops.get(stop_ids[from_rtree_stop.stop_i as usize]);
+            stops_within(dataset, from_rtree_stop.position, params.max_distance_km)
+                .into_iter()
+                .filter_map(move |(to_stop_i, distance_km)| {
+                    let to_stop = stops.get(stop_ids[to_stop_i as usize])?;
+                    let from_stop = from_stop?;
+                    (from_stop != to_stop).then_some((
+                        from_stop,
+                        Transfer {
+                            to_stop,
+                            duration: params.duration(distance_km),
+                        },
+                    ))
+                })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use gtfs_io::{Gtfs, GtfsBufferSlices};
+    use indoc::indoc;
+
+    use super::*;
+    use crate::routing::{parse_time, plan_journeys, JourneyLeg, JourneyQuery};
+
+    /// Two lines whose end and start are 200m apart, but there is no transfers.txt.
+    const STOPS: &str = indoc! {"
+        stop_id,stop_name,stop_lat,stop_lon
+        A,A,52.5000,13.3000
+        B,B,52.5000,13.4000
+        B2,B2,52.5018,13.4000
+        C,C,52.5000,13.5000
+    "};
+    const TRIPS: &str = indoc! {"
+        route_id,service_id,trip_id
+        R1,DAILY,T1
+        R2,DAILY,T2
+    "};
+    const STOP_TIMES: &str = indoc! {"
+        trip_id,arrival_time,departure_time,stop_id,stop_sequence
+        T1,08:00:00,08:00:00,A,1
+        T1,08:10:00,08:10:00,B,2
+        T2,08:20:00,08:20:00,B2,1
+        T2,08:30:00,08:30:00,C,2
+    "};
+    const CALENDAR: &str = indoc! {"
+        service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date
+        DAILY,1,1,1,1,1,1,1,20250101,20251231
+    "};
+
+    fn load_dataset(walking_params: WalkingParams) -> GtfsDataset<'static> {
+        GtfsDataset::new(
+            Gtfs::from_buffers(GtfsBufferSlices {
+                stops: Some(STOPS.as_bytes()),
+                trips: Some(TRIPS.as_bytes()),
+                stop_times: Some(STOP_TIMES.as_bytes()),
+                calendars: Some(CALENDAR.as_bytes()),
+                ..Default::default()
+            })
+            .unwrap(),
+            walking_params,
+        )
+    }
+
+    #[test]
+    fn test_transfer_graph() {
+        let transfer = |to_stop: usize, duration| Transfer {
+            to_stop: StopIdx::from(to_stop),
+            duration,
+        };
+        let graph = TransferGraph::new(
+            4,
+            vec![
+                (StopIdx::from(2), transfer(0, 30)),
+                (StopIdx::from(0), transfer(1, 60)),
+                (StopIdx::from(2), transfer(1, 10)),
+                (StopIdx::from(0), transfer(1, 20)),
+            ],
+        );
+        assert_eq!(graph.transfers_num(), 3);
+        assert_eq!(graph.transfers(StopIdx::from(0)), &[transfer(1, 20)]);
+        assert_eq!(graph.transfers(StopIdx::from(1)), &[]);
+        assert_eq!(
+            graph.transfers(StopIdx::from(2)),
+            &[transfer(0, 30), transfer(1, 10)]
+        );
+        assert_eq!(graph.transfers(StopIdx::from(3)), &[]);
+        assert_eq!(graph.transfers(StopIdx::from(4)), &[]);
+        assert_eq!(graph.edges().count(), 3);
+    }
+
+    #[test]
+    fn test_walking_transfers() {
+        let dataset = load_dataset(WalkingParams::default());
+        let ids = dataset.get_ids();
+        let mut transfers = walking_transfers(&dataset, &ids.stops, &WalkingParams::default());
+        transfers.sort_by_key(|(from_stop, transfer)| (*from_stop, transfer.to_stop));
+        let stop = |stop_id| ids.stops.get(stop_id).unwrap();
+        assert_eq!(transfers.len(), 2);
+        assert_eq!(transfers[0].0, stop("B"));
+        assert_eq!(transfers[0].1.to_stop, stop("B2"));
+        assert_eq!(transfers[1].0, stop("B2"));
+        assert_eq!(transfers[1].1.to_stop, stop("B"));
+        // About 200m at 4.5 km/h.
+        assert!((155..=165).contains(&transfers[0].1.duration));
+
+        let no_transfers = walking_transfers(
+            &dataset,
+            &ids.stops,
+            &WalkingParams {
+                max_distance_km: 0.1,
+                ..Default::default()
+            },
+        );
+        assert!(no_transfers.is_empty());
+    }
+
+    #[test]
+    fn test_journey_with_walking_transfer() {
+        let query = JourneyQuery {
+            from_stop_id: "A",
+            to_stop_id: "C",
+            departure: parse_time("07:55").unwrap(),
+            max_transfers: 2,
+        };
+        let dataset = load_dataset(WalkingParams::default());
+        let timetable = dataset.get_timetable(gtfs_io::Date::new(2025, 1, 6).unwrap());
+        let journeys = plan_journeys(&timetable, &query).unwrap();
+        assert_eq!(journeys.len(), 1);
+        assert_eq!(journeys[0].arrival, parse_time("08:30").unwrap());
+        assert_eq!(journeys[0].transfers_num(), 1);
+        assert!(matches!(journeys[0].legs[1], JourneyLeg::Walk { .. }));
+
+        // The footpaths are shared by the timetables of all days.
+        let footpaths = dataset.footpaths.get().unwrap().as_ptr();
+        let other_timetable = dataset.get_timetable(gtfs_io::Date::new(2025, 1, 7).unwrap());
+        assert_eq!(dataset.footpaths.get().unwrap().as_ptr(), footpaths);
+        assert_eq!(other_timetable.transfers_num(), timetable.transfers_num());
+
+        // B and B2 are too far apart for this dataset.
+        let dataset = load_dataset(WalkingParams {
+            max_distance_km: 0.1,
+            ..Default::default()
+        });
+        let timetable = dataset.get_timetable(gtfs_io::Date::new(2025, 1, 6).unwrap());
+        assert!(dataset.get_footpaths().is_empty());
+        assert!(plan_journeys(&timetable, &query).unwrap().is_empty());
+    }
+}
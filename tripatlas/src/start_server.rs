@@ -1,15 +1,18 @@
 use actix_cors::Cors;
-use actix_web::{web, App, HttpServer};
-use gtfs_io::GtfsFilter;
+use actix_web::{error::BlockingError, web, App, HttpServer};
+use gtfs_io::{Date, GtfsFilter};
 use serde::{Deserialize, Serialize};
-use std::{net::TcpListener, path::PathBuf, sync::OnceLock};
+use std::{net::TcpListener, path::PathBuf, sync::Arc};
 
-use crate::gtfs_dataset::GtfsDataset;
+use crate::{
+    gtfs_dataset::GtfsDataset,
+    routing::{Timetable, WalkingParams},
+};
 
 pub struct State {
     pub config: Config,
     pub metrics: PrometheusMetrics,
-    pub datasets: Vec<GtfsDataset>,
+    pub datasets: Vec<GtfsDataset<'static>>,
 }
 
 pub struct PrometheusMetrics {
@@ -21,9 +24,31 @@ pub struct PrometheusMetrics {
     pub metrics_requests_total: prometheus::Counter,
     pub config_requests_total: prometheus::Counter,
     pub station_requests_total: prometheus::Counter,
+    pub reachability_requests_total: prometheus::Counter,
+    pub journey_requests_total: prometheus::Counter,
+    pub journey_request_errors_total: prometheus::Counter,
+    pub journey_request_duration_seconds: prometheus::Histogram,
+    pub departure_requests_total: prometheus::Counter,
     pub _experimental_requests_total: prometheus::Counter,
 }
 
+/// The timetables of all datasets for the given day. They are built on the blocking thread pool,
+/// because that takes a while on first use.
+pub async fn get_timetables(
+    state: &web::Data<State>,
+    date: Date,
+) -> Result<Vec<Arc<Timetable<'static>>>, BlockingError> {
+    let state = state.clone();
+    web::block(move || {
+        state
+            .datasets
+            .iter()
+            .map(|dataset| dataset.get_timetable(date))
+            .collect()
+    })
+    .await
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Config {
     pub allow_shutdown_from_frontend: bool,
@@ -75,6 +100,43 @@ fn prepare_prometheus_metrics() -> PrometheusMetrics {
         .namespace(namespace),
     )
     .unwrap();
+    let reachability_requests_total = prometheus::Counter::with_opts(
+        prometheus::Opts::new(
+            "reachability_requests_total",
+            "Total number of reachability requests",
+        )
+        .namespace(namespace),
+    )
+    .unwrap();
+    let journey_requests_total = prometheus::Counter::with_opts(
+        prometheus::Opts::new("journey_requests_total", "Total number of journey requests")
+            .namespace(namespace),
+    )
+    .unwrap();
+    let journey_request_errors_total = prometheus::Counter::with_opts(
+        prometheus::Opts::new(
+            "journey_request_errors_total",
+            "Total number of journey requests that were rejected",
+        )
+        .namespace(namespace),
+    )
+    .unwrap();
+    let journey_request_duration_seconds = prometheus::Histogram::with_opts(
+        prometheus::HistogramOpts::new(
+            "journey_request_duration_seconds",
+            "Time it takes to answer journey requests",
+        )
+        .namespace(namespace),
+    )
+    .unwrap();
+    let departure_requests_total = prometheus::Counter::with_opts(
+        prometheus::Opts::new(
+            "departure_requests_total",
+            "Total number of departure board requests",
+        )
+        .namespace(namespace),
+    )
+    .unwrap();
 
     let experimental_requests_total = prometheus::Counter::with_opts(
         prometheus::Opts::new(
@@ -93,6 +155,10 @@ fn prepare_prometheus_metrics() -> PrometheusMetrics {
         &metrics_requests_total,
         &config_requests_total,
         &station_requests_total,
+        &reachability_requests_total,
+        &journey_requests_total,
+        &journey_request_errors_total,
+        &departure_requests_total,
         &experimental_requests_total,
     ];
 
@@ -100,6 +166,9 @@ fn prepare_prometheus_metrics() -> PrometheusMetrics {
     for counter in counters {
         registry.register(Box::new(counter.clone())).unwrap();
     }
+    registry
+        .register(Box::new(journey_request_duration_seconds.clone()))
+        .unwrap();
 
     PrometheusMetrics {
         registry,
@@ -110,6 +179,11 @@ fn prepare_prometheus_metrics() -> PrometheusMetrics {
         metrics_requests_total,
         config_requests_total,
         station_requests_total,
+        reachability_requests_total,
+        journey_requests_total,
+        journey_request_errors_total,
+        journey_request_duration_seconds,
+        departure_requests_total,
         _experimental_requests_total: experimental_requests_total,
     }
 }
@@ -119,24 +193,28 @@ pub async fn start_server(
     on_start: Option<Box<dyn FnOnce() + Send>>,
     allow_shutdown_from_frontend: bool,
     gtfs_datasets: Vec<PathBuf>,
+    walking_params: WalkingParams,
 ) -> std::io::Result<()> {
     for path in &gtfs_datasets {
         println!("Loading GTFS from {:?}", path);
     }
 
-    static CELL: OnceLock<Vec<gtfs_io::GtfsBuffers>> = OnceLock::new();
-    let buffers = CELL.get_or_init(|| {
+    // The datasets borrow from the buffers for as long as the process runs.
+    let buffers: &'static [gtfs_io::GtfsBuffers] = Box::leak(
         gtfs_datasets
             .iter()
-            .map(|p| gtfs_io::GtfsBuffers::from_path(&p, &GtfsFilter::all()))
+            .map(|p| gtfs_io::GtfsBuffers::from_path(p, &GtfsFilter::all()))
             .collect::<Result<Vec<_>, _>>()
             .unwrap()
-    });
+            .into_boxed_slice(),
+    );
     let datasets = buffers
         .iter()
-        .map(|b| GtfsDataset {
-            raw: gtfs_io::Gtfs::from_buffers(b.to_slices()).unwrap(),
-            stops_tree: OnceLock::new(),
+        .map(|b| {
+            GtfsDataset::new(
+                gtfs_io::Gtfs::from_buffers(b.to_slices()).unwrap(),
+                walking_params,
+            )
         })
         .collect::<Vec<_>>();
 
@@ -159,6 +237,9 @@ pub async fn start_server(
             .service(crate::routes::api_basics::route_api_shutdown)
             .service(crate::routes::api_basics::route_api_metrics)
             .service(crate::routes::stations::route_api_stations)
+            .service(crate::routes::reachability::route_api_reachability)
+            .service(crate::routes::journeys::route_api_journeys)
+            .service(crate::routes::departures::route_api_departures)
             .service(crate::routes::frontend::route_frontend)
     })
     .listen(listener)?;
@@ -1,4 +1,8 @@
-use std::net::TcpListener;
+use std::{net::TcpListener, path::PathBuf};
+
+use gtfs_io::{GtfsBuffers, GtfsFilter};
+
+use crate::routing::WalkingParams;
 
 struct TestContext {
     handle: tokio::task::JoinHandle<()>,
@@ -12,16 +16,11 @@ impl Drop for TestContext {
     }
 }
 
+#[derive(Default)]
 struct SetupParams {
     allow_shutdown_from_frontend: bool,
-}
-
-impl Default for SetupParams {
-    fn default() -> Self {
-        Self {
-            allow_shutdown_from_frontend: false,
-        }
-    }
+    gtfs_datasets: Vec<PathBuf>,
+    walking_params: WalkingParams,
 }
 
 async fn setup_with_params(params: SetupParams) -> TestContext {
@@ -34,7 +33,8 @@ async fn setup_with_params(params: SetupParams) -> TestContext {
             listener,
             None,
             params.allow_shutdown_from_frontend,
-            vec![],
+            params.gtfs_datasets,
+            params.walking_params,
         )
         .await
         .expect("Failed to start server");
@@ -54,6 +54,30 @@ async fn setup() -> TestContext {
     setup_with_params(Default::default()).await
 }
 
+/// A small feed with two lines that are connected by a short walk.
+async fn setup_with_city_dataset() -> TestContext {
+    setup_with_params(SetupParams {
+        gtfs_datasets: vec![PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("testdata")
+            .join("gtfs_city")],
+        ..Default::default()
+    })
+    .await
+}
+
+/// The dummy feed from the gtfs_io tests, for the unit tests of this crate.
+pub fn load_gtfs_dummy_buffers(filter: &GtfsFilter) -> GtfsBuffers {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("..")
+        .join("libs")
+        .join("gtfs_io")
+        .join("tests")
+        .join("testdata")
+        .join("gtfs_dummy");
+    GtfsBuffers::from_dir(&path, filter)
+}
+
 impl TestContext {
     async fn get(&self, path: &str) -> reqwest::Response {
         self.client
@@ -117,3 +141,276 @@ async fn metrics_are_available() {
     let response = ctx.get("/api/metrics").await;
     assert_eq!(response.status(), reqwest::StatusCode::OK);
 }
+
+#[tokio::test]
+async fn reachability_needs_valid_params() {
+    let ctx = setup().await;
+    let response = ctx
+        .get("/api/reachability/10_550_335.json?lat=52.5&lon=13.4&date=2025-01-01&time=08:00")
+        .await;
+    assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+    let response = ctx
+        .get("/api/reachability/10_550_335.json?lat=52.5&lon=13.4&date=20250101")
+        .await;
+    assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn reachability_without_dataset_is_not_found() {
+    let ctx = setup().await;
+    let response = ctx
+        .get("/api/reachability/10_550_335.json?lat=52.5&lon=13.4&date=20250101&time=08:00")
+        .await;
+    assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn reachability_includes_all_datasets() {
+    let testdata = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("testdata");
+    let ctx = setup_with_params(SetupParams {
+        gtfs_datasets: vec![testdata.join("gtfs_city"), testdata.join("gtfs_suburb")],
+        ..Default::default()
+    })
+    .await;
+    let response = ctx
+        .get("/api/reachability/12_2199_1343.json?lat=52.5070&lon=13.3320&date=20250106&time=08:00")
+        .await;
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    let stops: Vec<serde_json::Value> = response.json().await.unwrap();
+    let minutes_at = |lat: f64| {
+        stops
+            .iter()
+            .find(|stop| (stop["lat"].as_f64().unwrap() - lat).abs() < 1e-4)
+            .map(|stop| stop["minutes"].as_u64().unwrap())
+    };
+    // Zoologischer Garten is in the first dataset, Tiergarten only in the second one.
+    assert_eq!(minutes_at(52.5070), Some(0));
+    assert_eq!(minutes_at(52.5145), Some(12));
+}
+
+async fn get_journeys(ctx: &TestContext, query: &str) -> Vec<serde_json::Value> {
+    let response = ctx.get(&format!("/api/journeys?{}", query)).await;
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    response.json().await.unwrap()
+}
+
+#[tokio::test]
+async fn journeys_between_stops() {
+    let ctx = setup_with_city_dataset().await;
+    let journeys = get_journeys(&ctx, "from=ZOO&to=PRENZLAUER&date=20250106&time=07:55").await;
+    assert_eq!(journeys.len(), 1);
+    let journey = &journeys[0];
+    assert_eq!(journey["departure"], "08:00:00");
+    assert_eq!(journey["arrival"], "08:30:00");
+    assert_eq!(journey["transfers"], 1);
+
+    let legs = journey["legs"].as_array().unwrap();
+    let leg_types: Vec<_> = legs
+        .iter()
+        .map(|leg| leg["type"].as_str().unwrap())
+        .collect();
+    assert_eq!(leg_types, ["transit", "walk", "transit"]);
+    assert_eq!(legs[0]["trip_id"], "S5_1");
+    assert_eq!(legs[0]["route_short_name"], "S5");
+    assert_eq!(legs[0]["route_color"], "#EB7405");
+    assert_eq!(legs[0]["headsign"], "Alexanderplatz");
+    assert_eq!(legs[0]["from"]["name"], "Zoologischer Garten");
+    assert_eq!(legs[0]["to"]["stop_id"], "ALEX");
+    let intermediate_stops = legs[0]["intermediate_stops"].as_array().unwrap();
+    assert_eq!(intermediate_stops.len(), 1);
+    assert_eq!(intermediate_stops[0]["stop_id"], "HBF_1");
+    assert_eq!(intermediate_stops[0]["departure"], "08:09:00");
+    assert_eq!(legs[1]["from"]["stop_id"], "ALEX");
+    assert_eq!(legs[1]["to"]["stop_id"], "ALEX_TRAM");
+    assert_eq!(legs[2]["trip_id"], "M4_1");
+    assert_eq!(legs[2]["route_color"], "#D82020");
+    assert_eq!(legs[2]["headsign"], "Prenzlauer Allee");
+
+    // Journeys from a station start at any of its platforms.
+    let journeys = get_journeys(&ctx, "from=HBF&to=ALEX&date=20250106&time=08:05").await;
+    assert_eq!(journeys.len(), 1);
+    assert_eq!(journeys[0]["legs"][0]["from"]["stop_id"], "HBF_1");
+    assert_eq!(journeys[0]["departure"], "08:09:00");
+
+    // The service doesn't run on weekends.
+    let journeys = get_journeys(&ctx, "from=ZOO&to=PRENZLAUER&date=20250105&time=07:55").await;
+    assert!(journeys.is_empty());
+}
+
+#[tokio::test]
+async fn journeys_between_positions() {
+    let ctx = setup_with_city_dataset().await;
+    // About 55m from ZOO and 60m from PRENZLAUER.
+    let journeys = get_journeys(
+        &ctx,
+        "from=52.5075,13.3320&to=52.5395,13.4245&date=20250106&time=07:55",
+    )
+    .await;
+    assert_eq!(journeys.len(), 1);
+    let legs = journeys[0]["legs"].as_array().unwrap();
+    let leg_types: Vec<_> = legs
+        .iter()
+        .map(|leg| leg["type"].as_str().unwrap())
+        .collect();
+    assert_eq!(leg_types, ["walk", "transit", "walk", "transit", "walk"]);
+    assert_eq!(legs[0]["from"]["stop_id"], serde_json::Value::Null);
+    assert_eq!(legs[0]["to"]["stop_id"], "ZOO");
+    assert_eq!(legs[0]["arrival"], "08:00:00");
+    assert_eq!(legs[4]["from"]["stop_id"], "PRENZLAUER");
+    assert_eq!(legs[4]["departure"], "08:30:00");
+    assert!(journeys[0]["departure"].as_str().unwrap() < "08:00:00");
+    assert!(journeys[0]["arrival"].as_str().unwrap() > "08:30:00");
+}
+
+#[tokio::test]
+async fn journeys_need_valid_params() {
+    let ctx = setup_with_city_dataset().await;
+    let response = ctx
+        .get("/api/journeys?from=ZOO&to=ALEX&date=2025-01-06&time=08:00")
+        .await;
+    assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+    let response = ctx
+        .get("/api/journeys?from=ZOO&to=ALEX&date=20250106&time=8am")
+        .await;
+    assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+    let response = ctx
+        .get("/api/journeys?from=ZOO&date=20250106&time=08:00")
+        .await;
+    assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+    let response = ctx
+        .get("/api/journeys?from=ZOO&to=UNKNOWN&date=20250106&time=08:00")
+        .await;
+    assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+    let response = ctx
+        .get("/api/journeys?from=0,0&to=ALEX&date=20250106&time=08:00")
+        .await;
+    assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn journeys_without_dataset_are_not_found() {
+    let ctx = setup().await;
+    let response = ctx
+        .get("/api/journeys?from=ZOO&to=ALEX&date=20250106&time=08:00")
+        .await;
+    assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+}
+
+async fn get_departures(ctx: &TestContext, path: &str) -> serde_json::Value {
+    let response = ctx.get(path).await;
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    response.json().await.unwrap()
+}
+
+#[tokio::test]
+async fn departures_include_child_platforms() {
+    let ctx = setup_with_city_dataset().await;
+    let board = get_departures(&ctx, "/api/stops/0/HBF/departures?date=20250106&time=08:00").await;
+    assert_eq!(board["stop_id"], "HBF");
+    assert_eq!(board["name"], "Hauptbahnhof");
+    let departures = board["departures"].as_array().unwrap();
+    let trip_ids: Vec<_> = departures
+        .iter()
+        .map(|d| d["trip_id"].as_str().unwrap())
+        .collect();
+    assert_eq!(trip_ids, ["S5_3", "S5_1", "S5_2", "S5_4"]);
+    let departure = &departures[0];
+    assert_eq!(departure["time"], "08:07:00");
+    assert_eq!(departure["stop_id"], "HBF_2");
+    assert_eq!(departure["platform_code"], "2");
+    assert_eq!(departure["route_name"], "S5");
+    assert_eq!(departure["route_color"], "#EB7405");
+    assert_eq!(departure["headsign"], "Zoologischer Garten");
+    assert_eq!(departure["pickup_type"], "regular");
+    assert_eq!(departures[1]["platform_code"], "1");
+    assert_eq!(departures[3]["time"], "24:14:00");
+    assert_eq!(departures[3]["pickup_type"], "must_coordinate_with_driver");
+
+    let board = get_departures(
+        &ctx,
+        "/api/stops/0/HBF/departures?date=20250106&time=08:00&limit=2",
+    )
+    .await;
+    assert_eq!(board["departures"].as_array().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn departures_skip_stop_times_without_pickup() {
+    let ctx = setup_with_city_dataset().await;
+    // The S5 trips towards Alexanderplatz end there.
+    let board = get_departures(
+        &ctx,
+        "/api/stops/0/ALEX/departures?date=20250106&time=07:00",
+    )
+    .await;
+    let departures = board["departures"].as_array().unwrap();
+    assert_eq!(departures.len(), 1);
+    assert_eq!(departures[0]["trip_id"], "S5_3");
+
+    // The bus ends at Mauerpark, even though its stop time allows pickup.
+    let board = get_departures(
+        &ctx,
+        "/api/stops/0/MAUERPARK/departures?date=20250106&time=07:00",
+    )
+    .await;
+    assert!(board["departures"].as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn departures_expand_frequency_based_trips() {
+    let ctx = setup_with_city_dataset().await;
+    let board = get_departures(
+        &ctx,
+        "/api/stops/0/PRENZLAUER/departures?date=20250106&time=09:10",
+    )
+    .await;
+    let departures = board["departures"].as_array().unwrap();
+    let times: Vec<_> = departures
+        .iter()
+        .map(|d| d["time"].as_str().unwrap())
+        .collect();
+    assert_eq!(times, ["09:20:00", "09:40:00"]);
+    assert_eq!(departures[0]["trip_id"], "B100_T");
+}
+
+#[tokio::test]
+async fn departures_after_midnight_use_previous_day() {
+    let ctx = setup_with_city_dataset().await;
+    // Tuesday morning, the trip belongs to the Monday service.
+    let board = get_departures(
+        &ctx,
+        "/api/stops/0/HBF_1/departures?date=20250107&time=00:00",
+    )
+    .await;
+    assert_eq!(board["departures"][0]["trip_id"], "S5_4");
+    assert_eq!(board["departures"][0]["time"], "00:14:00");
+
+    // Nothing runs on Saturday, so there is nothing on Sunday morning.
+    let board = get_departures(
+        &ctx,
+        "/api/stops/0/HBF_1/departures?date=20250112&time=00:00",
+    )
+    .await;
+    assert!(board["departures"].as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn departures_need_valid_params() {
+    let ctx = setup_with_city_dataset().await;
+    let response = ctx
+        .get("/api/stops/0/HBF/departures?date=2025-01-06&time=08:00")
+        .await;
+    assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+    let response = ctx.get("/api/stops/0/HBF/departures?date=20250106").await;
+    assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+    let response = ctx
+        .get("/api/stops/0/UNKNOWN/departures?date=20250106&time=08:00")
+        .await;
+    assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+    let response = ctx
+        .get("/api/stops/1/HBF/departures?date=20250106&time=08:00")
+        .await;
+    assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+}
@@ -6,3 +6,20 @@ pub fn bytes_to_human_string(bytes: Byte) -> String {
         .get_appropriate_unit(byte_unit::UnitType::Decimal)
         .to_string()
 }
+
+/// Parses a date in the GTFS format `YYYYMMDD`.
+pub fn parse_date(date: &str) -> anyhow::Result<gtfs_io::Date> {
+    use csvelo::ParseCsvField;
+    gtfs_io::Date::parse_csv_field(date.as_bytes())
+        .map_err(|_| anyhow::anyhow!("Expected date as YYYYMMDD, got {:?}", date))
+}
+
+/// The value in the given row of an optional GTFS column.
+pub fn cell<T>(column: Option<&Vec<T>>, row: Option<usize>) -> Option<&T> {
+    column?.get(row?)
+}
+
+/// Formats a GTFS color as `#RRGGBB`.
+pub fn format_color(color: &gtfs_io::Color) -> String {
+    format!("#{:02X}{:02X}{:02X}", color.r, color.g, color.b)
+}